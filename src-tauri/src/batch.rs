@@ -0,0 +1,241 @@
+use crate::download::DownloadProgress;
+use crate::errors::DownloadError;
+use serde::Serialize;
+
+/// Bookkeeping for a running batch, updated as its items finish
+#[derive(Debug, Clone)]
+pub struct BatchTracker {
+    /// Download ids of the queued items, used to find their active downloads
+    /// The same URL can be queued twice, so items aren't matched by URL
+    pub download_ids: Vec<String>,
+    pub completed: usize,
+    pub failed: usize,
+    /// Items whose file already existed; they add nothing to the byte counts
+    pub skipped: usize,
+    /// Size on disk of the items that already completed
+    pub finished_bytes: u64,
+}
+
+impl BatchTracker {
+    pub fn new(download_ids: Vec<String>) -> Self {
+        Self {
+            download_ids,
+            completed: 0,
+            failed: 0,
+            skipped: 0,
+            finished_bytes: 0,
+        }
+    }
+
+    /// Record a finished item; `bytes` is its file size when it completed
+    pub fn finish(&mut self, bytes: Option<u64>) {
+        match bytes {
+            Some(bytes) => {
+                self.completed += 1;
+                self.finished_bytes += bytes;
+            }
+            None => self.failed += 1,
+        }
+    }
+
+    /// Record an item skipped because its file already existed
+    pub fn skip(&mut self) {
+        self.skipped += 1;
+    }
+
+    /// Snapshot of the batch, given the latest progress of its running items
+    pub fn status(&self, batch_id: &str, active: &[DownloadProgress]) -> BatchStatus {
+        let total = self.download_ids.len();
+        let active_bytes: Vec<(u64, u64)> = active
+            .iter()
+            .filter_map(|p| Some((p.downloaded_bytes()?, p.total_bytes?)))
+            .collect();
+
+        let downloaded_bytes = if self.completed == 0 && active_bytes.is_empty() {
+            None
+        } else {
+            Some(self.finished_bytes + active_bytes.iter().map(|(done, _)| done).sum::<u64>())
+        };
+
+        BatchStatus {
+            batch_id: batch_id.to_string(),
+            total,
+            completed: self.completed,
+            failed: self.failed,
+            skipped: self.skipped,
+            downloaded_bytes,
+            eta_seconds: self.estimate_eta(total, active, &active_bytes),
+        }
+    }
+
+    /// Remaining bytes over the combined speed of the running items
+    /// Items that haven't started are assumed to be the average size seen so far
+    fn estimate_eta(
+        &self,
+        total: usize,
+        active: &[DownloadProgress],
+        active_bytes: &[(u64, u64)],
+    ) -> Option<u64> {
+        let speed: f64 = active.iter().filter_map(|p| p.speed_bytes).sum();
+        if speed <= 0.0 {
+            return None;
+        }
+
+        let known_items = self.completed + active_bytes.len();
+        if known_items == 0 {
+            return None;
+        }
+        let known_bytes = self.finished_bytes + active_bytes.iter().map(|(_, t)| t).sum::<u64>();
+        let average_size = known_bytes as f64 / known_items as f64;
+
+        let waiting =
+            total.saturating_sub(self.completed + self.failed + self.skipped + active.len());
+        let remaining = active_bytes
+            .iter()
+            .map(|(done, total)| total.saturating_sub(*done) as f64)
+            .sum::<f64>()
+            + waiting as f64 * average_size;
+
+        Some((remaining / speed).ceil() as u64)
+    }
+}
+
+/// A batch item that did not download, with the reason shown in the summary
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchFailure {
+    pub url: String,
+    pub reason: String,
+}
+
+/// Payload of the "batch-complete" event
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchSummary {
+    pub batch_id: String,
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Cancelled items and files skipped because they already existed
+    pub skipped: usize,
+    pub failures: Vec<BatchFailure>,
+}
+
+impl BatchSummary {
+    /// Summary of a playlist downloaded from a single URL, keyed by its download id
+    /// yt-dlp stops at the first failing item, so the items after it count as skipped;
+    /// a cancel skips the item that was downloading as well
+    pub fn for_playlist(
+        batch_id: &str,
+        item_url: &str,
+        (current, total): (u32, u32),
+        outcome: Result<(), &DownloadError>,
+    ) -> Self {
+        let total = total as usize;
+        let current = (current as usize).min(total).max(1);
+        let mut summary = Self {
+            batch_id: batch_id.to_string(),
+            total,
+            ..Default::default()
+        };
+        match outcome {
+            Ok(()) => summary.succeeded = total,
+            Err(DownloadError::Cancelled) => {
+                summary.succeeded = current - 1;
+                summary.skipped = total - summary.succeeded;
+            }
+            Err(error) => {
+                summary.succeeded = current - 1;
+                summary.failed = 1;
+                summary.skipped = total.saturating_sub(current);
+                summary.failures.push(BatchFailure {
+                    url: item_url.to_string(),
+                    reason: error.to_string(),
+                });
+            }
+        }
+        summary
+    }
+}
+
+/// Overall progress of a batch, returned by get_batch_status
+/// Byte and ETA fields are null until a download has reported sizes and speed
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchStatus {
+    pub batch_id: String,
+    pub total: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub downloaded_bytes: Option<u64>,
+    pub eta_seconds: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(percent: f32, total_bytes: u64, speed_bytes: f64) -> DownloadProgress {
+        DownloadProgress {
+            id: String::new(),
+            percent,
+            speed: String::new(),
+            eta: String::new(),
+            frag_current: None,
+            frag_total: None,
+            total_bytes: Some(total_bytes),
+            speed_bytes: Some(speed_bytes),
+        }
+    }
+
+    #[test]
+    fn test_batch_status() {
+        let mut tracker = BatchTracker::new(vec!["a".into(), "b".into(), "c".into()]);
+
+        // Nothing has reported sizes or speed yet
+        let empty = tracker.status("batch", &[]);
+        assert_eq!(empty.total, 3);
+        assert_eq!(empty.downloaded_bytes, None);
+        assert_eq!(empty.eta_seconds, None);
+
+        tracker.finish(Some(1000));
+        let status = tracker.status("batch", &[progress(50.0, 1000, 100.0)]);
+        assert_eq!(status.completed, 1);
+        assert_eq!(status.downloaded_bytes, Some(1500));
+        // 500 left on the active item plus one waiting item of ~1000 at 100 B/s
+        assert_eq!(status.eta_seconds, Some(15));
+
+        tracker.finish(None);
+        let status = tracker.status("batch", &[progress(50.0, 1000, 100.0)]);
+        assert_eq!(status.failed, 1);
+        assert_eq!(status.eta_seconds, Some(5));
+
+        // A skipped file neither adds bytes nor leaves an item waiting
+        let mut tracker = BatchTracker::new(vec!["a".into(), "b".into()]);
+        tracker.skip();
+        let status = tracker.status("batch", &[progress(50.0, 1000, 100.0)]);
+        assert_eq!(status.skipped, 1);
+        assert_eq!(status.downloaded_bytes, Some(500));
+        assert_eq!(status.eta_seconds, Some(5));
+    }
+
+    #[test]
+    fn test_playlist_summary() {
+        let done = BatchSummary::for_playlist("id", "u", (5, 5), Ok(()));
+        assert_eq!((done.succeeded, done.failed, done.skipped), (5, 0, 0));
+
+        let error = DownloadError::ContentUnavailable("removed".into());
+        let failed = BatchSummary::for_playlist("id", "item", (3, 5), Err(&error));
+        assert_eq!((failed.succeeded, failed.failed, failed.skipped), (2, 1, 2));
+        assert_eq!(failed.failures[0].url, "item");
+
+        let cancelled =
+            BatchSummary::for_playlist("id", "u", (3, 5), Err(&DownloadError::Cancelled));
+        assert_eq!(
+            (cancelled.succeeded, cancelled.failed, cancelled.skipped),
+            (2, 0, 3)
+        );
+        assert!(cancelled.failures.is_empty());
+    }
+}
@@ -1,11 +1,20 @@
+use crate::download::AudioFormat;
+use crate::network::{http_client, idle_timeout, record_github_ratelimit};
+use crate::ytdlp_updater::YTDLP_UPDATE_LOCK;
 use hex;
 use reqwest;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_shell::process::Command;
+use tokio::sync::Notify;
 use tracing::{error, info, warn};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -14,6 +23,10 @@ pub struct BinaryInfo {
     pub version: String,
     pub last_check: u64,
     pub path: String,
+    /// Where the binary was downloaded from, e.g. which ffmpeg fallback source
+    /// Missing from info files written before this was recorded
+    #[serde(default)]
+    pub source: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,12 +47,278 @@ pub struct DownloadProgress {
     pub binary: String,
     pub progress: f64,
     pub status: String,
+    /// Bytes per second, only reported while the file itself is transferring
+    pub speed_bps: Option<f64>,
+    /// Seconds left in the transfer; None when the server sent no Content-Length
+    pub eta_seconds: Option<u64>,
+    /// Why the binary failed; set only on the last event for a failed download
+    pub error: Option<String>,
+}
+
+impl DownloadProgress {
+    /// Completion and failure are final states the UI must always see
+    fn is_terminal(&self) -> bool {
+        self.progress >= 100.0 || self.error.is_some()
+    }
+}
+
+/// Result of running a tool with its version flag
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolDiagnostic {
+    pub name: String,
+    pub path: String,
+    pub version: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Emitted when a downloaded binary fails SHA-256 verification
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecksumFailure {
+    pub binary: String,
+    pub message: String,
+}
+
+/// Audio encoders the installed ffmpeg build provides
+/// Static builds sometimes omit libmp3lame or libopus, which breaks those formats
+#[derive(Debug, Serialize, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FfmpegEncoders {
+    pub mp3: bool,
+    pub aac: bool,
+    pub opus: bool,
+    pub flac: bool,
+}
+
+impl FfmpegEncoders {
+    /// Parse the encoder table printed by `ffmpeg -encoders`
+    pub fn parse(output: &str) -> Self {
+        // Rows look like " A....D libmp3lame           libmp3lame MP3 (MPEG audio layer 3)"
+        let names: Vec<&str> = output
+            .lines()
+            .filter_map(|line| {
+                let mut columns = line.split_whitespace();
+                let flags = columns.next()?;
+                let name = columns.next()?;
+                if flags.len() == 6 && flags.starts_with('A') {
+                    Some(name)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        let has = |candidates: &[&str]| names.iter().any(|name| candidates.contains(name));
+
+        Self {
+            mp3: has(&["libmp3lame"]),
+            aac: has(&["aac", "libfdk_aac"]),
+            opus: has(&["libopus", "opus"]),
+            flac: has(&["flac"]),
+        }
+    }
+
+    /// Whether ffmpeg can produce an audio format; WAV is plain PCM and always available
+    pub fn supports(&self, format: AudioFormat) -> bool {
+        match format {
+            AudioFormat::Mp3 => self.mp3,
+            AudioFormat::M4a => self.aac,
+            AudioFormat::Opus => self.opus,
+            AudioFormat::Flac => self.flac,
+            AudioFormat::Wav => true,
+        }
+    }
+}
+
+/// Duration and video size of a finished download, read with ffprobe
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaProbe {
+    /// Length in seconds
+    pub duration: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+impl MediaProbe {
+    /// Parse `ffprobe -show_entries format=duration:stream=width,height -of json` output
+    pub fn parse(output: &str) -> Option<Self> {
+        let json: serde_json::Value = serde_json::from_str(output).ok()?;
+
+        let duration = json["format"]["duration"]
+            .as_str()
+            .and_then(|duration| duration.parse::<f64>().ok());
+
+        // Audio streams and cover art come without a size; take the first real video stream
+        let video_stream = json["streams"].as_array().and_then(|streams| {
+            streams
+                .iter()
+                .find(|stream| stream["width"].as_u64().is_some())
+        });
+        let dimension = |key: &str| {
+            video_stream
+                .and_then(|stream| stream[key].as_u64())
+                .map(|value| value as u32)
+        };
+
+        Some(Self {
+            duration,
+            width: dimension("width"),
+            height: dimension("height"),
+        })
+    }
+}
+
+/// Seconds of output written so far, from a `-progress` line like "out_time_us=1500000"
+fn parse_progress_out_time(line: &str) -> Option<f64> {
+    let micros = line
+        .trim()
+        .strip_prefix("out_time_us=")?
+        .parse::<u64>()
+        .ok()?;
+    Some(micros as f64 / 1_000_000.0)
+}
+
+/// Write a binary beside its final path, then rename it into place
+/// Each writer uses its own temp file, so a crash or a concurrent update can
+/// never leave a partially written executable at `path`
+pub fn write_binary_atomically(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let mut temp_name = path.as_os_str().to_owned();
+    temp_name.push(format!(".{}.tmp", uuid::Uuid::new_v4()));
+    let temp_path = PathBuf::from(temp_name);
+
+    fs::write(&temp_path, bytes)?;
+    fs::rename(&temp_path, path).map_err(|e| {
+        fs::remove_file(&temp_path).ok();
+        e
+    })
+}
+
+/// Binaries ripVID downloads at runtime, in setup order
+pub const REQUIRED_BINARIES: [&str; 3] = ["yt-dlp", "ffmpeg", "ffprobe"];
+
+/// Failure reason recorded for binaries whose download was cancelled
+pub const SETUP_CANCELLED: &str = "Setup cancelled";
+
+/// Part of a binary's progress bar covered by the transfer, between the
+/// "Downloading" and "Verifying"/"Saving" steps
+const TRANSFER_PROGRESS_START: f64 = 25.0;
+const TRANSFER_PROGRESS_END: f64 = 75.0;
+
+/// Minimum gap between progress events for one binary (about 10 per second), so
+/// three parallel downloads don't flood the setup screen
+const BINARY_PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Drops progress events that arrive too soon after the last one for the same binary
+/// A new status, 100% and errors always go through, so no step is ever skipped;
+/// only repeated updates of the same step (the byte transfer) are coalesced
+#[derive(Default)]
+struct ProgressThrottle {
+    /// Time and status of the last event emitted for each binary
+    last_emitted: HashMap<String, (Instant, String)>,
+}
+
+impl ProgressThrottle {
+    fn admit(&mut self, event: &DownloadProgress, now: Instant) -> bool {
+        let due = match self.last_emitted.get(&event.binary) {
+            Some((at, status)) => {
+                event.is_terminal()
+                    || *status != event.status
+                    || now.duration_since(*at) >= BINARY_PROGRESS_INTERVAL
+            }
+            None => true,
+        };
+        if due {
+            self.last_emitted
+                .insert(event.binary.clone(), (now, event.status.clone()));
+        }
+        due
+    }
+}
+
+/// Average speed and remaining seconds of a transfer
+/// ETA is None when the total size isn't known
+fn transfer_rate(
+    downloaded: u64,
+    total: Option<u64>,
+    elapsed: Duration,
+) -> (Option<f64>, Option<u64>) {
+    let seconds = elapsed.as_secs_f64();
+    if downloaded == 0 || seconds <= 0.0 {
+        return (None, None);
+    }
+    let speed = downloaded as f64 / seconds;
+    let eta = total.map(|total| (total.saturating_sub(downloaded) as f64 / speed).ceil() as u64);
+    (Some(speed), eta)
+}
+
+/// A binary that could not be downloaded during setup
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BinaryFailure {
+    pub name: String,
+    pub reason: String,
+}
+
+/// Outcome of the startup binary check, shown on the setup screen
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SetupStatus {
+    pub ready: bool,
+    /// Binaries are still being checked or downloaded; downloads wait for this
+    pub in_progress: bool,
+    pub failures: Vec<BinaryFailure>,
+}
+
+impl SetupStatus {
+    /// Record the outcome of retrying a single binary
+    pub fn record_retry(&mut self, name: &str, result: Result<(), String>) {
+        self.failures.retain(|failure| failure.name != name);
+
+        if let Err(reason) = result {
+            self.failures.push(BinaryFailure {
+                name: name.to_string(),
+                reason,
+            });
+        }
+
+        self.ready = self.failures.is_empty();
+    }
+
+    /// Status while setup is running, shown as "Setting up…"
+    pub fn in_progress() -> Self {
+        Self {
+            in_progress: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn from_result(result: Result<(), Vec<BinaryFailure>>) -> Self {
+        match result {
+            Ok(()) => Self {
+                ready: true,
+                in_progress: false,
+                failures: Vec::new(),
+            },
+            Err(failures) => Self {
+                ready: false,
+                in_progress: false,
+                failures,
+            },
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct BinaryManager {
     app_handle: AppHandle,
     data_dir: PathBuf,
+    /// Shared by clones, so cancelling stops all parallel downloads
+    cancelled: Arc<AtomicBool>,
+    /// Wakes transfers waiting on the network when cancel_setup is called
+    cancel_notify: Arc<Notify>,
+    /// Shared by clones, so each binary is throttled however many tasks report on it
+    progress_throttle: Arc<Mutex<ProgressThrottle>>,
 }
 
 impl BinaryManager {
@@ -53,35 +332,59 @@ impl BinaryManager {
         Self {
             app_handle,
             data_dir,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            cancel_notify: Arc::new(Notify::new()),
+            progress_throttle: Arc::new(Mutex::new(ProgressThrottle::default())),
         }
     }
 
     /// Ensure all binaries are present and up-to-date
     /// This is called on app startup
     pub async fn ensure_all_binaries(&self) -> Result<(), String> {
+        self.ensure_all_binaries_detailed()
+            .await
+            .map_err(|failures| {
+                let errors: Vec<String> = failures
+                    .iter()
+                    .map(|failure| format!("{}: {}", failure.name, failure.reason))
+                    .collect();
+                format!("Failed to download: {}", errors.join(", "))
+            })
+    }
+
+    /// Same as ensure_all_binaries, but reports each binary that failed separately
+    pub async fn ensure_all_binaries_detailed(&self) -> Result<(), Vec<BinaryFailure>> {
         info!("Ensuring all required binaries are present...");
 
+        let setup_failure = |reason: String| {
+            vec![BinaryFailure {
+                name: "setup".to_string(),
+                reason,
+            }]
+        };
+
         // Create data directory
         fs::create_dir_all(&self.data_dir)
-            .map_err(|e| format!("Failed to create binaries directory: {}", e))?;
+            .map_err(|e| setup_failure(format!("Failed to create binaries directory: {}", e)))?;
 
         // Check each binary
         let mut missing = Vec::new();
 
-        if !self.is_binary_present("yt-dlp")? {
+        if !self.is_binary_present("yt-dlp").map_err(setup_failure)? {
             missing.push("yt-dlp");
         }
-        if !self.is_binary_present("ffmpeg")? {
+        if !self.is_binary_present("ffmpeg").map_err(setup_failure)? {
             missing.push("ffmpeg");
         }
-        if !self.is_binary_present("ffprobe")? {
+        if !self.is_binary_present("ffprobe").map_err(setup_failure)? {
             missing.push("ffprobe");
         }
 
         // If any are missing, download them (first run)
         if !missing.is_empty() {
             info!("First run detected. Downloading: {:?}", missing);
-            self.emit_progress("setup", 0.0, "Downloading required tools...")?;
+            self.emit_progress("setup", 0.0, "Downloading required tools...")
+                .map_err(setup_failure)?;
 
             // Download in parallel for speed
             let manager1 = self.clone_for_background();
@@ -95,29 +398,37 @@ impl BinaryManager {
             ];
 
             let mut errors = Vec::new();
-            const BINARY_NAMES: [&str; 3] = ["yt-dlp", "ffmpeg", "ffprobe"];
             for (i, handle) in handles.into_iter().enumerate() {
-                let binary_name = BINARY_NAMES[i];
+                let binary_name = REQUIRED_BINARIES[i];
                 match handle.await {
                     Ok(Ok(())) => {
                         info!("{} downloaded successfully", binary_name);
                     }
                     Ok(Err(e)) => {
                         error!("{} download failed: {}", binary_name, e);
-                        errors.push(format!("{}: {}", binary_name, e));
+                        self.emit_failure(binary_name, &e);
+                        errors.push(BinaryFailure {
+                            name: binary_name.to_string(),
+                            reason: e,
+                        });
                     }
                     Err(e) => {
                         error!("{} task panicked: {}", binary_name, e);
-                        errors.push(format!("{}: task failed", binary_name));
+                        self.emit_failure(binary_name, "task failed");
+                        errors.push(BinaryFailure {
+                            name: binary_name.to_string(),
+                            reason: "task failed".to_string(),
+                        });
                     }
                 }
             }
 
             if !errors.is_empty() {
-                return Err(format!("Failed to download: {}", errors.join(", ")));
+                return Err(errors);
             }
 
-            self.emit_progress("setup", 100.0, "All tools ready!")?;
+            self.emit_progress("setup", 100.0, "All tools ready!")
+                .map_err(setup_failure)?;
         }
 
         // Check for updates in background (non-blocking)
@@ -131,6 +442,99 @@ impl BinaryManager {
         Ok(())
     }
 
+    /// Delete the binaries directory, including the *-info.json and last-check.json files
+    /// The next ensure_all_binaries call downloads everything from scratch
+    pub fn remove_all_binaries(&self) -> Result<(), String> {
+        if !self.data_dir.exists() {
+            return Ok(());
+        }
+
+        info!("Removing binaries directory: {:?}", self.data_dir);
+        fs::remove_dir_all(&self.data_dir)
+            .map_err(|e| format!("Failed to remove binaries directory: {}", e))
+    }
+
+    /// Stop any binary downloads in flight; they fail with SETUP_CANCELLED
+    /// Transfers waiting on a stalled server are woken rather than left until the next
+    /// chunk. Nothing is written until a download completes, so no partial binaries
+    /// are left behind
+    pub fn cancel_setup(&self) {
+        info!("Cancelling binary setup");
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.cancel_notify.notify_waiters();
+    }
+
+    /// Forget an earlier cancel_setup before a new setup run starts
+    /// Done by the caller up front rather than inside the run, so a cancel sent right
+    /// after starting isn't wiped out
+    pub fn reset_cancel(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Await one network step of a binary download (send, chunk or body)
+    /// Fails with SETUP_CANCELLED as soon as cancel_setup is called, and with an error
+    /// when the server sends nothing for IDLE_TIMEOUT
+    async fn transfer_step<T>(
+        &self,
+        step: impl Future<Output = reqwest::Result<T>>,
+    ) -> Result<T, String> {
+        // Registered before the flag is checked, so a cancel in between still wakes us
+        let cancelled = self.cancel_notify.notified();
+        tokio::pin!(cancelled);
+        cancelled.as_mut().enable();
+        if self.is_cancelled() {
+            return Err(SETUP_CANCELLED.to_string());
+        }
+
+        tokio::select! {
+            _ = cancelled => Err(SETUP_CANCELLED.to_string()),
+            result = idle_timeout(step) => result,
+        }
+    }
+
+    /// Read a response body chunk by chunk, stopping as soon as setup is cancelled
+    /// Reports progress, speed and ETA for `binary` as the bytes arrive
+    async fn read_body(
+        &self,
+        binary: &str,
+        mut response: reqwest::Response,
+    ) -> Result<Vec<u8>, String> {
+        let total = response.content_length().filter(|length| *length > 0);
+        let started = Instant::now();
+        let mut body = Vec::new();
+
+        loop {
+            let chunk = match self.transfer_step(response.chunk()).await {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => break,
+                Err(e) if e == SETUP_CANCELLED => return Err(e),
+                Err(e) => return Err(format!("Failed to read bytes: {}", e)),
+            };
+            body.extend_from_slice(&chunk);
+
+            let downloaded = body.len() as u64;
+            let (speed_bps, eta_seconds) = transfer_rate(downloaded, total, started.elapsed());
+            // Without a Content-Length the bar holds still while speed keeps updating
+            let fraction = total.map_or(0.0, |total| (downloaded as f64 / total as f64).min(1.0));
+            let event = DownloadProgress {
+                binary: binary.to_string(),
+                progress: TRANSFER_PROGRESS_START
+                    + (TRANSFER_PROGRESS_END - TRANSFER_PROGRESS_START) * fraction,
+                status: format!("Downloading {}...", binary),
+                speed_bps,
+                eta_seconds,
+                error: None,
+            };
+            self.emit_throttled(event).ok();
+        }
+
+        Ok(body)
+    }
+
     /// Check for updates in the background (once per day)
     async fn check_updates_background(&self) -> Result<(), String> {
         if !self.should_check_updates()? {
@@ -187,6 +591,169 @@ impl BinaryManager {
         Ok(path.exists())
     }
 
+    /// Check that ffmpeg can be used for merging/conversion
+    /// Prefers the runtime-downloaded copy, falling back to one on the system PATH;
+    /// async so probing the system copy doesn't block a runtime thread
+    pub async fn is_ffmpeg_available(&self) -> bool {
+        if self.is_binary_present("ffmpeg").unwrap_or(false) {
+            return true;
+        }
+
+        tokio::process::Command::new("ffmpeg")
+            .arg("-version")
+            .output()
+            .await
+            .map(|output| output.status.success())
+            .unwrap_or(false)
+    }
+
+    /// List the audio encoders compiled into ffmpeg
+    /// Uses the same binary as is_ffmpeg_available: the runtime copy, else the system one
+    pub fn ffmpeg_encoders(&self) -> Result<FfmpegEncoders, String> {
+        let path = match self.get_binary_path("ffmpeg") {
+            Ok(path) if path.exists() => path,
+            _ => PathBuf::from("ffmpeg"),
+        };
+
+        let output = std::process::Command::new(&path)
+            .args(["-hide_banner", "-encoders"])
+            .output()
+            .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "ffmpeg -encoders exited with code {:?}",
+                output.status.code()
+            ));
+        }
+
+        let encoders = FfmpegEncoders::parse(&String::from_utf8_lossy(&output.stdout));
+        info!("ffmpeg audio encoders: {:?}", encoders);
+        Ok(encoders)
+    }
+
+    /// Read duration and resolution from a media file with ffprobe
+    /// Uses the runtime copy, else the system one, like ffmpeg_encoders
+    pub fn probe_media(&self, file: &Path) -> Result<MediaProbe, String> {
+        let path = match self.get_binary_path("ffprobe") {
+            Ok(path) if path.exists() => path,
+            _ => PathBuf::from("ffprobe"),
+        };
+
+        let output = std::process::Command::new(&path)
+            .args([
+                "-v",
+                "error",
+                "-show_entries",
+                "format=duration:stream=width,height",
+                "-of",
+                "json",
+            ])
+            .arg(file)
+            .output()
+            .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "ffprobe exited with code {:?}",
+                output.status.code()
+            ));
+        }
+
+        MediaProbe::parse(&String::from_utf8_lossy(&output.stdout))
+            .ok_or_else(|| "Could not parse ffprobe output".to_string())
+    }
+
+    /// Copy every stream of `input` into a fresh container at `output`, without re-encoding
+    /// Rebuilds the index of files whose merge was interrupted; MP4-family outputs
+    /// also get the moov atom moved to the front. `on_progress` receives 0-100 when
+    /// the duration can still be read from the damaged file
+    pub fn remux(
+        &self,
+        input: &Path,
+        output: &Path,
+        mut on_progress: impl FnMut(f64),
+    ) -> Result<(), String> {
+        use std::io::{BufRead, BufReader};
+        use std::process::Stdio;
+
+        let path = match self.get_binary_path("ffmpeg") {
+            Ok(path) if path.exists() => path,
+            _ => PathBuf::from("ffmpeg"),
+        };
+        let duration = self
+            .probe_media(input)
+            .ok()
+            .and_then(|probe| probe.duration)
+            .filter(|duration| *duration > 0.0);
+
+        let mut command = std::process::Command::new(&path);
+        command
+            .args(["-hide_banner", "-v", "error", "-y", "-i"])
+            .arg(input)
+            .args(["-map", "0", "-c", "copy"]);
+        let extension = output
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if matches!(extension.as_str(), "mp4" | "m4a" | "mov") {
+            command.args(["-movflags", "+faststart"]);
+        }
+        let mut child = command
+            .args(["-progress", "pipe:1", "-nostats"])
+            .arg(output)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+
+        if let Some(stdout) = child.stdout.take() {
+            let lines = BufReader::new(stdout).lines().map_while(Result::ok);
+            for written in lines.filter_map(|line| parse_progress_out_time(&line)) {
+                if let Some(duration) = duration {
+                    on_progress((written / duration * 100.0).clamp(0.0, 100.0));
+                }
+            }
+        }
+
+        let result = child
+            .wait_with_output()
+            .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+        if !result.status.success() {
+            let stderr = String::from_utf8_lossy(&result.stderr);
+            return Err(format!(
+                "ffmpeg exited with code {:?}: {}",
+                result.status.code(),
+                stderr.trim()
+            ));
+        }
+
+        on_progress(100.0);
+        Ok(())
+    }
+
+    /// Find aria2c for use as yt-dlp's external downloader
+    /// Returns a runtime-downloaded copy if present, else "aria2c" when it is on the PATH
+    /// Async like is_ffmpeg_available, so the PATH probe doesn't block a runtime worker
+    pub async fn aria2c_location(&self) -> Option<String> {
+        if let Ok(path) = self.get_binary_path("aria2c") {
+            if path.exists() {
+                return Some(path.to_string_lossy().to_string());
+            }
+        }
+
+        tokio::process::Command::new("aria2c")
+            .arg("--version")
+            .output()
+            .await
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|_| "aria2c".to_string())
+    }
+
+    /// Directory the runtime binaries are stored in
+    pub fn binaries_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
     /// Get the path for a binary
     pub fn get_binary_path(&self, name: &str) -> Result<PathBuf, String> {
         let filename = if cfg!(windows) {
@@ -198,22 +765,49 @@ impl BinaryManager {
         Ok(self.data_dir.join(filename))
     }
 
+    /// Download a single binary by name, e.g. to retry one that failed during setup
+    pub async fn download_binary(&self, name: &str) -> Result<(), String> {
+        fs::create_dir_all(&self.data_dir)
+            .map_err(|e| format!("Failed to create binaries directory: {}", e))?;
+
+        let result = match name {
+            "yt-dlp" => self.download_ytdlp().await,
+            "ffmpeg" => self.download_ffmpeg().await,
+            "ffprobe" => self.download_ffprobe().await,
+            _ => {
+                return Err(format!(
+                    "Unknown binary '{}'. Expected one of: {}",
+                    name,
+                    REQUIRED_BINARIES.join(", ")
+                ))
+            }
+        };
+
+        if let Err(e) = &result {
+            self.emit_failure(name, e);
+        }
+        result
+    }
+
     /// Download yt-dlp
     async fn download_ytdlp(&self) -> Result<(), String> {
+        let _update = YTDLP_UPDATE_LOCK.lock().await;
         self.emit_progress("yt-dlp", 0.0, "Downloading yt-dlp...")?;
 
-        let client = reqwest::Client::new();
+        let client = http_client();
 
         // Get latest release
-        let response = client
+        let request = client
             .get("https://api.github.com/repos/yt-dlp/yt-dlp/releases/latest")
-            .header("User-Agent", "ripVID")
-            .send()
+            .header("User-Agent", "ripVID");
+        let response = self
+            .transfer_step(request.send())
             .await
             .map_err(|e| format!("Failed to fetch yt-dlp release: {}", e))?;
+        record_github_ratelimit(response.headers());
 
-        let release: GitHubRelease = response
-            .json()
+        let release: GitHubRelease = self
+            .transfer_step(response.json())
             .await
             .map_err(|e| format!("Failed to parse release: {}", e))?;
 
@@ -228,16 +822,12 @@ impl BinaryManager {
         self.emit_progress("yt-dlp", 25.0, "Downloading binary...")?;
 
         // Download binary
-        let response = client
-            .get(&asset.browser_download_url)
-            .send()
+        let response = self
+            .transfer_step(client.get(&asset.browser_download_url).send())
             .await
             .map_err(|e| format!("Download failed: {}", e))?;
 
-        let bytes = response
-            .bytes()
-            .await
-            .map_err(|e| format!("Failed to read bytes: {}", e))?;
+        let bytes = self.read_body("yt-dlp", response).await?;
 
         self.emit_progress("yt-dlp", 75.0, "Verifying checksum...")?;
 
@@ -254,15 +844,17 @@ impl BinaryManager {
         let actual_checksum = self.calculate_sha256(&bytes);
 
         if actual_checksum.to_lowercase() != expected_checksum.to_lowercase() {
-            return Err(format!(
-                "Checksum mismatch! Expected: {}, Got: {}",
+            // Hashes go to the log only; the user gets guidance instead
+            error!(
+                "yt-dlp checksum mismatch! Expected: {}, Got: {}",
                 expected_checksum, actual_checksum
-            ));
+            );
+            return Err(self.emit_checksum_failed("yt-dlp"));
         }
 
         // Save binary
         let path = self.get_binary_path("yt-dlp")?;
-        fs::write(&path, bytes).map_err(|e| format!("Failed to save: {}", e))?;
+        write_binary_atomically(&path, &bytes).map_err(|e| format!("Failed to save: {}", e))?;
 
         // Make executable on Unix
         #[cfg(unix)]
@@ -274,7 +866,7 @@ impl BinaryManager {
         }
 
         // Save version info
-        self.save_binary_info("yt-dlp", &release.tag_name, &path)?;
+        self.save_binary_info("yt-dlp", &release.tag_name, "GitHub releases", &path)?;
 
         self.emit_progress("yt-dlp", 100.0, "Ready!")?;
 
@@ -287,7 +879,7 @@ impl BinaryManager {
     async fn download_ffmpeg(&self) -> Result<(), String> {
         self.emit_progress("ffmpeg", 0.0, "Downloading ffmpeg...")?;
 
-        let client = reqwest::Client::new();
+        let client = http_client();
 
         // Try multiple sources for reliability
         let sources = self.get_ffmpeg_sources();
@@ -301,6 +893,7 @@ impl BinaryManager {
                     info!("ffmpeg downloaded successfully from {}", source.name);
                     return Ok(());
                 }
+                Err(e) if e == SETUP_CANCELLED => return Err(e),
                 Err(e) => {
                     warn!("Failed to download from {}: {}", source.name, e);
                     if i < sources.len() - 1 {
@@ -317,7 +910,7 @@ impl BinaryManager {
     async fn download_ffprobe(&self) -> Result<(), String> {
         self.emit_progress("ffprobe", 0.0, "Downloading ffprobe...")?;
 
-        let client = reqwest::Client::new();
+        let client = http_client();
 
         let sources = self.get_ffprobe_sources();
 
@@ -330,6 +923,7 @@ impl BinaryManager {
                     info!("ffprobe downloaded successfully from {}", source.name);
                     return Ok(());
                 }
+                Err(e) if e == SETUP_CANCELLED => return Err(e),
                 Err(e) => {
                     warn!("Failed to download from {}: {}", source.name, e);
                     if i < sources.len() - 1 {
@@ -350,17 +944,13 @@ impl BinaryManager {
     ) -> Result<(), String> {
         self.emit_progress(binary_name, 25.0, &format!("Downloading from {}...", source.name))?;
 
-        let response = client
-            .get(&source.url)
-            .send()
-            .await
-            .map_err(|e| e.to_string())?;
+        let response = self.transfer_step(client.get(&source.url).send()).await?;
 
         if !response.status().is_success() {
             return Err(format!("HTTP {}", response.status()));
         }
 
-        let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+        let bytes = self.read_body(binary_name, response).await?;
 
         self.emit_progress(binary_name, 75.0, "Saving binary...")?;
 
@@ -368,12 +958,13 @@ impl BinaryManager {
         let final_bytes = if source.is_zip {
             self.extract_from_zip(&bytes, binary_name)?
         } else {
-            bytes.to_vec()
+            bytes
         };
 
         // Save binary
         let path = self.get_binary_path(binary_name)?;
-        fs::write(&path, final_bytes).map_err(|e| format!("Failed to save: {}", e))?;
+        write_binary_atomically(&path, &final_bytes)
+            .map_err(|e| format!("Failed to save: {}", e))?;
 
         // Make executable on Unix
         #[cfg(unix)]
@@ -385,7 +976,7 @@ impl BinaryManager {
         }
 
         // Save version info
-        self.save_binary_info(binary_name, &source.version, &path)?;
+        self.save_binary_info(binary_name, &source.version, source.name, &path)?;
 
         Ok(())
     }
@@ -539,7 +1130,13 @@ impl BinaryManager {
         Ok(())
     }
 
-    fn save_binary_info(&self, name: &str, version: &str, path: &PathBuf) -> Result<(), String> {
+    fn save_binary_info(
+        &self,
+        name: &str,
+        version: &str,
+        source: &str,
+        path: &PathBuf,
+    ) -> Result<(), String> {
         let info = BinaryInfo {
             name: name.to_string(),
             version: version.to_string(),
@@ -548,6 +1145,7 @@ impl BinaryManager {
                 .unwrap()
                 .as_secs(),
             path: path.to_string_lossy().to_string(),
+            source: Some(source.to_string()),
         };
 
         let info_file = self.data_dir.join(format!("{}-info.json", name));
@@ -560,6 +1158,19 @@ impl BinaryManager {
         Ok(())
     }
 
+    /// Read the recorded info for each installed binary
+    /// Binaries without a readable info file are left out
+    pub fn binary_versions(&self) -> Vec<BinaryInfo> {
+        REQUIRED_BINARIES
+            .iter()
+            .filter_map(|name| {
+                let info_file = self.data_dir.join(format!("{}-info.json", name));
+                let content = fs::read_to_string(info_file).ok()?;
+                serde_json::from_str(&content).ok()
+            })
+            .collect()
+    }
+
     fn calculate_sha256(&self, data: &[u8]) -> String {
         let mut hasher = Sha256::new();
         hasher.update(data);
@@ -573,10 +1184,9 @@ impl BinaryManager {
         checksums_url: &str,
         asset_name: &str,
     ) -> Result<String, String> {
-        let response = client
-            .get(checksums_url)
-            .header("User-Agent", "ripVID")
-            .send()
+        let request = client.get(checksums_url).header("User-Agent", "ripVID");
+        let response = self
+            .transfer_step(request.send())
             .await
             .map_err(|e| format!("Failed to download checksum file: {}", e))?;
 
@@ -587,8 +1197,8 @@ impl BinaryManager {
             ));
         }
 
-        let checksums_text = response
-            .text()
+        let checksums_text = self
+            .transfer_step(response.text())
             .await
             .map_err(|e| format!("Failed to read checksum file: {}", e))?;
 
@@ -608,17 +1218,61 @@ impl BinaryManager {
     }
 
     fn emit_progress(&self, binary: &str, progress: f64, status: &str) -> Result<(), String> {
-        let event = DownloadProgress {
+        self.emit_throttled(DownloadProgress {
             binary: binary.to_string(),
             progress,
             status: status.to_string(),
-        };
+            speed_bps: None,
+            eta_seconds: None,
+            error: None,
+        })
+    }
+
+    /// Tell the setup screen a binary failed; never throttled
+    fn emit_failure(&self, binary: &str, reason: &str) {
+        self.emit_throttled(DownloadProgress {
+            binary: binary.to_string(),
+            progress: 0.0,
+            status: "Failed".to_string(),
+            speed_bps: None,
+            eta_seconds: None,
+            error: Some(reason.to_string()),
+        })
+        .ok();
+    }
+
+    /// Send a "binary-download-progress" event unless the throttle coalesces it
+    fn emit_throttled(&self, event: DownloadProgress) -> Result<(), String> {
+        let admitted = self
+            .progress_throttle
+            .lock()
+            .map(|mut throttle| throttle.admit(&event, Instant::now()))
+            .unwrap_or(true);
+        if !admitted {
+            return Ok(());
+        }
 
         self.app_handle
             .emit("binary-download-progress", event)
-            .map_err(|e| e.to_string())?;
+            .map_err(|e| e.to_string())
+    }
 
-        Ok(())
+    /// Report a checksum mismatch as a security safeguard rather than a crash
+    /// Returns the user-facing error message
+    fn emit_checksum_failed(&self, binary: &str) -> String {
+        let message = format!(
+            "The downloaded {} did not match its official checksum, so it was discarded as a security precaution. \
+             This is usually a corrupted or interrupted download. Check your connection and try again.",
+            binary
+        );
+
+        let event = ChecksumFailure {
+            binary: binary.to_string(),
+            message: message.clone(),
+        };
+        self.app_handle.emit("binary-checksum-failed", event).ok();
+
+        message
     }
 
     pub fn clone_for_background(&self) -> Self {
@@ -626,9 +1280,196 @@ impl BinaryManager {
     }
 }
 
+/// Execute a tool with its version flag to confirm it actually runs (not just that it exists)
+pub async fn diagnose_tool(name: &str, path: String, command: Command) -> ToolDiagnostic {
+    let (version, error) = match command.output().await {
+        Ok(output) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let version = stdout.lines().next().unwrap_or("").trim().to_string();
+            info!("{} reports version: {}", name, version);
+            (Some(version), None)
+        }
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            warn!(
+                "{} exited with {:?}: {}",
+                name,
+                output.status.code(),
+                stderr
+            );
+            (
+                None,
+                Some(format!(
+                    "Exited with code {:?}: {}",
+                    output.status.code(),
+                    stderr
+                )),
+            )
+        }
+        Err(e) => {
+            error!("Failed to execute {}: {}", name, e);
+            (None, Some(format!("Failed to execute: {}", e)))
+        }
+    };
+
+    ToolDiagnostic {
+        name: name.to_string(),
+        path,
+        version,
+        error,
+    }
+}
+
 struct DownloadSource {
     name: &'static str,
     url: String,
     version: String,
     is_zip: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concurrent_binary_writes_never_leave_partial_file() {
+        let dir = std::env::temp_dir().join(format!("ripvid-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("yt-dlp");
+
+        // Two updaters replacing the binary with different releases at once
+        let releases: Vec<Vec<u8>> = vec![vec![b'a'; 4 * 1024 * 1024], vec![b'b'; 3 * 1024 * 1024]];
+        let writers: Vec<_> = releases
+            .iter()
+            .cloned()
+            .map(|bytes| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..5 {
+                        write_binary_atomically(&path, &bytes).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let written = fs::read(&path).unwrap();
+        assert!(releases.contains(&written));
+        // No temp files are left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_parse_progress_out_time() {
+        assert_eq!(parse_progress_out_time("out_time_us=1500000"), Some(1.5));
+        assert_eq!(parse_progress_out_time("out_time_us=N/A"), None);
+        assert_eq!(parse_progress_out_time("progress=continue"), None);
+    }
+
+    #[test]
+    fn test_transfer_rate() {
+        let (speed, eta) = transfer_rate(1_000, Some(5_000), Duration::from_secs(2));
+        assert_eq!(speed, Some(500.0));
+        assert_eq!(eta, Some(8));
+
+        // Unknown Content-Length still reports speed, but no ETA
+        let (speed, eta) = transfer_rate(1_000, None, Duration::from_secs(2));
+        assert_eq!(speed, Some(500.0));
+        assert_eq!(eta, None);
+
+        assert_eq!(
+            transfer_rate(0, Some(5_000), Duration::from_secs(2)),
+            (None, None)
+        );
+    }
+
+    #[test]
+    fn test_progress_throttle() {
+        let progress = |binary: &str, progress: f64, status: &str| DownloadProgress {
+            binary: binary.to_string(),
+            progress,
+            status: status.to_string(),
+            speed_bps: None,
+            eta_seconds: None,
+            error: None,
+        };
+        let mut throttle = ProgressThrottle::default();
+        let start = Instant::now();
+        let soon = start + Duration::from_millis(20);
+
+        assert!(throttle.admit(&progress("ffmpeg", 30.0, "Downloading ffmpeg..."), start));
+        // Another binary has its own budget
+        assert!(throttle.admit(&progress("ffprobe", 30.0, "Downloading ffprobe..."), soon));
+        // Repeated transfer updates are coalesced until the interval passes
+        assert!(!throttle.admit(&progress("ffmpeg", 31.0, "Downloading ffmpeg..."), soon));
+        assert!(throttle.admit(
+            &progress("ffmpeg", 40.0, "Downloading ffmpeg..."),
+            start + BINARY_PROGRESS_INTERVAL
+        ));
+
+        // Step changes, completion and failures are never dropped
+        let later = start + BINARY_PROGRESS_INTERVAL + Duration::from_millis(10);
+        assert!(throttle.admit(&progress("ffmpeg", 75.0, "Saving binary..."), later));
+        assert!(throttle.admit(&progress("ffmpeg", 100.0, "Ready!"), later));
+        let mut failed = progress("ffprobe", 30.0, "Downloading ffprobe...");
+        failed.error = Some(SETUP_CANCELLED.to_string());
+        assert!(throttle.admit(&failed, soon));
+    }
+
+    #[test]
+    fn test_parse_ffmpeg_encoders() {
+        let output = "Encoders:
+ V..... = Video
+ A..... = Audio
+ ------
+ V....D libx264              libx264 H.264 / AVC / MPEG-4 AVC
+ A....D aac                  AAC (Advanced Audio Coding)
+ A....D flac                 FLAC (Free Lossless Audio Codec)
+ A....D pcm_s16le            PCM signed 16-bit little-endian
+";
+        let encoders = FfmpegEncoders::parse(output);
+        assert_eq!(
+            encoders,
+            FfmpegEncoders {
+                mp3: false,
+                aac: true,
+                opus: false,
+                flac: true,
+            }
+        );
+        assert!(!encoders.supports(AudioFormat::Mp3));
+        assert!(encoders.supports(AudioFormat::M4a));
+        assert!(encoders.supports(AudioFormat::Wav));
+    }
+
+    #[test]
+    fn test_parse_media_probe() {
+        let output = r#"{
+            "programs": [],
+            "streams": [
+                {},
+                { "width": 1920, "height": 1080 }
+            ],
+            "format": { "duration": "212.345000" }
+        }"#;
+        assert_eq!(
+            MediaProbe::parse(output),
+            Some(MediaProbe {
+                duration: Some(212.345),
+                width: Some(1920),
+                height: Some(1080),
+            })
+        );
+
+        let audio_only = r#"{ "streams": [{}], "format": { "duration": "60.0" } }"#;
+        let probe = MediaProbe::parse(audio_only).unwrap();
+        assert_eq!(probe.duration, Some(60.0));
+        assert_eq!(probe.height, None);
+
+        assert_eq!(MediaProbe::parse("not json"), None);
+    }
+}
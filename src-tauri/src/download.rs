@@ -1,16 +1,26 @@
-use crate::binary_manager::BinaryManager;
+use crate::batch::BatchSummary;
+use crate::binary_manager::{BinaryManager, MediaProbe};
+use crate::dates::civil_from_days;
 use crate::errors::{
-    is_auth_error, is_dpapi_error, is_ffmpeg_error, is_network_error, is_rate_limit_error,
-    is_retryable_error, DownloadError,
+    classify_ytdlp_error, is_resumable_error, is_retryable_error, stderr_tail, DownloadError,
+};
+use crate::metadata::run_registered;
+use crate::network::resolve_proxy;
+use crate::queue::{DownloadQueue, QueuedDownload};
+use crate::settings::{AppSettings, SettingsManager};
+use crate::url_classifier::detect_platform;
+use crate::validation::{
+    validate_extra_args, validate_geo_bypass, validate_impersonate, validate_path,
+    validate_subtitle_langs, validate_time_range, validate_user_agent, GEO_BYPASS_DEFAULT,
 };
 use crate::ytdlp_updater::YtdlpUpdater;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tauri::{AppHandle, Emitter, Manager};
-use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+use tauri_plugin_shell::process::{Command, CommandChild, CommandEvent};
 use tauri_plugin_shell::ShellExt;
 use tokio::sync::Mutex;
 use tracing::{debug, error, info, warn};
@@ -41,716 +51,3294 @@ fn strip_extended_path_prefix(path: &std::path::Path) -> String {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum DownloadType {
-    Video { quality: String },
-    Audio,
-}
-
-/// Progress information for downloads
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DownloadProgress {
-    pub percent: f32,
-    pub speed: String,
-    pub eta: String,
+    Video {
+        quality: String,
+        #[serde(default)]
+        embed_thumbnail: bool,
+        /// Save the thumbnail as a separate image next to the video (--write-thumbnail)
+        #[serde(default)]
+        write_thumbnail: bool,
+        #[serde(default)]
+        thumbnail_format: ThumbnailFormat,
+        #[serde(default)]
+        container: Container,
+        /// Write title/uploader/date tags into the file (--embed-metadata)
+        #[serde(default)]
+        embed_metadata: bool,
+        /// Write straight to the final file (--no-part) for filesystems that can't rename
+        #[serde(default)]
+        no_part: bool,
+        /// Use aria2c with multiple connections when it is available
+        #[serde(default)]
+        use_aria2c: bool,
+        /// Keep the separate pre-merge streams next to the merged file (-k)
+        #[serde(default)]
+        keep_original: bool,
+        /// Validated yt-dlp arguments appended as-is
+        #[serde(default)]
+        extra_args: Vec<String>,
+        /// What to do when the output file already exists
+        #[serde(default)]
+        collision: Collision,
+        /// Fragments downloaded in parallel (-N); None picks automatically
+        #[serde(default)]
+        concurrent_fragments: Option<u32>,
+        /// Prefer VP9/Opus streams (--prefer-free-formats) so they land in webm without a remux
+        #[serde(default)]
+        prefer_free_formats: bool,
+        #[serde(default)]
+        subtitle_mode: SubtitleMode,
+        /// Languages of uploaded subtitles (--write-subs)
+        #[serde(default, alias = "subtitle_langs")]
+        manual_sub_langs: Vec<String>,
+        /// Languages of auto-generated captions (--write-auto-subs); yt-dlp still
+        /// prefers uploaded subtitles when a language has both
+        #[serde(default)]
+        auto_sub_langs: Vec<String>,
+        /// Save the best video-only and audio-only streams as two files, without merging
+        #[serde(default)]
+        separate_streams: bool,
+        /// Country to appear to download from for region-locked content
+        #[serde(default)]
+        geo_bypass: Option<String>,
+        /// Step down to the next lower quality when this one fails
+        #[serde(default)]
+        quality_fallback: bool,
+        /// Custom User-Agent header for sites that block yt-dlp's default client
+        #[serde(default)]
+        user_agent: Option<String>,
+        /// Browser client yt-dlp imitates (--impersonate), e.g. "chrome"
+        #[serde(default)]
+        impersonate: Option<String>,
+    },
+    Audio {
+        #[serde(default = "default_true")]
+        embed_thumbnail: bool,
+        #[serde(default)]
+        write_thumbnail: bool,
+        #[serde(default)]
+        thumbnail_format: ThumbnailFormat,
+        /// Preferred audio language (e.g. "en") for multi-track uploads
+        #[serde(default)]
+        audio_track: Option<String>,
+        #[serde(default)]
+        audio_format: AudioFormat,
+        #[serde(default = "default_true")]
+        embed_metadata: bool,
+        /// Fill the artist tag from the uploader name
+        #[serde(default)]
+        artist_from_uploader: bool,
+        #[serde(default)]
+        no_part: bool,
+        #[serde(default)]
+        use_aria2c: bool,
+        #[serde(default)]
+        extra_args: Vec<String>,
+        #[serde(default)]
+        collision: Collision,
+        #[serde(default)]
+        concurrent_fragments: Option<u32>,
+        /// Only download from this timestamp, e.g. to skip a music video intro
+        #[serde(default)]
+        start_time: Option<String>,
+        /// Only download up to this timestamp
+        #[serde(default)]
+        end_time: Option<String>,
+        #[serde(default)]
+        geo_bypass: Option<String>,
+        #[serde(default)]
+        user_agent: Option<String>,
+        #[serde(default)]
+        impersonate: Option<String>,
+    },
 }
 
-/// Handle to an active download process
-pub struct DownloadHandle {
-    pub id: String,
-    pub child: CommandChild,
-    pub url: String,
-    pub output_path: String,
-}
+impl DownloadType {
+    /// Whether yt-dlp will need ffmpeg: audio is always converted, video streams are
+    /// merged unless saved separately, and embedding or converting thumbnails uses it too
+    pub fn requires_ffmpeg(&self) -> bool {
+        match self {
+            DownloadType::Video {
+                separate_streams,
+                embed_metadata,
+                subtitle_mode,
+                write_thumbnail,
+                thumbnail_format,
+                ..
+            } => {
+                !*separate_streams
+                    || *embed_metadata
+                    || matches!(subtitle_mode, SubtitleMode::Embed | SubtitleMode::Both)
+                    || (*write_thumbnail && thumbnail_format.convert_to().is_some())
+            }
+            DownloadType::Audio { .. } => true,
+        }
+    }
 
-/// Configuration for browser cookie support
-#[derive(Debug, Clone)]
-pub struct BrowserConfig {
-    pub use_cookies: bool,
-    pub browser: Option<String>,
-}
+    pub fn no_part(&self) -> bool {
+        match self {
+            DownloadType::Video { no_part, .. } | DownloadType::Audio { no_part, .. } => *no_part,
+        }
+    }
 
-impl BrowserConfig {
-    pub fn new(use_cookies: bool) -> Self {
-        Self {
-            use_cookies,
-            browser: if use_cookies { detect_browser() } else { None },
+    pub fn write_thumbnail(&self) -> bool {
+        match self {
+            DownloadType::Video {
+                write_thumbnail, ..
+            }
+            | DownloadType::Audio {
+                write_thumbnail, ..
+            } => *write_thumbnail,
         }
     }
-}
 
-/// Detect which browser to use for cookies
-pub fn detect_browser() -> Option<String> {
-    info!("Starting browser detection for cookie extraction...");
+    pub fn use_aria2c(&self) -> bool {
+        match self {
+            DownloadType::Video { use_aria2c, .. } | DownloadType::Audio { use_aria2c, .. } => {
+                *use_aria2c
+            }
+        }
+    }
 
-    // Try to detect installed browsers in order of preference
-    // Firefox first - doesn't have Windows DPAPI cookie encryption issues
-    let browsers = vec!["firefox", "chrome", "edge"];
+    pub fn collision(&self) -> Collision {
+        match self {
+            DownloadType::Video { collision, .. } | DownloadType::Audio { collision, .. } => {
+                *collision
+            }
+        }
+    }
 
-    for browser in browsers {
-        debug!("Checking for browser: {}", browser);
-        if is_browser_installed(browser) {
-            info!("✓ Detected browser for cookies: {}", browser);
-            return Some(browser.to_string());
-        } else {
-            debug!("✗ Browser not found: {}", browser);
+    pub fn concurrent_fragments(&self) -> Option<u32> {
+        match self {
+            DownloadType::Video {
+                concurrent_fragments,
+                ..
+            }
+            | DownloadType::Audio {
+                concurrent_fragments,
+                ..
+            } => *concurrent_fragments,
         }
     }
 
-    warn!("No supported browser found for cookie extraction");
-    warn!("Checked: Firefox, Chrome, Edge");
-    warn!("Recommendation: Install Firefox for best compatibility on Windows");
-    None
-}
+    pub fn separate_streams(&self) -> bool {
+        matches!(
+            self,
+            DownloadType::Video {
+                separate_streams: true,
+                ..
+            }
+        )
+    }
 
-/// Check if a browser is installed (improved detection)
-fn is_browser_installed(browser: &str) -> bool {
-    #[cfg(target_os = "windows")]
-    {
-        use std::process::Command;
+    pub fn quality_fallback(&self) -> bool {
+        matches!(
+            self,
+            DownloadType::Video {
+                quality_fallback: true,
+                ..
+            }
+        )
+    }
 
-        let found = match browser {
-            "firefox" => {
-                // Check multiple Firefox locations
-                let paths = vec![
-                    "C:\\Program Files\\Mozilla Firefox\\firefox.exe",
-                    "C:\\Program Files (x86)\\Mozilla Firefox\\firefox.exe",
-                ];
+    /// Current video quality, None for audio downloads
+    pub fn quality(&self) -> Option<&str> {
+        match self {
+            DownloadType::Video { quality, .. } => Some(quality),
+            DownloadType::Audio { .. } => None,
+        }
+    }
 
-                for path in &paths {
-                    debug!("  Checking path: {}", path);
-                    if std::path::Path::new(path).exists() {
-                        debug!("  ✓ Found at: {}", path);
-                        return true;
-                    }
-                }
+    /// Copy of an audio download adapted to a source without video, such as an X Space
+    /// Such sources have no artwork, so cover art embedding is turned off instead of failing
+    pub fn for_audio_only_source(&self) -> Self {
+        let mut download_type = self.clone();
+        if let DownloadType::Audio {
+            embed_thumbnail, ..
+        } = &mut download_type
+        {
+            *embed_thumbnail = false;
+        }
+        download_type
+    }
 
-                // Try AppData location
-                if let Ok(appdata) = std::env::var("LOCALAPPDATA") {
-                    let local_path = format!("{}\\Mozilla Firefox\\firefox.exe", appdata);
-                    debug!("  Checking AppData: {}", local_path);
-                    if std::path::Path::new(&local_path).exists() {
-                        debug!("  ✓ Found at: {}", local_path);
-                        return true;
-                    }
-                }
+    /// Copy of this download at another video quality; audio downloads are returned unchanged
+    pub fn with_quality(&self, new_quality: String) -> Self {
+        let mut download_type = self.clone();
+        if let DownloadType::Video { quality, .. } = &mut download_type {
+            *quality = new_quality;
+        }
+        download_type
+    }
 
-                false
+    pub fn user_agent(&self) -> Option<&str> {
+        match self {
+            DownloadType::Video { user_agent, .. } | DownloadType::Audio { user_agent, .. } => {
+                user_agent.as_deref()
             }
-            "chrome" => {
-                // Check multiple Chrome locations
-                let paths = vec![
-                    "C:\\Program Files\\Google\\Chrome\\Application\\chrome.exe",
-                    "C:\\Program Files (x86)\\Google\\Chrome\\Application\\chrome.exe",
-                ];
+        }
+    }
 
-                for path in &paths {
-                    debug!("  Checking path: {}", path);
-                    if std::path::Path::new(path).exists() {
-                        debug!("  ✓ Found at: {}", path);
-                        return true;
-                    }
-                }
+    pub fn impersonate(&self) -> Option<&str> {
+        match self {
+            DownloadType::Video { impersonate, .. } | DownloadType::Audio { impersonate, .. } => {
+                impersonate.as_deref()
+            }
+        }
+    }
 
-                // Try AppData location
-                if let Ok(appdata) = std::env::var("LOCALAPPDATA") {
-                    let local_path =
-                        format!("{}\\Google\\Chrome\\Application\\chrome.exe", appdata);
-                    debug!("  Checking AppData: {}", local_path);
-                    if std::path::Path::new(&local_path).exists() {
-                        debug!("  ✓ Found at: {}", local_path);
-                        return true;
-                    }
-                }
+    pub fn geo_bypass(&self) -> Option<&str> {
+        match self {
+            DownloadType::Video { geo_bypass, .. } | DownloadType::Audio { geo_bypass, .. } => {
+                geo_bypass.as_deref()
+            }
+        }
+    }
 
-                false
+    /// Copy of this download that lets yt-dlp bypass geo restrictions on its own
+    pub fn with_geo_bypass(&self) -> Self {
+        let mut download_type = self.clone();
+        match &mut download_type {
+            DownloadType::Video { geo_bypass, .. } | DownloadType::Audio { geo_bypass, .. } => {
+                *geo_bypass = Some(GEO_BYPASS_DEFAULT.to_string());
             }
-            "edge" => {
-                // Check multiple Edge locations
-                let paths = vec![
-                    "C:\\Program Files\\Microsoft\\Edge\\Application\\msedge.exe",
-                    "C:\\Program Files (x86)\\Microsoft\\Edge\\Application\\msedge.exe",
-                ];
+        }
+        download_type
+    }
 
-                for path in &paths {
-                    debug!("  Checking path: {}", path);
-                    if std::path::Path::new(path).exists() {
-                        debug!("  ✓ Found at: {}", path);
-                        return true;
-                    }
-                }
+    pub fn manual_sub_langs(&self) -> &[String] {
+        match self {
+            DownloadType::Video {
+                manual_sub_langs, ..
+            } => manual_sub_langs,
+            DownloadType::Audio { .. } => &[],
+        }
+    }
 
-                // Try where command as fallback
-                debug!("  Trying 'where msedge.exe' command...");
-                let where_result = Command::new("where")
-                    .arg("msedge.exe")
-                    .output()
-                    .map(|output| {
-                        let found = output.status.success();
-                        if found {
-                            let path = String::from_utf8_lossy(&output.stdout);
-                            debug!("  ✓ Found via 'where': {}", path.trim());
-                        }
-                        found
-                    })
-                    .unwrap_or(false);
+    pub fn auto_sub_langs(&self) -> &[String] {
+        match self {
+            DownloadType::Video { auto_sub_langs, .. } => auto_sub_langs,
+            DownloadType::Audio { .. } => &[],
+        }
+    }
 
-                if where_result {
-                    return true;
-                }
+    pub fn subtitle_mode(&self) -> SubtitleMode {
+        match self {
+            DownloadType::Video { subtitle_mode, .. } => *subtitle_mode,
+            DownloadType::Audio { .. } => SubtitleMode::None,
+        }
+    }
 
-                false
+    /// Copy of this download with other subtitle languages; audio downloads are returned
+    /// unchanged. With no languages left there is nothing to fetch, so subtitles turn off
+    pub fn with_sub_langs(&self, manual: Vec<String>, auto: Vec<String>) -> Self {
+        let mut download_type = self.clone();
+        if let DownloadType::Video {
+            subtitle_mode,
+            manual_sub_langs,
+            auto_sub_langs,
+            ..
+        } = &mut download_type
+        {
+            if manual.is_empty() && auto.is_empty() {
+                *subtitle_mode = SubtitleMode::None;
             }
-            _ => false,
-        };
+            *manual_sub_langs = manual;
+            *auto_sub_langs = auto;
+        }
+        download_type
+    }
 
-        found
+    pub fn extra_args(&self) -> &[String] {
+        match self {
+            DownloadType::Video { extra_args, .. } | DownloadType::Audio { extra_args, .. } => {
+                extra_args
+            }
+        }
     }
+}
 
-    #[cfg(target_os = "macos")]
-    {
-        match browser {
-            "firefox" => std::path::Path::new("/Applications/Firefox.app").exists(),
-            "chrome" => std::path::Path::new("/Applications/Google Chrome.app").exists(),
-            "edge" => std::path::Path::new("/Applications/Microsoft Edge.app").exists(),
-            _ => false,
+fn default_true() -> bool {
+    true
+}
+
+/// Container that separate video and audio streams are merged into
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Container {
+    /// Most compatible; prefers H.264/AAC streams so no re-encode is needed
+    #[default]
+    Mp4,
+    /// Accepts any codec (VP9, AV1, Opus) without lossy re-encoding
+    Mkv,
+    /// Native container for VP9/AV1 + Opus streams
+    Webm,
+}
+
+impl Container {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Container::Mp4 => "mp4",
+            Container::Mkv => "mkv",
+            Container::Webm => "webm",
         }
     }
 
-    #[cfg(target_os = "linux")]
-    {
-        use std::process::Command;
-        // On Linux, check if the browser command is available
-        Command::new("which")
-            .arg(browser)
-            .output()
-            .map(|output| output.status.success())
-            .unwrap_or(false)
+    /// WebM has no cover art support, so yt-dlp refuses to embed thumbnails
+    pub fn supports_thumbnail_embed(&self) -> bool {
+        !matches!(self, Container::Webm)
     }
 }
 
-/// Map quality string to yt-dlp format selector
-fn get_quality_format(quality: &str) -> String {
-    match quality.to_lowercase().as_str() {
-        "best" => {
-            "bestvideo[ext=mp4][vcodec^=avc]+bestaudio[ext=m4a]/best[ext=mp4]/best".to_string()
-        }
-        "1080p" | "1080" => {
-            "bestvideo[height<=1080][ext=mp4][vcodec^=avc]+bestaudio[ext=m4a]/best[ext=mp4]"
-                .to_string()
-        }
-        "720p" | "720" => {
-            "bestvideo[height<=720][ext=mp4][vcodec^=avc]+bestaudio[ext=m4a]/best[ext=mp4]"
-                .to_string()
-        }
-        "480p" | "480" => {
-            "bestvideo[height<=480][ext=mp4][vcodec^=avc]+bestaudio[ext=m4a]/best[ext=mp4]"
-                .to_string()
-        }
-        "360p" | "360" => {
-            "bestvideo[height<=360][ext=mp4]+bestaudio[ext=m4a]/best[ext=mp4]".to_string()
-        }
-        _ => {
-            warn!("Unknown quality '{}', using 'best'", quality);
-            "bestvideo[ext=mp4][vcodec^=avc]+bestaudio[ext=m4a]/best[ext=mp4]/best".to_string()
+/// Audio format yt-dlp extracts to (--audio-format)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioFormat {
+    #[default]
+    Mp3,
+    M4a,
+    Opus,
+    Flac,
+    Wav,
+}
+
+impl AudioFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::M4a => "m4a",
+            AudioFormat::Opus => "opus",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Wav => "wav",
         }
     }
+
+    /// WAV has no cover art support
+    pub fn supports_thumbnail_embed(&self) -> bool {
+        !matches!(self, AudioFormat::Wav)
+    }
 }
 
-/// Build arguments for yt-dlp based on download type
-fn build_ytdlp_args(
-    url: &str,
-    output_path: &str,
-    download_type: &DownloadType,
-    browser_config: &BrowserConfig,
-    binary_manager: &BinaryManager,
-) -> Vec<String> {
-    let mut args = vec![url.to_string(), "--no-playlist".to_string()];
+/// How to handle an output file that already exists
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Collision {
+    /// Leave the existing file alone and skip the download (--no-overwrites)
+    Skip,
+    /// Replace the existing file (--force-overwrites)
+    Overwrite,
+    /// Save as "name (2).ext", "name (3).ext", ... like a browser download
+    #[default]
+    Rename,
+}
 
-    // Add ffmpeg location using binary manager
-    match binary_manager.get_binary_path("ffmpeg") {
-        Ok(ffmpeg_path) => {
-            if let Some(ffmpeg_dir) = ffmpeg_path.parent() {
-                let ffmpeg_path_str = strip_extended_path_prefix(ffmpeg_dir);
-                args.push("--ffmpeg-location".to_string());
-                args.push(ffmpeg_path_str.clone());
-                info!("✓ Using runtime-downloaded ffmpeg at: {}", ffmpeg_path_str);
-            } else {
-                warn!("Could not determine ffmpeg directory");
-            }
-        }
-        Err(e) => {
-            warn!("Could not get ffmpeg path: {}. yt-dlp will use system ffmpeg if available", e);
+/// Whether subtitles are embedded into the video, saved next to it, or both
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubtitleMode {
+    #[default]
+    None,
+    /// Mux the subtitle tracks into the video (--embed-subs)
+    Embed,
+    /// Write .vtt/.srt files next to the video (--write-subs)
+    Sidecar,
+    Both,
+}
+
+/// Image format thumbnails are converted to before embedding or writing
+/// YouTube serves WEBP, which MP3 cover art doesn't support
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailFormat {
+    #[default]
+    Jpg,
+    Png,
+    /// Only for written thumbnails; embedding falls back to jpg
+    Webp,
+    /// Embed the thumbnail as served, without conversion
+    Original,
+}
+
+impl ThumbnailFormat {
+    /// Value for --convert-thumbnails, or None to skip conversion
+    fn convert_to(&self) -> Option<&'static str> {
+        match self {
+            ThumbnailFormat::Jpg => Some("jpg"),
+            ThumbnailFormat::Png => Some("png"),
+            ThumbnailFormat::Webp => Some("webp"),
+            ThumbnailFormat::Original => None,
         }
     }
 
-    // Add format-specific arguments
-    match download_type {
-        DownloadType::Video { quality } => {
-            args.push("-f".to_string());
-            args.push(get_quality_format(quality));
-            args.push("--merge-output-format".to_string());
-            args.push("mp4".to_string());
-        }
-        DownloadType::Audio => {
-            args.push("-x".to_string());
-            args.push("--audio-format".to_string());
-            args.push("mp3".to_string());
-            args.push("--audio-quality".to_string());
-            args.push("0".to_string());
-            args.push("--embed-thumbnail".to_string());
-            args.push("--add-metadata".to_string());
+    /// Conversion target when the thumbnail is embedded as cover art
+    fn convert_to_for_embed(&self) -> Option<&'static str> {
+        match self {
+            ThumbnailFormat::Webp => Some("jpg"),
+            other => other.convert_to(),
         }
     }
+}
 
-    // Add browser cookie support if enabled
-    if browser_config.use_cookies {
-        if let Some(browser) = &browser_config.browser {
-            args.push("--cookies-from-browser".to_string());
-            args.push(browser.clone());
-            info!("Using cookies from browser: {}", browser);
+/// Optional per-download knobs sent by the frontend
+/// Every field has a default so new options don't change the command signatures
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DownloadOptions {
+    /// Embed the thumbnail as cover art (defaults: off for video, on for audio)
+    pub embed_thumbnail: Option<bool>,
+    /// Embed title/uploader/date tags (defaults: off for video, on for audio)
+    pub embed_metadata: Option<bool>,
+    /// Set the artist tag from the uploader for audio downloads
+    pub artist_from_uploader: bool,
+    /// Merge container for video downloads
+    pub container: Container,
+    /// Also save the thumbnail as a separate image file
+    pub write_thumbnail: bool,
+    /// Thumbnail conversion applied before embedding into audio files or writing to disk
+    pub thumbnail_format: ThumbnailFormat,
+    /// Audio language to extract when a video has several tracks
+    pub audio_track: Option<String>,
+    /// Format audio is extracted to; None uses the default from settings
+    pub audio_format: Option<AudioFormat>,
+    /// Skip .part files, for SMB/NFS mounts where the final rename fails
+    pub no_part: bool,
+    /// Download with aria2c (16 connections) instead of yt-dlp's native downloader
+    pub use_aria2c: bool,
+    /// Keep the source video/audio streams after merging (video only)
+    pub keep_original: bool,
+    /// Additional yt-dlp arguments for power users; validated before use
+    pub extra_args: Vec<String>,
+    /// What to do when the output file already exists
+    pub collision: Collision,
+    /// Fragments downloaded in parallel for HLS/DASH; None picks automatically
+    pub concurrent_fragments: Option<u32>,
+    /// Keep free formats in their native container instead of remuxing to mp4 (video only)
+    pub prefer_free_formats: bool,
+    /// Subtitle handling; None uses the default from settings (video only)
+    pub subtitle_mode: Option<SubtitleMode>,
+    /// Uploaded subtitle languages; None uses the default from settings
+    #[serde(alias = "subtitleLangs")]
+    pub manual_sub_langs: Option<Vec<String>>,
+    /// Auto-generated caption languages; None uses the default from settings
+    pub auto_sub_langs: Option<Vec<String>>,
+    /// Save video and audio as two unmerged files, e.g. for editing (video only)
+    pub separate_streams: bool,
+    /// Start of the section to keep, as seconds, MM:SS or HH:MM:SS (audio only)
+    pub start_time: Option<String>,
+    /// End of the section to keep (audio only)
+    pub end_time: Option<String>,
+    /// Two-letter country code passed to --geo-bypass-country; None leaves geo bypass off
+    pub geo_bypass: Option<String>,
+    /// Retry at lower qualities (down to 360p) when the chosen one fails (video only)
+    pub quality_fallback: bool,
+    /// User-Agent header sent instead of yt-dlp's default
+    pub user_agent: Option<String>,
+    /// Browser client to impersonate for sites that reject yt-dlp's requests
+    pub impersonate: Option<String>,
+}
+
+impl DownloadOptions {
+    pub fn into_video(self, quality: String) -> DownloadType {
+        // mp4 would force H.264 back in, so highest-resolution downloads land in mkv
+        // and free-format downloads in webm, where VP9/Opus streams fit as served
+        let container = if self.container != Container::Mp4 {
+            self.container
+        } else if self.prefer_free_formats {
+            Container::Webm
+        } else if quality.eq_ignore_ascii_case(BEST_QUALITY) {
+            Container::Mkv
         } else {
-            warn!("Browser cookies requested but no browser detected");
+            self.container
+        };
+
+        DownloadType::Video {
+            quality,
+            embed_thumbnail: self.embed_thumbnail.unwrap_or(false),
+            write_thumbnail: self.write_thumbnail,
+            thumbnail_format: self.thumbnail_format,
+            container,
+            embed_metadata: self.embed_metadata.unwrap_or(false),
+            no_part: self.no_part,
+            use_aria2c: self.use_aria2c,
+            keep_original: self.keep_original,
+            extra_args: self.extra_args,
+            collision: self.collision,
+            concurrent_fragments: self.concurrent_fragments,
+            prefer_free_formats: self.prefer_free_formats,
+            subtitle_mode: self.subtitle_mode.unwrap_or_default(),
+            manual_sub_langs: self.manual_sub_langs.unwrap_or_default(),
+            auto_sub_langs: self.auto_sub_langs.unwrap_or_default(),
+            separate_streams: self.separate_streams,
+            geo_bypass: self.geo_bypass.filter(|c| !c.trim().is_empty()),
+            quality_fallback: self.quality_fallback,
+            user_agent: self.user_agent.filter(|ua| !ua.trim().is_empty()),
+            impersonate: self.impersonate.filter(|target| !target.trim().is_empty()),
         }
     }
 
-    // Add output path and progress options
-    args.push("-o".to_string());
-    args.push(output_path.to_string());
-    args.push("--progress".to_string());
-    args.push("--newline".to_string());
+    /// Fill in subtitle options the caller left unset from the user's defaults
+    pub fn with_subtitle_defaults(mut self, settings: &AppSettings) -> Self {
+        if self.subtitle_mode.is_none() {
+            self.subtitle_mode = Some(settings.default_subtitle_mode);
+        }
+        if self.manual_sub_langs.is_none() {
+            self.manual_sub_langs = Some(settings.default_manual_sub_langs.clone());
+        }
+        if self.auto_sub_langs.is_none() {
+            self.auto_sub_langs = Some(settings.default_auto_sub_langs.clone());
+        }
+        self
+    }
 
-    args
+    pub fn into_audio(self, default_format: AudioFormat) -> DownloadType {
+        DownloadType::Audio {
+            embed_thumbnail: self.embed_thumbnail.unwrap_or(true),
+            write_thumbnail: self.write_thumbnail,
+            thumbnail_format: self.thumbnail_format,
+            audio_track: self.audio_track,
+            audio_format: self.audio_format.unwrap_or(default_format),
+            embed_metadata: self.embed_metadata.unwrap_or(true),
+            artist_from_uploader: self.artist_from_uploader,
+            no_part: self.no_part,
+            use_aria2c: self.use_aria2c,
+            extra_args: self.extra_args,
+            collision: self.collision,
+            concurrent_fragments: self.concurrent_fragments,
+            start_time: self.start_time.filter(|t| !t.trim().is_empty()),
+            end_time: self.end_time.filter(|t| !t.trim().is_empty()),
+            geo_bypass: self.geo_bypass.filter(|c| !c.trim().is_empty()),
+            user_agent: self.user_agent.filter(|ua| !ua.trim().is_empty()),
+            impersonate: self.impersonate.filter(|target| !target.trim().is_empty()),
+        }
+    }
 }
 
-/// Parse progress information from yt-dlp output
-fn parse_progress(line: &str) -> Option<DownloadProgress> {
-    if !line.contains("[download]") || !line.contains("%") {
-        return None;
-    }
+/// Progress information for downloads
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadProgress {
+    /// Download this tick belongs to, so concurrent downloads don't cross-contaminate
+    #[serde(default)]
+    pub id: String,
+    pub percent: f32,
+    pub speed: String,
+    pub eta: String,
+    pub frag_current: Option<u32>,
+    pub frag_total: Option<u32>,
+    /// Size of the file being downloaded, when yt-dlp knows or estimates it
+    #[serde(default)]
+    pub total_bytes: Option<u64>,
+    /// Current speed in bytes per second
+    #[serde(default)]
+    pub speed_bytes: Option<f64>,
+}
 
-    let percent_regex = Regex::new(r"(\d+(?:\.\d+)?)%").ok()?;
-    let percent = percent_regex
-        .captures(line)?
-        .get(1)?
-        .as_str()
-        .parse::<f32>()
-        .ok()?;
+impl DownloadProgress {
+    /// Bytes of the current file downloaded so far, derived from the percent
+    pub fn downloaded_bytes(&self) -> Option<u64> {
+        self.total_bytes
+            .map(|total| (total as f64 * f64::from(self.percent) / 100.0) as u64)
+    }
+}
 
-    let speed_regex = Regex::new(r"at\s+(\S+)").ok()?;
-    let speed = speed_regex
-        .captures(line)
-        .and_then(|cap| cap.get(1))
-        .map(|m| m.as_str().to_string())
-        .unwrap_or_else(|| "---".to_string());
+/// Aggregate progress across a playlist download
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistProgress {
+    #[serde(default)]
+    pub id: String,
+    pub current_item: u32,
+    pub total_items: u32,
+    pub percent: f32,
+}
 
-    let eta_regex = Regex::new(r"ETA\s+(\S+)").ok()?;
-    let eta = eta_regex
-        .captures(line)
-        .and_then(|cap| cap.get(1))
-        .map(|m| m.as_str().to_string())
-        .unwrap_or_else(|| "--:--".to_string());
+impl PlaylistProgress {
+    /// Combine the playlist position with the current item's percent
+    /// Overall = (completed_items + current_item_percent / 100) / total_items
+    fn new(current_item: u32, total_items: u32, item_percent: f32) -> Self {
+        let completed_items = current_item.saturating_sub(1) as f32;
+        let percent = if total_items > 0 {
+            ((completed_items + item_percent / 100.0) / total_items as f32 * 100.0).min(100.0)
+        } else {
+            item_percent
+        };
 
-    Some(DownloadProgress {
-        percent,
-        speed,
-        eta,
-    })
+        Self {
+            id: String::new(),
+            current_item,
+            total_items,
+            percent,
+        }
+    }
 }
 
-/// Retry a download operation with exponential backoff
-async fn retry_with_backoff<F, Fut, T>(operation: F, max_attempts: u32) -> Result<T, DownloadError>
-where
-    F: Fn() -> Fut,
-    Fut: std::future::Future<Output = Result<T, DownloadError>>,
-{
-    let mut attempts = 0;
-    let mut delay = Duration::from_secs(1);
+/// Minimum time between progress events for one download
+const PROGRESS_EMIT_INTERVAL: Duration = Duration::from_millis(100);
 
-    loop {
-        attempts += 1;
-        debug!("Attempt {} of {}", attempts, max_attempts);
+/// Limits progress events so fast downloads don't flood the event bridge
+/// A tick is emitted once both the interval has passed and the whole percent
+/// changed; 100% is always emitted
+#[derive(Debug, Default)]
+struct ProgressThrottle {
+    last_emit: Option<std::time::Instant>,
+    last_percent: Option<u32>,
+}
 
-        match operation().await {
-            Ok(result) => return Ok(result),
-            Err(error) => {
-                if attempts >= max_attempts || !is_retryable_error(&error) {
-                    error!("Operation failed after {} attempts: {}", attempts, error);
-                    return Err(error);
+impl ProgressThrottle {
+    fn should_emit(&mut self, percent: f32, now: std::time::Instant) -> bool {
+        let whole_percent = percent.floor() as u32;
+        let emit = percent >= 100.0
+            || match (self.last_emit, self.last_percent) {
+                (Some(last_emit), Some(last_percent)) => {
+                    now.duration_since(last_emit) >= PROGRESS_EMIT_INTERVAL
+                        && whole_percent != last_percent
                 }
+                _ => true,
+            };
 
-                warn!(
-                    "Attempt {} failed: {}. Retrying in {:?}...",
-                    attempts, error, delay
-                );
-
-                tokio::time::sleep(delay).await;
-
-                // Exponential backoff: 1s, 2s, 4s, 8s, etc.
-                delay *= 2;
-            }
+        if emit {
+            self.last_emit = Some(now);
+            self.last_percent = Some(whole_percent);
         }
+        emit
     }
 }
 
-/// Unified download function for both video and audio
-pub async fn download_content(
-    url: String,
-    output_path: String,
-    download_type: DownloadType,
-    browser_config: BrowserConfig,
-    window: tauri::WebviewWindow,
-    app: AppHandle,
-    ytdlp_updater: Arc<Mutex<YtdlpUpdater>>,
-    active_downloads: Arc<Mutex<std::collections::HashMap<String, DownloadHandle>>>,
-    binary_manager: Arc<BinaryManager>,
-) -> Result<String, DownloadError> {
-    let download_id = Uuid::new_v4().to_string();
+/// Where download events go; the app window in practice, a recorder in tests
+trait EventSink: Clone + Send + Sync + 'static {
+    fn send<S: Serialize + Clone>(&self, event: &str, payload: S) -> tauri::Result<()>;
+}
 
-    info!(
-        "Starting download: id={}, type={:?}, url={}, output={}",
-        download_id, download_type, url, output_path
-    );
+impl EventSink for tauri::WebviewWindow {
+    fn send<S: Serialize + Clone>(&self, event: &str, payload: S) -> tauri::Result<()> {
+        self.emit(event, payload)
+    }
+}
 
-    // Build arguments
-    let args = build_ytdlp_args(&url, &output_path, &download_type, &browser_config, &binary_manager);
-    debug!("yt-dlp args prepared (count: {})", args.len());
+/// Lifecycle phase carried by the unified "download-update" event
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadPhase {
+    #[default]
+    Started,
+    Downloading,
+    Processing,
+    Completed,
+    Failed,
+    Cancelled,
+}
 
-    // Get yt-dlp path with retry
-    let ytdlp_path = retry_with_backoff(
-        || async {
-            let updater = ytdlp_updater.lock().await;
-            updater
-                .ensure_updated()
-                .await
-                .map_err(|e| DownloadError::ProcessFailed(format!("Failed to get yt-dlp: {}", e)))
-        },
-        3,
-    )
-    .await
-    .unwrap_or_else(|_| PathBuf::from("yt-dlp"));
+/// Single event stream keyed by download id, emitted alongside the granular events
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadUpdate {
+    pub id: String,
+    pub phase: DownloadPhase,
+    pub percent: Option<f32>,
+    pub speed: Option<String>,
+    pub eta: Option<String>,
+    pub message: Option<String>,
+}
 
-    // Spawn yt-dlp process
-    let (mut rx, child) = if ytdlp_path == PathBuf::from("yt-dlp") {
-        info!("Using bundled yt-dlp sidecar");
-        app.shell()
-            .sidecar("yt-dlp")
-            .map_err(|e| DownloadError::Sidecar(e.to_string()))?
-            .args(&args)
-            .spawn()
-            .map_err(|e| DownloadError::ProcessFailed(e.to_string()))?
-    } else {
-        info!("Using updated yt-dlp from: {:?}", ytdlp_path);
-        app.shell()
-            .command(ytdlp_path)
-            .args(&args)
-            .spawn()
-            .map_err(|e| DownloadError::ProcessFailed(e.to_string()))?
-    };
+impl DownloadUpdate {
+    fn new(id: &str, phase: DownloadPhase) -> Self {
+        Self {
+            id: id.to_string(),
+            phase,
+            percent: None,
+            speed: None,
+            eta: None,
+            message: None,
+        }
+    }
 
-    // Store download handle for potential cancellation
-    {
-        let mut downloads = active_downloads.lock().await;
-        downloads.insert(
-            download_id.clone(),
-            DownloadHandle {
-                id: download_id.clone(),
-                child,
-                url: url.clone(),
-                output_path: output_path.clone(),
-            },
-        );
-        info!("Stored download handle: {}", download_id);
+    fn progress(id: &str, progress: &DownloadProgress) -> Self {
+        Self {
+            percent: Some(progress.percent),
+            speed: Some(progress.speed.clone()),
+            eta: Some(progress.eta.clone()),
+            ..Self::new(id, DownloadPhase::Downloading)
+        }
     }
 
-    // Emit download started event
-    window
-        .emit(
-            "download-started",
-            serde_json::json!({
-                "id": download_id,
-                "path": output_path
-            }),
-        )
-        .ok();
+    fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
 
-    // Clone variables for async task
-    let window_clone = window.clone();
-    let window_clone2 = window.clone();
-    let window_clone3 = window.clone();
-    let output_path_clone = output_path.clone();
-    let download_id_clone = download_id.clone();
-    let active_downloads_clone = active_downloads.clone();
-
-    // Spawn async task to handle command events
-    tauri::async_runtime::spawn(async move {
-        let mut stderr_buffer = String::new();
-
-        while let Some(event) = rx.recv().await {
-            match event {
-                CommandEvent::Stdout(line_data) => {
-                    let line = String::from_utf8_lossy(&line_data).to_string();
-                    debug!("[stdout] {}", line);
-
-                    // Detect merger/processing phase
-                    if line.contains("[Merger]")
-                        || line.contains("Merging formats")
-                        || line.contains("[ffmpeg]")
-                    {
-                        info!("Video processing phase detected");
-                        window_clone
-                            .emit(
-                                "download-processing",
-                                serde_json::json!({
-                                    "message": "Processing video...",
-                                    "id": download_id_clone
-                                }),
-                            )
-                            .ok();
-                    }
+    fn emit<E: EventSink>(self, sink: &E) {
+        sink.send("download-update", self).ok();
+    }
+}
 
-                    // Parse and emit progress
-                    if let Some(progress) = parse_progress(&line) {
-                        window_clone.emit("download-progress", &progress).ok();
-                    }
-                }
-                CommandEvent::Stderr(line_data) => {
-                    let line = String::from_utf8_lossy(&line_data).to_string();
-                    debug!("[stderr] {}", line);
-                    stderr_buffer.push_str(&line);
-                    stderr_buffer.push('\n');
-
-                    // Emit status messages for important events
-                    if line.contains("Sleeping") || line.contains("rate limit") {
-                        window_clone2.emit("download-status", &line).ok();
-                    }
-                }
-                CommandEvent::Terminated(payload) => {
-                    // Remove from active downloads
-                    {
-                        let mut downloads = active_downloads_clone.lock().await;
-                        downloads.remove(&download_id_clone);
-                        info!("Removed download handle: {}", download_id_clone);
-                    }
+/// The finished file, sent with "download-complete" so the library can add it without a rescan
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletedFile {
+    pub path: String,
+    /// None if the file couldn't be found or stat'ed
+    pub size: Option<u64>,
+    /// Duration and resolution; empty if ffprobe is unavailable or fails
+    #[serde(flatten)]
+    pub media: MediaProbe,
+}
 
-                    if let Some(code) = payload.code {
-                        if code == 0 {
-                            info!("Download completed successfully: {}", download_id_clone);
-                            window_clone3
-                                .emit(
-                                    "download-complete",
-                                    serde_json::json!({
-                                        "success": true,
-                                        "id": download_id_clone,
-                                        "path": output_path_clone
-                                    }),
-                                )
-                                .ok();
-                        } else {
-                            // Log full stderr for debugging
-                            error!(
-                                "Download failed with exit code {}. Full stderr output:",
-                                code
-                            );
-                            error!("{}", stderr_buffer);
-
-                            // Analyze stderr to provide better error messages
-                            let error_msg = if is_ffmpeg_error(&stderr_buffer) {
-                                "Video processing failed. FFmpeg is required to merge video and audio streams. Please restart the application and try again.".to_string()
-                            } else if is_dpapi_error(&stderr_buffer) {
-                                "Cookie decryption failed. Chrome/Edge on Windows have encryption issues. Solutions: 1) Close your browser completely and try again, 2) Install Firefox (recommended), or 3) Disable browser cookies in settings.".to_string()
-                            } else if is_auth_error(&stderr_buffer) {
-                                "Authentication required. Try enabling browser cookies.".to_string()
-                            } else if is_rate_limit_error(&stderr_buffer) {
-                                "Rate limit exceeded. Please wait and try again.".to_string()
-                            } else if is_network_error(&stderr_buffer) {
-                                "Network error. Check your connection and try again.".to_string()
-                            } else {
-                                format!("Exit code: {}", code)
-                            };
-
-                            error!("Download failed: {} - {}", download_id_clone, error_msg);
-                            window_clone3
-                                .emit(
-                                    "download-complete",
-                                    serde_json::json!({
-                                        "success": false,
-                                        "id": download_id_clone,
-                                        "error": error_msg
-                                    }),
-                                )
-                                .ok();
-                        }
-                    } else {
-                        error!(
-                            "Download terminated without exit code: {}",
-                            download_id_clone
-                        );
-                        window_clone3
-                            .emit(
-                                "download-complete",
-                                serde_json::json!({
-                                    "success": false,
-                                    "id": download_id_clone,
-                                    "error": "Process terminated without exit code"
-                                }),
-                            )
-                            .ok();
-                    }
-                }
-                _ => {}
+impl CompletedFile {
+    /// Stat the file and probe it with ffprobe, keeping whatever succeeds
+    fn gather(path: &str, binary_manager: &BinaryManager) -> Self {
+        let size = match std::fs::metadata(path) {
+            Ok(metadata) => Some(metadata.len()),
+            Err(e) => {
+                warn!("Could not stat completed file {}: {}", path, e);
+                return Self {
+                    path: path.to_string(),
+                    size: None,
+                    media: MediaProbe::default(),
+                };
             }
-        }
-    });
+        };
 
-    Ok(download_id)
-}
+        let media = binary_manager
+            .probe_media(Path::new(path))
+            .unwrap_or_else(|e| {
+                debug!("Could not probe completed file {}: {}", path, e);
+                MediaProbe::default()
+            });
 
-/// Smart download with automatic cookie retry
-/// Attempts download without cookies first, then retries with cookies if authentication is needed
-pub async fn download_content_with_smart_retry(
-    url: String,
-    output_path: String,
-    download_type: DownloadType,
-    window: tauri::WebviewWindow,
-    app: AppHandle,
-    ytdlp_updater: Arc<Mutex<YtdlpUpdater>>,
-    active_downloads: Arc<Mutex<std::collections::HashMap<String, DownloadHandle>>>,
-    binary_manager: Arc<BinaryManager>,
-) -> Result<String, DownloadError> {
-    info!("🔄 Smart download initiated for: {}", url);
+        Self {
+            path: path.to_string(),
+            size,
+            media,
+        }
+    }
+}
 
-    // Attempt 1: Try WITHOUT cookies (works for 90% of videos)
-    info!("📥 Attempt 1: Downloading without authentication...");
-    let browser_config = BrowserConfig {
-        use_cookies: false,
-        browser: None,
-    };
+/// Terminal "download-complete" payload, sent exactly once per download id
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadComplete {
+    pub success: bool,
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<CompletedFile>,
+    /// Both files of a separate-streams download, video first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+}
 
-    match download_content(
-        url.clone(),
-        output_path.clone(),
-        download_type.clone(),
-        browser_config,
-        window.clone(),
-        app.clone(),
-        ytdlp_updater.clone(),
-        active_downloads.clone(),
-        binary_manager.clone(),
-    )
-    .await
-    {
-        Ok(download_id) => {
-            info!("✅ Download succeeded without authentication!");
-            return Ok(download_id);
-        }
-        Err(e) => {
-            // Check if error is authentication-related
-            let error_str = e.to_string();
-            if error_str.contains("Authentication required")
-                || error_str.contains("Sign in")
-                || error_str.contains("Private video")
-                || error_str.contains("login required")
-                || error_str.contains("members-only")
-            {
-                warn!("🔐 Authentication required, retrying with browser cookies...");
-            } else {
-                // Not an auth error, fail immediately
-                error!("❌ Download failed (not auth-related): {}", e);
-                return Err(e);
-            }
+impl DownloadComplete {
+    fn succeeded(id: &str, path: &str, file: Option<CompletedFile>) -> Self {
+        Self {
+            success: true,
+            id: id.to_string(),
+            path: Some(path.to_string()),
+            error: None,
+            file,
+            paths: Vec::new(),
         }
     }
 
-    // Attempt 2-4: Try with cookies from different browsers
-    let browsers_to_try = vec!["firefox", "chrome", "edge"];
+    fn failed(id: &str, error: &str) -> Self {
+        Self {
+            success: false,
+            id: id.to_string(),
+            path: None,
+            error: Some(error.to_string()),
+            file: None,
+            paths: Vec::new(),
+        }
+    }
+}
 
-    for (index, browser_name) in browsers_to_try.iter().enumerate() {
-        info!(
-            "📥 Attempt {}: Trying with {} cookies...",
-            index + 2,
-            browser_name
-        );
+/// Turn yt-dlp's exit code and stderr into the outcome of a download
+fn exit_outcome(
+    code: Option<i32>,
+    stderr: &str,
+    platform: Option<&str>,
+) -> Result<(), DownloadError> {
+    let code = match code {
+        Some(0) => return Ok(()),
+        Some(code) => code,
+        None => {
+            return Err(DownloadError::ProcessFailed(
+                "Process terminated without exit code".to_string(),
+            ))
+        }
+    };
 
-        // Check if browser is installed
-        if !is_browser_installed(browser_name) {
-            info!("⏭️  {} not installed, skipping...", browser_name);
-            continue;
+    // Analyze stderr to provide better error messages
+    Err(classify_ytdlp_error(stderr, platform).unwrap_or_else(|| {
+        let tail = stderr_tail(stderr, 3, 300);
+        if tail.is_empty() {
+            DownloadError::ProcessFailed(format!("Exit code: {}", code))
+        } else {
+            DownloadError::ProcessFailed(format!("Exit code: {}. {}", code, tail))
         }
+    }))
+}
 
-        let browser_config = BrowserConfig {
-            use_cookies: true,
-            browser: Some(browser_name.to_string()),
-        };
+/// App state every download attempt needs, bundled so retries can pass it along
+#[derive(Clone)]
+pub struct DownloadContext {
+    pub window: tauri::WebviewWindow,
+    pub app: AppHandle,
+    pub ytdlp_updater: Arc<Mutex<YtdlpUpdater>>,
+    pub active_downloads: Arc<Mutex<std::collections::HashMap<String, DownloadHandle>>>,
+    pub binary_manager: Arc<BinaryManager>,
+    pub download_queue: Arc<DownloadQueue>,
+    /// Where the last failed attempt stopped in a playlist, for the final summary
+    pub playlist_stop: Arc<std::sync::Mutex<Option<PlaylistStop>>>,
+}
 
-        match download_content(
-            url.clone(),
-            output_path.clone(),
-            download_type.clone(),
-            browser_config,
-            window.clone(),
-            app.clone(),
-            ytdlp_updater.clone(),
-            active_downloads.clone(),
-            binary_manager.clone(),
-        )
-        .await
-        {
-            Ok(download_id) => {
-                info!("✅ Download succeeded with {} cookies!", browser_name);
-                return Ok(download_id);
+/// What stays the same across every attempt of one download
+/// Retries only change the DownloadType and the credentials
+#[derive(Debug, Clone)]
+pub struct DownloadJob {
+    pub id: String,
+    pub url: String,
+    pub output_path: String,
+    pub proxy: Option<String>,
+}
+
+/// Playlist entry a failed attempt stopped at
+#[derive(Debug, Clone)]
+pub struct PlaylistStop {
+    pub item_url: String,
+    /// (current item, total items)
+    pub position: (u32, u32),
+}
+
+/// Handle to an active download process
+pub struct DownloadHandle {
+    pub id: String,
+    pub child: CommandChild,
+    pub url: String,
+    pub output_path: String,
+    pub items: Arc<Mutex<ItemTracker>>,
+    /// yt-dlp writes straight to the destination, so a cancelled file is partial itself
+    pub no_part: bool,
+}
+
+/// Per-item state for a running download, shared with the event loop
+/// Lets cancellation clean up only the in-progress item of a playlist
+#[derive(Debug, Default)]
+pub struct ItemTracker {
+    pub completed_items: u32,
+    pub current_destination: Option<String>,
+    /// Latest progress tick, read by get_batch_status for throughput estimates
+    pub latest_progress: Option<DownloadProgress>,
+    pub phase: DownloadPhase,
+    /// File ffmpeg is writing right now, removed if the download stops mid-merge
+    pub processing_target: Option<ProcessingTarget>,
+}
+
+/// Output of a merge or audio extraction that hasn't finished yet
+#[derive(Debug, Clone)]
+pub struct ProcessingTarget {
+    pub path: String,
+    /// ExtractAudio writes the final file directly; the merger goes through "name.temp.ext"
+    pub in_place: bool,
+    /// Already on disk before this step (e.g. overwriting), so it isn't ours to remove
+    pub existed: bool,
+}
+
+impl ItemTracker {
+    /// State of the download for get_active_downloads
+    pub fn snapshot(&self, id: String, url: String, output_path: String) -> ActiveDownload {
+        let progress = self.latest_progress.as_ref();
+        ActiveDownload {
+            id,
+            url,
+            output_path,
+            phase: self.phase,
+            percent: progress.map(|p| p.percent),
+            speed: progress.map(|p| p.speed.clone()),
+            eta: progress.map(|p| p.eta.clone()),
+        }
+    }
+}
+
+/// A running download, so a reopened window can rebuild its list without past events
+/// Progress fields are null until yt-dlp reports the first tick
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveDownload {
+    pub id: String,
+    pub url: String,
+    pub output_path: String,
+    pub phase: DownloadPhase,
+    pub percent: Option<f32>,
+    pub speed: Option<String>,
+    pub eta: Option<String>,
+}
+
+/// Configuration for browser cookie support
+#[derive(Debug, Clone)]
+pub struct BrowserConfig {
+    pub use_cookies: bool,
+    pub browser: Option<String>,
+    /// Linux keyring holding Chromium's cookie key (e.g. "gnomekeyring")
+    pub keyring: Option<String>,
+    /// Authenticate with credentials from a .netrc file (--netrc) instead of cookies
+    pub use_netrc: bool,
+    /// Validated .netrc path; None lets yt-dlp use ~/.netrc
+    pub netrc_location: Option<String>,
+}
+
+impl BrowserConfig {
+    pub fn new(use_cookies: bool) -> Self {
+        let browser = if use_cookies { detect_browser() } else { None };
+        let keyring = browser.as_deref().and_then(keyring_for_browser);
+
+        Self {
+            use_cookies,
+            browser,
+            keyring,
+            use_netrc: false,
+            netrc_location: None,
+        }
+    }
+
+    /// Use cookies from a specific browser, e.g. one from the retry_browsers setting
+    pub fn for_browser(browser: &str) -> Self {
+        Self {
+            use_cookies: true,
+            browser: Some(browser.to_string()),
+            keyring: keyring_for_browser(browser),
+            use_netrc: false,
+            netrc_location: None,
+        }
+    }
+
+    /// Use username/password credentials from a .netrc file, for sites where
+    /// cookie extraction doesn't apply
+    pub fn netrc(location: Option<String>) -> Self {
+        Self {
+            use_cookies: false,
+            browser: None,
+            keyring: None,
+            use_netrc: true,
+            netrc_location: location,
+        }
+    }
+
+    /// Value for --cookies-from-browser, qualified with the keyring when known
+    fn cookies_from_browser_arg(&self) -> Option<String> {
+        let browser = self.browser.as_ref()?;
+        Some(match &self.keyring {
+            Some(keyring) => format!("{}+{}", browser, keyring),
+            None => browser.clone(),
+        })
+    }
+}
+
+/// Chromium-based browsers encrypt cookies with a key stored in the OS keyring on Linux
+fn keyring_for_browser(browser: &str) -> Option<String> {
+    match browser {
+        "chrome" | "edge" | "brave" => detect_keyring(),
+        _ => None,
+    }
+}
+
+/// Detect which keyring backend is available on Linux
+/// Returns a yt-dlp keyring name: kwallet6, kwallet5, gnomekeyring or basictext
+#[cfg(target_os = "linux")]
+pub fn detect_keyring() -> Option<String> {
+    use std::process::Command;
+
+    let command_exists = |command: &str| {
+        Command::new("which")
+            .arg(command)
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false)
+    };
+
+    let desktop = std::env::var("XDG_CURRENT_DESKTOP")
+        .unwrap_or_default()
+        .to_lowercase();
+    debug!("Detecting keyring for desktop: {}", desktop);
+
+    let keyring = if desktop.contains("kde") && command_exists("kwalletd6") {
+        "kwallet6"
+    } else if desktop.contains("kde") && command_exists("kwalletd5") {
+        "kwallet5"
+    } else if command_exists("gnome-keyring-daemon") {
+        "gnomekeyring"
+    } else {
+        "basictext"
+    };
+
+    info!("Using keyring for cookie decryption: {}", keyring);
+    Some(keyring.to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn detect_keyring() -> Option<String> {
+    None
+}
+
+/// Detect which browser to use for cookies
+pub fn detect_browser() -> Option<String> {
+    info!("Starting browser detection for cookie extraction...");
+
+    // Try to detect installed browsers in order of preference
+    // Firefox first - doesn't have Windows DPAPI cookie encryption issues
+    let browsers = vec!["firefox", "chrome", "edge"];
+
+    for browser in browsers {
+        debug!("Checking for browser: {}", browser);
+        if is_browser_installed(browser) {
+            info!("✓ Detected browser for cookies: {}", browser);
+            return Some(browser.to_string());
+        } else {
+            debug!("✗ Browser not found: {}", browser);
+        }
+    }
+
+    warn!("No supported browser found for cookie extraction");
+    warn!("Checked: Firefox, Chrome, Edge");
+    warn!("Recommendation: Install Firefox for best compatibility on Windows");
+    None
+}
+
+/// Check if a browser is installed (improved detection)
+fn is_browser_installed(browser: &str) -> bool {
+    #[cfg(target_os = "windows")]
+    {
+        use std::process::Command;
+
+        let found = match browser {
+            "firefox" => {
+                // Check multiple Firefox locations
+                let paths = vec![
+                    "C:\\Program Files\\Mozilla Firefox\\firefox.exe",
+                    "C:\\Program Files (x86)\\Mozilla Firefox\\firefox.exe",
+                ];
+
+                for path in &paths {
+                    debug!("  Checking path: {}", path);
+                    if std::path::Path::new(path).exists() {
+                        debug!("  ✓ Found at: {}", path);
+                        return true;
+                    }
+                }
+
+                // Try AppData location
+                if let Ok(appdata) = std::env::var("LOCALAPPDATA") {
+                    let local_path = format!("{}\\Mozilla Firefox\\firefox.exe", appdata);
+                    debug!("  Checking AppData: {}", local_path);
+                    if std::path::Path::new(&local_path).exists() {
+                        debug!("  ✓ Found at: {}", local_path);
+                        return true;
+                    }
+                }
+
+                false
+            }
+            "chrome" => {
+                // Check multiple Chrome locations
+                let paths = vec![
+                    "C:\\Program Files\\Google\\Chrome\\Application\\chrome.exe",
+                    "C:\\Program Files (x86)\\Google\\Chrome\\Application\\chrome.exe",
+                ];
+
+                for path in &paths {
+                    debug!("  Checking path: {}", path);
+                    if std::path::Path::new(path).exists() {
+                        debug!("  ✓ Found at: {}", path);
+                        return true;
+                    }
+                }
+
+                // Try AppData location
+                if let Ok(appdata) = std::env::var("LOCALAPPDATA") {
+                    let local_path =
+                        format!("{}\\Google\\Chrome\\Application\\chrome.exe", appdata);
+                    debug!("  Checking AppData: {}", local_path);
+                    if std::path::Path::new(&local_path).exists() {
+                        debug!("  ✓ Found at: {}", local_path);
+                        return true;
+                    }
+                }
+
+                false
+            }
+            "edge" => {
+                // Check multiple Edge locations
+                let paths = vec![
+                    "C:\\Program Files\\Microsoft\\Edge\\Application\\msedge.exe",
+                    "C:\\Program Files (x86)\\Microsoft\\Edge\\Application\\msedge.exe",
+                ];
+
+                for path in &paths {
+                    debug!("  Checking path: {}", path);
+                    if std::path::Path::new(path).exists() {
+                        debug!("  ✓ Found at: {}", path);
+                        return true;
+                    }
+                }
+
+                // Try where command as fallback
+                debug!("  Trying 'where msedge.exe' command...");
+                let where_result = Command::new("where")
+                    .arg("msedge.exe")
+                    .output()
+                    .map(|output| {
+                        let found = output.status.success();
+                        if found {
+                            let path = String::from_utf8_lossy(&output.stdout);
+                            debug!("  ✓ Found via 'where': {}", path.trim());
+                        }
+                        found
+                    })
+                    .unwrap_or(false);
+
+                if where_result {
+                    return true;
+                }
+
+                false
+            }
+            "brave" => {
+                // Check system-wide and per-user Brave locations
+                let path =
+                    "C:\\Program Files\\BraveSoftware\\Brave-Browser\\Application\\brave.exe";
+                debug!("  Checking path: {}", path);
+                if std::path::Path::new(path).exists() {
+                    debug!("  ✓ Found at: {}", path);
+                    return true;
+                }
+
+                if let Ok(appdata) = std::env::var("LOCALAPPDATA") {
+                    let local_path = format!(
+                        "{}\\BraveSoftware\\Brave-Browser\\Application\\brave.exe",
+                        appdata
+                    );
+                    debug!("  Checking AppData: {}", local_path);
+                    if std::path::Path::new(&local_path).exists() {
+                        debug!("  ✓ Found at: {}", local_path);
+                        return true;
+                    }
+                }
+
+                false
+            }
+            _ => false,
+        };
+
+        found
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let app_name = match browser {
+            "firefox" => "Firefox.app",
+            "chrome" => "Google Chrome.app",
+            "edge" => "Microsoft Edge.app",
+            "brave" => "Brave Browser.app",
+            _ => return false,
+        };
+
+        if std::path::Path::new("/Applications")
+            .join(app_name)
+            .exists()
+        {
+            return true;
+        }
+
+        // Per-user installs (including Homebrew casks with --appdir=~/Applications)
+        if let Some(home) = dirs::home_dir() {
+            let user_path = home.join("Applications").join(app_name);
+            debug!("  Checking user Applications: {:?}", user_path);
+            if user_path.exists() {
+                debug!("  ✓ Found at: {:?}", user_path);
+                return true;
+            }
+        }
+
+        false
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        use std::process::Command;
+
+        let command_exists = |command: &str| {
+            Command::new("which")
+                .arg(command)
+                .output()
+                .map(|output| output.status.success())
+                .unwrap_or(false)
+        };
+
+        // On Linux, check if the browser command is available
+        if command_exists(browser) {
+            return true;
+        }
+
+        // Distro packages use different command names
+        let (commands, flatpak_id, snap_name): (&[&str], &str, &str) = match browser {
+            "firefox" => (&[], "org.mozilla.firefox", "firefox"),
+            "chrome" => (
+                &["google-chrome", "google-chrome-stable"],
+                "com.google.Chrome",
+                "",
+            ),
+            "edge" => (
+                &["microsoft-edge", "microsoft-edge-stable"],
+                "com.microsoft.Edge",
+                "",
+            ),
+            "brave" => (&["brave-browser"], "com.brave.Browser", "brave"),
+            _ => return false,
+        };
+
+        if commands.iter().any(|command| command_exists(command)) {
+            return true;
+        }
+
+        // Flatpak installs, system-wide and per-user
+        let mut flatpak_dirs = vec![std::path::PathBuf::from("/var/lib/flatpak/app")];
+        if let Some(home) = dirs::home_dir() {
+            flatpak_dirs.push(home.join(".local/share/flatpak/app"));
+        }
+        for dir in &flatpak_dirs {
+            let app_dir = dir.join(flatpak_id);
+            debug!("  Checking flatpak: {:?}", app_dir);
+            if app_dir.exists() {
+                debug!("  ✓ Found flatpak at: {:?}", app_dir);
+                return true;
+            }
+        }
+
+        // Snap installs
+        if !snap_name.is_empty() {
+            let snap_path = std::path::Path::new("/snap/bin").join(snap_name);
+            debug!("  Checking snap: {:?}", snap_path);
+            if snap_path.exists() {
+                debug!("  ✓ Found snap at: {:?}", snap_path);
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+/// Human-readable browser name for status messages
+fn browser_display_name(browser: &str) -> &str {
+    match browser {
+        "firefox" => "Firefox",
+        "chrome" => "Chrome",
+        "edge" => "Edge",
+        "brave" => "Brave",
+        other => other,
+    }
+}
+
+/// Highest resolution regardless of codec (VP9/AV1 allowed), as opposed to the
+/// compatibility-focused "best" which prefers H.264 in mp4
+pub const BEST_QUALITY: &str = "best_quality";
+
+/// Whether a quality string is one yt-dlp format selection understands ("best" or a height)
+pub fn is_valid_quality(quality: &str) -> bool {
+    let quality = quality.to_lowercase();
+    quality == "best"
+        || quality == BEST_QUALITY
+        || quality.trim_end_matches('p').parse::<u32>().is_ok()
+}
+
+/// Heights a failing download steps down through, ending at the lowest we'll fall back to
+const QUALITY_LADDER: [u32; 4] = [1080, 720, 480, 360];
+
+/// Most quality downgrades a single download may go through
+pub const MAX_QUALITY_DOWNGRADES: usize = 3;
+
+/// Next quality on the fallback ladder below `quality`
+/// A step is only taken when it would pick a lower stream than `quality` already
+/// does, so steps that land on the same stream are skipped; an empty `available`
+/// list (qualities unknown) allows every step
+pub fn next_lower_quality(quality: &str, available: &[String]) -> Option<String> {
+    let current = quality_height(quality).unwrap_or(u32::MAX);
+    let heights: Vec<u32> = available
+        .iter()
+        .filter(|q| !q.eq_ignore_ascii_case("best"))
+        .filter_map(|q| quality_height(q))
+        .collect();
+    // Height the selector picks at a given cap, like [height<=N]
+    let picked = |cap: u32| {
+        heights
+            .iter()
+            .copied()
+            .filter(|&height| height <= cap)
+            .max()
+    };
+    let current_pick = picked(current);
+
+    QUALITY_LADDER
+        .iter()
+        .copied()
+        .filter(|&step| step < current)
+        .find(|&step| {
+            heights.is_empty()
+                || matches!((picked(step), current_pick), (Some(lower), Some(now)) if lower < now)
+        })
+        .map(|step| format!("{}p", step))
+}
+
+/// Map quality string to yt-dlp format selector
+fn get_quality_format(quality: &str) -> String {
+    match quality.to_lowercase().as_str() {
+        "best" => {
+            "bestvideo[ext=mp4][vcodec^=avc]+bestaudio[ext=m4a]/best[ext=mp4]/best".to_string()
+        }
+        "1080p" | "1080" => {
+            "bestvideo[height<=1080][ext=mp4][vcodec^=avc]+bestaudio[ext=m4a]/best[ext=mp4]"
+                .to_string()
+        }
+        "720p" | "720" => {
+            "bestvideo[height<=720][ext=mp4][vcodec^=avc]+bestaudio[ext=m4a]/best[ext=mp4]"
+                .to_string()
+        }
+        "480p" | "480" => {
+            "bestvideo[height<=480][ext=mp4][vcodec^=avc]+bestaudio[ext=m4a]/best[ext=mp4]"
+                .to_string()
+        }
+        "360p" | "360" => {
+            "bestvideo[height<=360][ext=mp4]+bestaudio[ext=m4a]/best[ext=mp4]".to_string()
+        }
+        _ => {
+            warn!("Unknown quality '{}', using 'best'", quality);
+            "bestvideo[ext=mp4][vcodec^=avc]+bestaudio[ext=m4a]/best[ext=mp4]/best".to_string()
+        }
+    }
+}
+
+/// Extract the maximum height from a quality string ("1080p" -> 1080)
+/// Returns None for "best" or unrecognised values
+fn quality_height(quality: &str) -> Option<u32> {
+    let quality = quality.to_lowercase();
+    if quality == "best" || quality == BEST_QUALITY {
+        return None;
+    }
+
+    match quality.trim_end_matches('p').parse::<u32>() {
+        Ok(height) => Some(height),
+        Err(_) => {
+            warn!("Unknown quality '{}', using 'best'", quality);
+            None
+        }
+    }
+}
+
+/// Map quality string to a yt-dlp format selector suited to the merge container
+/// mp4 keeps the H.264/AAC preference; mkv and webm accept VP9/AV1/Opus streams
+fn get_container_format(quality: &str, container: Container) -> String {
+    let height_filter = quality_height(quality)
+        .map(|height| format!("[height<={}]", height))
+        .unwrap_or_default();
+
+    match container {
+        Container::Mp4 => get_quality_format(quality),
+        Container::Mkv => format!("bestvideo{h}+bestaudio/best{h}", h = height_filter),
+        Container::Webm => format!(
+            "bestvideo{h}[ext=webm]+bestaudio[ext=webm]/bestvideo{h}+bestaudio/best{h}",
+            h = height_filter
+        ),
+    }
+}
+
+/// yt-dlp arguments for a subtitle mode; languages are ignored when subtitles are off
+/// yt-dlp has a single --sub-langs list, so it gets both lists combined; they are
+/// resolved against the video's tracks first (VideoMetadata::resolve_sub_langs) so an
+/// uploaded-only language doesn't pull in auto captions. Uploaded subtitles are skipped
+/// when only auto-generated languages are chosen, and a legacy "auto" entry in either
+/// list turns on auto-generated captions
+fn get_subtitle_args(mode: SubtitleMode, manual: &[String], auto: &[String]) -> Vec<String> {
+    let mut args = Vec::new();
+    if mode == SubtitleMode::None {
+        return args;
+    }
+
+    if matches!(mode, SubtitleMode::Sidecar | SubtitleMode::Both)
+        && (!manual.is_empty() || auto.is_empty())
+    {
+        args.push("--write-subs".to_string());
+    }
+    if matches!(mode, SubtitleMode::Embed | SubtitleMode::Both) {
+        args.push("--embed-subs".to_string());
+    }
+    if !auto.is_empty() || manual.iter().any(|lang| lang == "auto") {
+        args.push("--write-auto-subs".to_string());
+    }
+
+    let mut langs: Vec<&str> = Vec::new();
+    for lang in manual.iter().chain(auto) {
+        if lang != "auto" && !langs.contains(&lang.as_str()) {
+            langs.push(lang);
+        }
+    }
+    if !langs.is_empty() {
+        args.push("--sub-langs".to_string());
+        args.push(langs.join(","));
+    }
+
+    args
+}
+
+/// Format selector downloading the best video-only and audio-only streams as two files
+fn get_separate_streams_format(quality: &str) -> String {
+    let height_filter = quality_height(quality)
+        .map(|height| format!("[height<={}]", height))
+        .unwrap_or_default();
+    format!("bestvideo{},bestaudio", height_filter)
+}
+
+/// Output template for separate streams: "name.video.ext" and "name.audio.ext"
+/// Audio-only formats have no width, which picks the suffix
+fn separate_streams_template(output_path: &str) -> String {
+    Path::new(output_path)
+        .with_extension("%(width&video|audio)s.%(ext)s")
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Files written by a separate-streams download, video first
+/// Unfinished .part/.ytdl leftovers are ignored
+fn separate_stream_paths(output_path: &str) -> Vec<String> {
+    let output = Path::new(output_path);
+    let (dir, stem) = match (output.parent(), output.file_stem()) {
+        (Some(dir), Some(stem)) => (dir, stem.to_string_lossy()),
+        _ => return Vec::new(),
+    };
+
+    ["video", "audio"]
+        .iter()
+        .filter_map(|kind| {
+            let prefix = format!("{}.{}.", stem, kind);
+            std::fs::read_dir(dir)
+                .ok()?
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .find(|path| {
+                    let name = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    name.starts_with(&prefix)
+                        && !name.ends_with(".part")
+                        && !name.ends_with(".ytdl")
+                })
+        })
+        .map(|path| path.to_string_lossy().to_string())
+        .collect()
+}
+
+/// Audio downloads take a dedicated audio stream, else extract it from the best muxed one
+const AUDIO_FORMAT_SELECTOR: &str = "bestaudio/best";
+
+/// Map an audio language code to a format selector, falling back to any best audio
+/// Returns None for codes that aren't plain language tags (e.g. "en", "pt-BR")
+fn get_audio_track_format(language: &str) -> Option<String> {
+    let is_language_tag = !language.is_empty()
+        && language.len() <= 16
+        && language
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if !is_language_tag {
+        warn!("Ignoring invalid audio track language '{}'", language);
+        return None;
+    }
+
+    Some(format!("bestaudio[language={}]/bestaudio/best", language))
+}
+
+/// Pick the path to download to under the collision strategy
+/// Rename finds the first free "name (N).ext"; the other strategies keep the path as is
+pub fn resolve_output_collision(output_path: &str, collision: Collision) -> String {
+    let path = Path::new(output_path);
+    // Output templates are expanded by yt-dlp, so there is no concrete name to check
+    if collision != Collision::Rename || !path.exists() || output_path.contains("%(") {
+        return output_path.to_string();
+    }
+
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+
+    (2..)
+        .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| !candidate.exists())
+        .map(|candidate| candidate.to_string_lossy().to_string())
+        .unwrap_or_else(|| output_path.to_string())
+}
+
+/// Geo bypass arguments; "default" lets yt-dlp pick the country from the extractor
+fn get_geo_bypass_args(geo_bypass: Option<&str>) -> Vec<String> {
+    match geo_bypass {
+        Some(GEO_BYPASS_DEFAULT) => vec!["--geo-bypass".to_string()],
+        Some(country) => vec!["--geo-bypass-country".to_string(), country.to_string()],
+        None => Vec::new(),
+    }
+}
+
+/// Insert "YYYY/YYYY-MM/" folders between an output path's folder and file name
+/// `upload_date` is yt-dlp's YYYYMMDD; today's date is used when it's missing or malformed
+pub fn date_organized_path(output_path: &str, upload_date: Option<&str>) -> PathBuf {
+    let (year, month) = upload_date
+        .and_then(parse_upload_date)
+        .unwrap_or_else(current_year_month);
+
+    let path = Path::new(output_path);
+    let dir = path
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(format!("{:04}", year))
+        .join(format!("{:04}-{:02}", year, month));
+    match path.file_name() {
+        Some(file_name) => dir.join(file_name),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Year and month of a YYYYMMDD date
+fn parse_upload_date(date: &str) -> Option<(i64, u32)> {
+    if date.len() != 8 || !date.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let year = date[..4].parse().ok()?;
+    let month = date[4..6].parse().ok().filter(|m| (1..=12).contains(m))?;
+    Some((year, month))
+}
+
+/// Current UTC year and month
+fn current_year_month() -> (i64, u32) {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or(0);
+    let (year, month, _) = civil_from_days(days as i64);
+    (year, month)
+}
+
+/// Value for --download-sections, or None when the whole media is wanted
+/// Times are trimmed like parse_timestamp trims them when validating
+fn get_download_section(start_time: Option<&str>, end_time: Option<&str>) -> Option<String> {
+    if start_time.is_none() && end_time.is_none() {
+        return None;
+    }
+
+    Some(format!(
+        "*{}-{}",
+        start_time.map(str::trim).unwrap_or("0"),
+        end_time.map(str::trim).unwrap_or("inf")
+    ))
+}
+
+/// Fragments fetched in parallel when the download doesn't set concurrent_fragments
+/// yt-dlp only applies -N to fragmented protocols (HLS/DASH), so single-file
+/// HTTP downloads keep using one connection without probing the format first
+const AUTO_CONCURRENT_FRAGMENTS: u32 = 4;
+
+/// Upper bound for concurrent_fragments; more mostly trips rate limits
+const MAX_CONCURRENT_FRAGMENTS: u32 = 16;
+
+/// Per-download directory for .part files, fragments and pre-merge streams
+fn download_temp_dir(download_id: &str) -> PathBuf {
+    download_temp_root().join(download_id)
+}
+
+/// Folder holding every download's temp folder
+pub fn download_temp_root() -> PathBuf {
+    std::env::temp_dir().join("ripVID")
+}
+
+fn remove_temp_dir(temp_dir: &Path) {
+    if let Err(e) = std::fs::remove_dir_all(temp_dir) {
+        debug!("Could not remove temp directory {:?}: {}", temp_dir, e);
+    }
+}
+
+/// Create the output directory if needed and confirm a file can be written to it
+fn ensure_output_dir_writable(output_path: &str) -> Result<(), DownloadError> {
+    let dir = match Path::new(output_path).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => return Ok(()),
+    };
+
+    let cannot_write = |e: std::io::Error| {
+        DownloadError::Io(std::io::Error::new(
+            e.kind(),
+            format!("cannot write to {}: {}", dir.display(), e),
+        ))
+    };
+
+    std::fs::create_dir_all(dir).map_err(cannot_write)?;
+
+    let probe = dir.join(format!(".ripvid-write-test-{}", Uuid::new_v4()));
+    std::fs::write(&probe, b"").map_err(cannot_write)?;
+    if let Err(e) = std::fs::remove_file(&probe) {
+        warn!("Failed to remove write test file {:?}: {}", probe, e);
+    }
+
+    Ok(())
+}
+
+/// Files saved next to a download that aren't downloads themselves:
+/// streams kept by keep_original and thumbnails written by write_thumbnail
+pub fn is_sidecar_file(path: &Path) -> bool {
+    is_kept_format_file(path) || is_thumbnail_file(path)
+}
+
+/// Whether a file is a single-format stream kept by -k (e.g. "clip.f137.mp4")
+/// rather than a finished download
+fn is_kept_format_file(path: &Path) -> bool {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.rsplit_once(".f"))
+        .map(|(_, format_id)| {
+            !format_id.is_empty() && format_id.chars().all(|c| c.is_ascii_digit())
+        })
+        .unwrap_or(false)
+}
+
+/// Image extensions written as thumbnails, in order of preference
+const THUMBNAIL_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "webp"];
+
+fn is_thumbnail_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| THUMBNAIL_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Thumbnail saved next to a media file under the same base name, preferring jpg
+pub fn find_thumbnail(path: &Path) -> Option<PathBuf> {
+    THUMBNAIL_EXTENSIONS
+        .iter()
+        .map(|ext| path.with_extension(ext))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Build arguments for yt-dlp based on download type
+async fn build_ytdlp_args(
+    url: &str,
+    output_path: &str,
+    temp_dir: &Path,
+    download_type: &DownloadType,
+    browser_config: &BrowserConfig,
+    proxy: Option<&str>,
+    binary_manager: &BinaryManager,
+) -> Vec<String> {
+    let mut args = vec![url.to_string(), "--no-playlist".to_string()];
+
+    // Add ffmpeg location using binary manager
+    match binary_manager.get_binary_path("ffmpeg") {
+        Ok(ffmpeg_path) => {
+            if let Some(ffmpeg_dir) = ffmpeg_path.parent() {
+                let ffmpeg_path_str = strip_extended_path_prefix(ffmpeg_dir);
+                args.push("--ffmpeg-location".to_string());
+                args.push(ffmpeg_path_str.clone());
+                info!("✓ Using runtime-downloaded ffmpeg at: {}", ffmpeg_path_str);
+            } else {
+                warn!("Could not determine ffmpeg directory");
+            }
+        }
+        Err(e) => {
+            warn!("Could not get ffmpeg path: {}. yt-dlp will use system ffmpeg if available", e);
+        }
+    }
+
+    // Add format-specific arguments
+    match download_type {
+        DownloadType::Video {
+            quality,
+            embed_thumbnail,
+            write_thumbnail,
+            thumbnail_format,
+            container,
+            embed_metadata,
+            keep_original,
+            prefer_free_formats,
+            subtitle_mode,
+            manual_sub_langs,
+            auto_sub_langs,
+            separate_streams,
+            ..
+        } => {
+            if *prefer_free_formats {
+                args.push("--prefer-free-formats".to_string());
+            }
+            args.extend(get_subtitle_args(
+                *subtitle_mode,
+                manual_sub_langs,
+                auto_sub_langs,
+            ));
+            args.push("-f".to_string());
+            if *separate_streams {
+                // A comma downloads each format on its own instead of merging them
+                args.push(get_separate_streams_format(quality));
+            } else {
+                args.push(get_container_format(quality, *container));
+                args.push("--merge-output-format".to_string());
+                args.push(container.as_str().to_string());
+            }
+            // mp4/mkv support cover art; ffmpeg performs the embedding
+            if *embed_thumbnail {
+                if *separate_streams {
+                    warn!("Skipping thumbnail embedding: streams are saved unmerged");
+                } else if container.supports_thumbnail_embed() {
+                    args.push("--embed-thumbnail".to_string());
+                } else {
+                    warn!(
+                        "Skipping thumbnail embedding: {} does not support cover art",
+                        container.as_str()
+                    );
+                }
+            }
+            if *embed_metadata {
+                args.push("--embed-metadata".to_string());
+            }
+            if *keep_original {
+                args.push("-k".to_string());
+            }
+            // Video containers take the thumbnail as served; only written files are converted
+            if *write_thumbnail {
+                if let Some(format) = thumbnail_format.convert_to() {
+                    args.push("--convert-thumbnails".to_string());
+                    args.push(format.to_string());
+                }
+            }
+        }
+        DownloadType::Audio {
+            embed_thumbnail,
+            write_thumbnail,
+            thumbnail_format,
+            audio_track,
+            audio_format,
+            embed_metadata,
+            artist_from_uploader,
+            start_time,
+            end_time,
+            ..
+        } => {
+            // Explicit, so sources without an audio-only stream visibly fall back to a muxed one
+            args.push("-f".to_string());
+            args.push(
+                audio_track
+                    .as_deref()
+                    .and_then(get_audio_track_format)
+                    .unwrap_or_else(|| AUDIO_FORMAT_SELECTOR.to_string()),
+            );
+            // The section is cut while downloading, so ExtractAudio converts only the trimmed media
+            if let Some(section) = get_download_section(start_time.as_deref(), end_time.as_deref())
+            {
+                args.push("--download-sections".to_string());
+                args.push(section);
+            }
+            args.push("-x".to_string());
+            args.push("--audio-format".to_string());
+            args.push(audio_format.as_str().to_string());
+            args.push("--audio-quality".to_string());
+            args.push("0".to_string());
+            let embed = *embed_thumbnail && audio_format.supports_thumbnail_embed();
+            if *embed_thumbnail && !embed {
+                warn!(
+                    "Skipping thumbnail embedding: {} does not support cover art",
+                    audio_format.as_str()
+                );
+            } else if embed {
+                args.push("--embed-thumbnail".to_string());
+            }
+            // Convert first so WEBP thumbnails don't silently fail to embed
+            // The same conversion applies to the written thumbnail
+            let convert_to = if embed {
+                thumbnail_format.convert_to_for_embed()
+            } else if *write_thumbnail {
+                thumbnail_format.convert_to()
+            } else {
+                None
+            };
+            if let Some(format) = convert_to {
+                args.push("--convert-thumbnails".to_string());
+                args.push(format.to_string());
+            }
+            if *embed_metadata {
+                if *artist_from_uploader {
+                    args.push("--parse-metadata".to_string());
+                    args.push("uploader:%(artist)s".to_string());
+                }
+                args.push("--embed-metadata".to_string());
+            }
+        }
+    }
+
+    if download_type.write_thumbnail() {
+        args.push("--write-thumbnail".to_string());
+    }
+
+    // Add browser cookie support if enabled
+    if browser_config.use_cookies {
+        if let Some(browser) = browser_config.cookies_from_browser_arg() {
+            args.push("--cookies-from-browser".to_string());
+            args.push(browser.clone());
+            info!("Using cookies from browser: {}", browser);
+        } else {
+            warn!("Browser cookies requested but no browser detected");
+        }
+    }
+
+    if browser_config.use_netrc {
+        args.push("--netrc".to_string());
+        if let Some(location) = &browser_config.netrc_location {
+            args.push("--netrc-location".to_string());
+            args.push(location.clone());
+        }
+        info!("Using .netrc credentials");
+    }
+
+    if download_type.no_part() {
+        args.push("--no-part".to_string());
+    }
+
+    // Rename already picked a free name, so yt-dlp's default of not overwriting is enough
+    match download_type.collision() {
+        Collision::Skip => args.push("--no-overwrites".to_string()),
+        Collision::Overwrite => args.push("--force-overwrites".to_string()),
+        Collision::Rename => {}
+    }
+
+    if let Some(proxy) = proxy {
+        info!("Using proxy for yt-dlp");
+        args.push("--proxy".to_string());
+        args.push(proxy.to_string());
+    }
+
+    args.extend(get_geo_bypass_args(download_type.geo_bypass()));
+
+    // Part of the download type, so every smart-retry attempt sends the same client
+    if let Some(user_agent) = download_type.user_agent() {
+        args.push("--user-agent".to_string());
+        args.push(user_agent.to_string());
+    }
+    if let Some(target) = download_type.impersonate() {
+        info!("Impersonating {} for yt-dlp requests", target);
+        args.push("--impersonate".to_string());
+        args.push(target.to_string());
+    }
+
+    // aria2c splits the download itself, so -N only applies to the native downloader
+    let aria2c = if download_type.use_aria2c() {
+        binary_manager.aria2c_location().await
+    } else {
+        None
+    };
+    if aria2c.is_none() {
+        let fragments = download_type
+            .concurrent_fragments()
+            .unwrap_or(AUTO_CONCURRENT_FRAGMENTS);
+        if fragments > 1 {
+            args.push("-N".to_string());
+            args.push(fragments.to_string());
+        }
+    }
+
+    if download_type.use_aria2c() {
+        match aria2c {
+            Some(aria2c) => {
+                info!("Using aria2c downloader: {}", aria2c);
+                args.push("--downloader".to_string());
+                args.push(aria2c);
+                args.push("--downloader-args".to_string());
+                args.push("aria2c:-x16 -s16".to_string());
+            }
+            None => info!("aria2c requested but not found, using the native downloader"),
+        }
+    }
+
+    // Placed before the output options so a passthrough can never override them
+    let extra_args = download_type.extra_args();
+    if !extra_args.is_empty() {
+        info!("Using extra yt-dlp arguments: {:?}", extra_args);
+        args.extend(extra_args.iter().cloned());
+    }
+
+    // Download and post-process in a temp directory; yt-dlp only moves the
+    // finished file into the library once everything succeeded. Kept source
+    // streams would stay behind in temp and be cleaned up, so skip it for -k
+    let keep_original = matches!(
+        download_type,
+        DownloadType::Video {
+            keep_original: true,
+            ..
+        }
+    );
+    let output_path = if download_type.separate_streams() {
+        separate_streams_template(output_path)
+    } else {
+        output_path.to_string()
+    };
+    let output = Path::new(&output_path);
+    match (output.parent(), output.file_name()) {
+        (Some(home), Some(file_name)) if !home.as_os_str().is_empty() && !keep_original => {
+            args.push("--paths".to_string());
+            args.push(format!("home:{}", home.display()));
+            args.push("--paths".to_string());
+            args.push(format!("temp:{}", temp_dir.display()));
+            args.push("-o".to_string());
+            args.push(file_name.to_string_lossy().to_string());
+        }
+        _ => {
+            args.push("-o".to_string());
+            args.push(output_path.clone());
+        }
+    }
+
+    // Add progress options
+    args.push("--progress".to_string());
+    args.push("--newline".to_string());
+
+    args
+}
+
+/// Parse progress information from yt-dlp output
+fn parse_progress(line: &str) -> Option<DownloadProgress> {
+    if !line.contains("[download]") {
+        return None;
+    }
+
+    // HLS/DASH downloads report "(frag 12/340)" alongside (or instead of) a percent
+    let frag_regex = Regex::new(r"\(frag\s+(\d+)/(\d+)\)").ok()?;
+    let (frag_current, frag_total) = match frag_regex.captures(line) {
+        Some(cap) => (
+            cap.get(1).and_then(|m| m.as_str().parse::<u32>().ok()),
+            cap.get(2).and_then(|m| m.as_str().parse::<u32>().ok()),
+        ),
+        None => (None, None),
+    };
+
+    let percent_regex = Regex::new(r"(\d+(?:\.\d+)?)%").ok()?;
+    let percent = match percent_regex
+        .captures(line)
+        .and_then(|cap| cap.get(1))
+        .and_then(|m| m.as_str().parse::<f32>().ok())
+    {
+        Some(percent) => percent,
+        // No byte percent available: synthesize one from the fragment ratio
+        None => match (frag_current, frag_total) {
+            (Some(current), Some(total)) if total > 0 => {
+                (current as f32 / total as f32 * 100.0).min(100.0)
+            }
+            _ => return None,
+        },
+    };
+
+    let speed_regex = Regex::new(r"at\s+(\S+)").ok()?;
+    let speed = speed_regex
+        .captures(line)
+        .and_then(|cap| cap.get(1))
+        .map(|m| m.as_str().to_string())
+        .unwrap_or_else(|| "---".to_string());
+
+    let eta_regex = Regex::new(r"ETA\s+(\S+)").ok()?;
+    let eta = eta_regex
+        .captures(line)
+        .and_then(|cap| cap.get(1))
+        .map(|m| m.as_str().to_string())
+        .unwrap_or_else(|| "--:--".to_string());
+
+    let total_regex = Regex::new(r"of\s+~?\s*(\d+(?:\.\d+)?[KMGT]?i?B)").ok()?;
+    let total_bytes = total_regex
+        .captures(line)
+        .and_then(|cap| cap.get(1))
+        .and_then(|m| parse_size(m.as_str()))
+        .map(|bytes| bytes as u64);
+    let speed_bytes = speed.strip_suffix("/s").and_then(parse_size);
+
+    Some(DownloadProgress {
+        id: String::new(),
+        percent,
+        speed,
+        eta,
+        frag_current,
+        frag_total,
+        total_bytes,
+        speed_bytes,
+    })
+}
+
+/// Parse a yt-dlp size like "120.50MiB", "800KiB" or "1.2GB" into bytes
+fn parse_size(size: &str) -> Option<f64> {
+    let split = size.find(|c: char| c.is_ascii_alphabetic())?;
+    let (number, unit) = size.split_at(split);
+    let number = number.parse::<f64>().ok()?;
+
+    let multiplier = match unit {
+        "B" => 1.0,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        "TiB" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        _ => return None,
+    };
+
+    Some(number * multiplier)
+}
+
+/// Parse the playlist position from yt-dlp's "Downloading item N of M" line
+fn parse_playlist_item(line: &str) -> Option<(u32, u32)> {
+    if !line.contains("[download]") {
+        return None;
+    }
+
+    let item_regex = Regex::new(r"Downloading item (\d+) of (\d+)").ok()?;
+    let captures = item_regex.captures(line)?;
+    let current = captures.get(1)?.as_str().parse::<u32>().ok()?;
+    let total = captures.get(2)?.as_str().parse::<u32>().ok()?;
+
+    Some((current, total))
+}
+
+/// Parse the file yt-dlp is currently writing from its "Destination:" line
+fn parse_destination(line: &str) -> Option<String> {
+    let destination_regex = Regex::new(r"\[download\] Destination:\s+(.+)$").ok()?;
+    let destination = destination_regex
+        .captures(line.trim_end())?
+        .get(1)?
+        .as_str();
+
+    Some(destination.to_string())
+}
+
+/// Parse the file an ffmpeg step is about to write, and whether it writes it in place
+/// "[Merger] Merging formats into "x.mp4"" or "[ExtractAudio] Destination: x.mp3"
+fn parse_processing_target(line: &str) -> Option<(String, bool)> {
+    let line = line.trim();
+    if let Some(path) = line.strip_prefix("[Merger] Merging formats into ") {
+        return Some((path.trim_matches('"').to_string(), false));
+    }
+    let path = line.strip_prefix("[ExtractAudio] Destination: ")?;
+    Some((path.to_string(), true))
+}
+
+/// The intermediate file yt-dlp's ffmpeg steps write before replacing the target
+/// "video.mp4" becomes "video.temp.mp4"
+fn ffmpeg_temp_path(path: &str) -> String {
+    let file = Path::new(path);
+    match (file.file_stem(), file.extension()) {
+        (Some(stem), Some(ext)) => file
+            .with_file_name(format!(
+                "{}.temp.{}",
+                stem.to_string_lossy(),
+                ext.to_string_lossy()
+            ))
+            .to_string_lossy()
+            .to_string(),
+        _ => format!("{}.temp", path),
+    }
+}
+
+/// Parse the message from a yt-dlp "WARNING:" line
+fn parse_warning(line: &str) -> Option<&str> {
+    let message = line.trim().strip_prefix("WARNING:")?.trim();
+    if message.is_empty() {
+        None
+    } else {
+        Some(message)
+    }
+}
+
+/// Retry a download operation with exponential backoff
+async fn retry_with_backoff<F, Fut, T>(operation: F, max_attempts: u32) -> Result<T, DownloadError>
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = Result<T, DownloadError>>,
+{
+    let mut attempts = 0;
+    let mut delay = Duration::from_secs(1);
+
+    loop {
+        attempts += 1;
+        debug!("Attempt {} of {}", attempts, max_attempts);
+
+        match operation().await {
+            Ok(result) => return Ok(result),
+            Err(error) => {
+                if attempts >= max_attempts || !is_retryable_error(&error) {
+                    error!("Operation failed after {} attempts: {}", attempts, error);
+                    return Err(error);
+                }
+
+                warn!(
+                    "Attempt {} failed: {}. Retrying in {:?}...",
+                    attempts, error, delay
+                );
+
+                tokio::time::sleep(delay).await;
+
+                // Exponential backoff: 1s, 2s, 4s, 8s, etc.
+                delay *= 2;
+            }
+        }
+    }
+}
+
+/// Resolve yt-dlp: the updated binary when available, else the bundled sidecar
+async fn ytdlp_command(
+    app: &AppHandle,
+    ytdlp_updater: &Arc<Mutex<YtdlpUpdater>>,
+) -> Result<Command, DownloadError> {
+    // Get yt-dlp path with retry
+    let ytdlp_path = retry_with_backoff(
+        || async {
+            let updater = ytdlp_updater.lock().await;
+            updater
+                .ensure_updated()
+                .await
+                .map_err(|e| DownloadError::ProcessFailed(format!("Failed to get yt-dlp: {}", e)))
+        },
+        3,
+    )
+    .await
+    .unwrap_or_else(|_| PathBuf::from("yt-dlp"));
+
+    if ytdlp_path == PathBuf::from("yt-dlp") {
+        info!("Using bundled yt-dlp sidecar");
+        app.shell()
+            .sidecar("yt-dlp")
+            .map_err(|e| DownloadError::Sidecar(e.to_string()))
+    } else {
+        info!("Using updated yt-dlp from: {:?}", ytdlp_path);
+        Ok(app.shell().command(ytdlp_path))
+    }
+}
+
+/// Unified download function for both video and audio
+/// Resolves once yt-dlp exits, with the classified error on failure
+/// With `resume` set, the partial data kept from the previous attempt is continued
+pub async fn download_content(
+    ctx: &DownloadContext,
+    job: &DownloadJob,
+    download_type: DownloadType,
+    browser_config: BrowserConfig,
+    resume: bool,
+) -> Result<String, DownloadError> {
+    let download_id = job.id.clone();
+    let url = job.url.clone();
+    let output_path = job.output_path.clone();
+    let window = &ctx.window;
+    let active_downloads = &ctx.active_downloads;
+    info!(
+        "Starting download: id={}, type={:?}, url={}, output={}",
+        download_id, download_type, url, output_path
+    );
+
+    // Catch permission problems and read-only mounts before yt-dlp fails on them
+    ensure_output_dir_writable(&output_path)?;
+
+    // Power-user passthrough is checked here so history retries are covered too
+    validate_extra_args(download_type.extra_args()).map_err(DownloadError::InvalidInput)?;
+    validate_subtitle_langs(download_type.manual_sub_langs())
+        .and_then(|_| validate_subtitle_langs(download_type.auto_sub_langs()))
+        .map_err(DownloadError::InvalidInput)?;
+    if let Some(country) = download_type.geo_bypass() {
+        validate_geo_bypass(country).map_err(DownloadError::InvalidInput)?;
+    }
+    if let Some(user_agent) = download_type.user_agent() {
+        validate_user_agent(user_agent).map_err(DownloadError::InvalidInput)?;
+    }
+    if let Some(target) = download_type.impersonate() {
+        validate_impersonate(target).map_err(DownloadError::InvalidInput)?;
+    }
+    if let Some(fragments) = download_type.concurrent_fragments() {
+        if fragments == 0 || fragments > MAX_CONCURRENT_FRAGMENTS {
+            return Err(DownloadError::InvalidInput(format!(
+                "Concurrent fragments must be between 1 and {}",
+                MAX_CONCURRENT_FRAGMENTS
+            )));
+        }
+    }
+    if let DownloadType::Audio {
+        start_time,
+        end_time,
+        ..
+    } = &download_type
+    {
+        validate_time_range(start_time.as_deref(), end_time.as_deref())
+            .map_err(DownloadError::InvalidInput)?;
+    }
+
+    // Smart retry reuses the id, so the previous attempt must have fully finished
+    if active_downloads.lock().await.contains_key(&download_id) {
+        return Err(DownloadError::InvalidInput(format!(
+            "Download {} is already running",
+            download_id
+        )));
+    }
+
+    // Intermediate files live here until yt-dlp moves the finished file into place
+    // Start empty so fragments from an earlier attempt can't be picked up again,
+    // unless this attempt is meant to continue them
+    let temp_dir = download_temp_dir(&download_id);
+    if temp_dir.exists() && !resume {
+        std::fs::remove_dir_all(&temp_dir).ok();
+    }
+    if let Err(e) = std::fs::create_dir_all(&temp_dir) {
+        warn!("Failed to create temp directory {:?}: {}", temp_dir, e);
+    }
+
+    // A cancel recorded while this attempt was being prepared
+    if ctx.download_queue.is_cancelled(&download_id) {
+        return Err(DownloadError::Cancelled);
+    }
+
+    // Build arguments
+    let mut args = build_ytdlp_args(
+        &url,
+        &output_path,
+        &temp_dir,
+        &download_type,
+        &browser_config,
+        job.proxy.as_deref(),
+        &ctx.binary_manager,
+    )
+    .await;
+    if resume {
+        args.push("--continue".to_string());
+    }
+    debug!("yt-dlp args prepared (count: {})", args.len());
+
+    // Spawn yt-dlp process
+    let (rx, child) = ytdlp_command(&ctx.app, &ctx.ytdlp_updater)
+        .await?
+        .args(&args)
+        .spawn()
+        .map_err(|e| DownloadError::ProcessFailed(e.to_string()))?;
+
+    // Store download handle for potential cancellation
+    let items = Arc::new(Mutex::new(ItemTracker::default()));
+    {
+        let mut downloads = active_downloads.lock().await;
+        downloads.insert(
+            download_id.clone(),
+            DownloadHandle {
+                id: download_id.clone(),
+                child,
+                url: url.clone(),
+                output_path: output_path.clone(),
+                items: items.clone(),
+                no_part: download_type.no_part(),
+            },
+        );
+        info!("Stored download handle: {}", download_id);
+    }
+
+    // cancel_download records the cancel before looking for the handle, so a cancel
+    // that missed the handle is seen here; the Terminated event then reports it
+    if ctx.download_queue.is_cancelled(&download_id) {
+        if let Some(handle) = active_downloads.lock().await.remove(&download_id) {
+            info!("Download {} was cancelled while starting", download_id);
+            handle.child.kill().ok();
+        }
+    }
+
+    // Emit download started event
+    window
+        .emit(
+            "download-started",
+            serde_json::json!({
+                "id": download_id,
+                "path": output_path
+            }),
+        )
+        .ok();
+    DownloadUpdate::new(&download_id, DownloadPhase::Started).emit(window);
+
+    if let DownloadType::Video { quality, .. } = &download_type {
+        if quality.eq_ignore_ascii_case(BEST_QUALITY) {
+            let message = "Best quality may use VP9/AV1 video in MKV, which some players and editors can't open. Choose \"best\" for maximum compatibility.";
+            info!("{}", message);
+            window
+                .emit(
+                    "download-status",
+                    serde_json::json!({
+                        "id": download_id,
+                        "message": message
+                    }),
+                )
+                .ok();
+        }
+    }
+
+    let process = ProcessEvents {
+        download_id: download_id.clone(),
+        url: url.clone(),
+        output_path: output_path.clone(),
+        temp_dir,
+        platform: detect_platform(&url),
+        no_part: download_type.no_part(),
+        separate_streams: download_type.separate_streams(),
+        items,
+        playlist_stop: ctx.playlist_stop.clone(),
+    };
+    let manager = ctx.binary_manager.clone();
+    let probe = move |path: &str| CompletedFile::gather(path, &manager);
+
+    // Wait for yt-dlp to finish so callers (smart retry, the queue) see the real outcome
+    tauri::async_runtime::spawn(handle_process_events(
+        rx,
+        window.clone(),
+        process,
+        active_downloads.clone(),
+        probe,
+    ))
+    .await
+    .unwrap_or_else(|_| {
+        Err(DownloadError::ProcessFailed(
+            "Download task ended unexpectedly".to_string(),
+        ))
+    })
+}
+
+/// What handle_process_events needs to know about the yt-dlp process it follows
+struct ProcessEvents {
+    download_id: String,
+    url: String,
+    output_path: String,
+    temp_dir: PathBuf,
+    platform: Option<&'static str>,
+    no_part: bool,
+    separate_streams: bool,
+    items: Arc<Mutex<ItemTracker>>,
+    playlist_stop: Arc<std::sync::Mutex<Option<PlaylistStop>>>,
+}
+
+/// Turn yt-dlp's output into UI events until the process exits
+/// Reads from `events` rather than the process so tests can feed it mock output.
+/// `active` is the map cancel_download takes the handle from, and `probe` describes
+/// the finished file
+async fn handle_process_events<E, H, P>(
+    mut rx: tauri::async_runtime::Receiver<CommandEvent>,
+    sink: E,
+    process: ProcessEvents,
+    active: Arc<Mutex<std::collections::HashMap<String, H>>>,
+    probe: P,
+) -> Result<String, DownloadError>
+where
+    E: EventSink,
+    P: Fn(&str) -> CompletedFile + Clone + Send + 'static,
+{
+    let ProcessEvents {
+        download_id,
+        url,
+        output_path,
+        temp_dir,
+        platform,
+        no_part,
+        separate_streams,
+        items,
+        playlist_stop,
+    } = process;
+    let mut stderr_buffer = String::new();
+    let mut playlist_position: Option<(u32, u32)> = None;
+    // Entry being extracted, named in the playlist summary if it fails
+    let mut item_url = url;
+    let mut throttle = ProgressThrottle::default();
+    // yt-dlp repeats some warnings for every format or playlist item
+    let mut seen_warnings = std::collections::HashSet::new();
+
+    while let Some(event) = rx.recv().await {
+        match event {
+            CommandEvent::Stdout(line_data) => {
+                let line = String::from_utf8_lossy(&line_data).to_string();
+                debug!("[stdout] {}", line);
+
+                // ffmpeg prints nothing while it runs, so the next line means it finished
+                if !line.trim().is_empty() {
+                    items.lock().await.processing_target =
+                        parse_processing_target(&line).map(|(path, in_place)| ProcessingTarget {
+                            existed: Path::new(&path).exists(),
+                            path,
+                            in_place,
+                        });
+                }
+
+                // Detect merger/processing phase
+                if line.contains("[Merger]")
+                    || line.contains("Merging formats")
+                    || line.contains("[ffmpeg]")
+                {
+                    info!("Video processing phase detected");
+                    items.lock().await.phase = DownloadPhase::Processing;
+                    sink.send(
+                        "download-processing",
+                        serde_json::json!({
+                            "message": "Processing video...",
+                            "id": download_id
+                        }),
+                    )
+                    .ok();
+                    DownloadUpdate::new(&download_id, DownloadPhase::Processing)
+                        .with_message("Processing video...")
+                        .emit(&sink);
+                }
+
+                // Track playlist position so the overall bar stays stable
+                if let Some((current, total)) = parse_playlist_item(&line) {
+                    info!("Playlist item {} of {}", current, total);
+                    // A playlist URL is reported like a batch, keyed by the download id
+                    if playlist_position.is_none() {
+                        sink.send(
+                            "batch-started",
+                            serde_json::json!({ "batchId": download_id, "total": total }),
+                        )
+                        .ok();
+                    }
+                    playlist_position = Some((current, total));
+                    {
+                        let mut tracker = items.lock().await;
+                        tracker.completed_items = current.saturating_sub(1);
+                        tracker.current_destination = None;
+                    }
+                    sink.send(
+                        "download-playlist-progress",
+                        PlaylistProgress {
+                            id: download_id.clone(),
+                            ..PlaylistProgress::new(current, total, 0.0)
+                        },
+                    )
+                    .ok();
+                }
+
+                if let Some(entry) = line.split("Extracting URL: ").nth(1) {
+                    item_url = entry.trim().to_string();
+                }
+
+                // Remember the in-progress file so cancel only removes its temp data
+                if let Some(destination) = parse_destination(&line) {
+                    items.lock().await.current_destination = Some(destination);
+                }
+
+                // Parse and emit progress
+                if let Some(mut progress) = parse_progress(&line).filter(|progress| {
+                    throttle.should_emit(progress.percent, std::time::Instant::now())
+                }) {
+                    progress.id = download_id.clone();
+                    {
+                        let mut tracker = items.lock().await;
+                        tracker.latest_progress = Some(progress.clone());
+                        tracker.phase = DownloadPhase::Downloading;
+                    }
+                    sink.send("download-progress", &progress).ok();
+                    DownloadUpdate::progress(&download_id, &progress).emit(&sink);
+
+                    if let Some((current, total)) = playlist_position {
+                        sink.send(
+                            "download-playlist-progress",
+                            PlaylistProgress {
+                                id: download_id.clone(),
+                                ..PlaylistProgress::new(current, total, progress.percent)
+                            },
+                        )
+                        .ok();
+                    }
+                }
             }
-            Err(e) => {
-                let error_str = e.to_string();
-                if error_str.contains("DPAPI") || error_str.contains("decrypt") {
-                    warn!(
-                        "⚠️  {} cookie decryption failed (DPAPI issue), trying next browser...",
-                        browser_name
+            CommandEvent::Stderr(line_data) => {
+                let line = String::from_utf8_lossy(&line_data).to_string();
+                debug!("[stderr] {}", line);
+                stderr_buffer.push_str(&line);
+                stderr_buffer.push('\n');
+
+                // Non-fatal notices, e.g. a fallback to a lower quality
+                if let Some(warning) = parse_warning(&line) {
+                    if seen_warnings.insert(warning.to_string()) {
+                        sink.send(
+                            "download-warning",
+                            serde_json::json!({
+                                "id": download_id,
+                                "message": warning
+                            }),
+                        )
+                        .ok();
+                    }
+                }
+
+                // Emit status messages for important events
+                if line.contains("Sleeping") || line.contains("rate limit") {
+                    sink.send(
+                        "download-status",
+                        serde_json::json!({
+                            "id": download_id,
+                            "message": line
+                        }),
+                    )
+                    .ok();
+                    DownloadUpdate::new(&download_id, DownloadPhase::Downloading)
+                        .with_message(line.trim())
+                        .emit(&sink);
+                }
+            }
+            CommandEvent::Terminated(payload) => {
+                // Remove from active downloads
+                // A missing handle means cancel_download already took it and
+                // emitted "download-cancelled", so don't report a failure too
+                let was_active = {
+                    let mut downloads = active.lock().await;
+                    downloads.remove(&download_id).is_some()
+                };
+
+                if !was_active {
+                    remove_temp_dir(&temp_dir);
+                    info!(
+                        "Download {} terminated after cancellation (code {:?}), suppressing completion event",
+                        download_id, payload.code
                     );
-                    continue;
-                } else {
-                    // Different error, might be the actual problem
-                    error!("❌ Download failed with {}: {}", browser_name, e);
-                    // Try next browser anyway
-                    continue;
+                    if let Some(position) = playlist_position {
+                        let summary = BatchSummary::for_playlist(
+                            &download_id,
+                            &item_url,
+                            position,
+                            Err(&DownloadError::Cancelled),
+                        );
+                        sink.send("batch-complete", summary).ok();
+                    }
+                    return Err(DownloadError::Cancelled);
+                }
+                info!("Removed download handle: {}", download_id);
+
+                let result = match exit_outcome(payload.code, &stderr_buffer, platform) {
+                    Ok(()) => {
+                        info!("Download completed successfully: {}", download_id);
+                        // Unmerged downloads report the video file as the main path
+                        let stream_paths = if separate_streams {
+                            separate_stream_paths(&output_path)
+                        } else {
+                            Vec::new()
+                        };
+                        let path = stream_paths
+                            .first()
+                            .cloned()
+                            .unwrap_or_else(|| output_path.clone());
+                        let probe = probe.clone();
+                        let probe_path = path.clone();
+                        let file = tokio::task::spawn_blocking(move || probe(&probe_path))
+                            .await
+                            .ok();
+                        sink.send(
+                            "download-complete",
+                            DownloadComplete {
+                                paths: stream_paths,
+                                ..DownloadComplete::succeeded(&download_id, &path, file)
+                            },
+                        )
+                        .ok();
+                        DownloadUpdate {
+                            percent: Some(100.0),
+                            ..DownloadUpdate::new(&download_id, DownloadPhase::Completed)
+                        }
+                        .emit(&sink);
+                        Ok(download_id.clone())
+                    }
+                    Err(error) => {
+                        // Log full stderr for debugging
+                        error!(
+                            "Download failed with exit code {:?}. Full stderr output:",
+                            payload.code
+                        );
+                        error!("{}", stderr_buffer);
+
+                        if matches!(error, DownloadError::PlayerError(_)) {
+                            sink.send(
+                                "download-update-suggested",
+                                serde_json::json!({
+                                    "id": download_id,
+                                    "command": "force_update_binaries",
+                                    "message": "yt-dlp needs an update to download from this site"
+                                }),
+                            )
+                            .ok();
+                        }
+
+                        // Not reported yet: a retry may follow, see report_download_failure
+                        error!("Download attempt failed: {} - {}", download_id, error);
+                        Err(error)
+                    }
+                };
+
+                // Leftovers from a failed attempt would collide with the next retry,
+                // except after a dropped connection where the next attempt resumes them
+                match &result {
+                    Err(error) if !no_part && is_resumable_error(error) => {
+                        info!("Keeping partial data of {} for resume", download_id);
+                    }
+                    Err(_) => {
+                        remove_temp_dir(&temp_dir);
+                        cleanup_partial_files(&items, &output_path, no_part).await;
+                    }
+                    // Whatever is left in the temp directory is partial by now
+                    Ok(_) => remove_temp_dir(&temp_dir),
+                }
+
+                if let Some(position) = playlist_position {
+                    if result.is_ok() {
+                        let summary =
+                            BatchSummary::for_playlist(&download_id, &item_url, position, Ok(()));
+                        sink.send("batch-complete", summary).ok();
+                    } else if let Ok(mut stop) = playlist_stop.lock() {
+                        *stop = Some(PlaylistStop { item_url, position });
+                    }
+                }
+
+                return result;
+            }
+            _ => {}
+        }
+    }
+
+    Err(DownloadError::ProcessFailed(
+        "Download task ended unexpectedly".to_string(),
+    ))
+}
+
+/// Times a download is resumed after a dropped connection before giving up
+pub const MAX_RESUME_ATTEMPTS: u32 = 3;
+
+/// download_content, resumed from the partial file when the connection drops
+/// Backs off 1s, 2s, 4s between attempts, the same as retry_with_backoff. A cancel
+/// while waiting has no process to kill, so the queue's record is checked before
+/// every attempt. Resumes are reported under smart retry's `attempt` number
+async fn download_content_resuming(
+    ctx: &DownloadContext,
+    job: &DownloadJob,
+    download_type: &DownloadType,
+    browser_config: &BrowserConfig,
+    attempt: u32,
+) -> Result<String, DownloadError> {
+    let mut resumes = 0;
+    let mut delay = Duration::from_secs(1);
+
+    loop {
+        if ctx.download_queue.is_cancelled(&job.id) {
+            info!("Download {} was cancelled between attempts", job.id);
+            if resumes > 0 {
+                discard_partial_download(job, download_type).await;
+            }
+            return Err(DownloadError::Cancelled);
+        }
+
+        let result = download_content(
+            ctx,
+            job,
+            download_type.clone(),
+            browser_config.clone(),
+            resumes > 0,
+        )
+        .await;
+
+        let error = match result {
+            Err(error) if is_resumable_error(&error) => error,
+            other => return other,
+        };
+
+        if resumes >= MAX_RESUME_ATTEMPTS {
+            warn!(
+                "Giving up on resuming {} after {} attempts: {}",
+                job.id, resumes, error
+            );
+            discard_partial_download(job, download_type).await;
+            return Err(error);
+        }
+        resumes += 1;
+
+        warn!(
+            "🔌 Download {} interrupted: {}. Resuming in {:?} ({}/{})...",
+            job.id, error, delay, resumes, MAX_RESUME_ATTEMPTS
+        );
+        ctx.window
+            .emit(
+                "download-retry",
+                serde_json::json!({
+                    "id": job.id,
+                    "attempt": attempt,
+                    "resume": resumes,
+                    "browser": null,
+                    "reason": "Connection lost, resuming download"
+                }),
+            )
+            .ok();
+
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+    }
+}
+
+/// Remove the partial data kept for a resume that will never happen
+async fn discard_partial_download(job: &DownloadJob, download_type: &DownloadType) {
+    remove_temp_dir(&download_temp_dir(&job.id));
+    let no_items = Mutex::new(ItemTracker::default());
+    cleanup_partial_files(&no_items, &job.output_path, download_type.no_part()).await;
+}
+
+/// Smart download with automatic cookie retry
+/// Attempts download without cookies first, then retries with cookies if authentication is needed.
+/// `download_id` is used for every attempt, so a cancel reaches whichever one is running
+pub async fn download_content_with_smart_retry(
+    ctx: &DownloadContext,
+    download_id: String,
+    url: String,
+    output_path: String,
+    download_type: DownloadType,
+    settings: Arc<SettingsManager>,
+) -> Result<String, DownloadError> {
+    info!("🔄 Smart download initiated for: {}", url);
+    let settings = settings.get().await;
+    let window = &ctx.window;
+    let binary_manager = &ctx.binary_manager;
+    let download_queue = &ctx.download_queue;
+
+    // Fail fast instead of wasting bandwidth on a download that can't be merged
+    // Re-downloading is left to retry_binary_download, which goes through the setup
+    // state, so concurrent downloads don't each start their own binary download
+    if download_type.requires_ffmpeg() && !binary_manager.is_ffmpeg_available().await {
+        warn!("ffmpeg not found, download needs it");
+        return Err(DownloadError::MissingDependency(
+            "ffmpeg is not installed. Re-download ffmpeg from the setup screen, then retry the download.".to_string(),
+        ));
+    }
+
+    // Static ffmpeg builds can lack an encoder; say so before downloading anything
+    if let DownloadType::Audio { audio_format, .. } = &download_type {
+        let manager = binary_manager.clone();
+        let format = *audio_format;
+        if let Ok(Ok(encoders)) =
+            tokio::task::spawn_blocking(move || manager.ffmpeg_encoders()).await
+        {
+            if !encoders.supports(format) {
+                return Err(DownloadError::MissingDependency(format!(
+                    "The installed ffmpeg can't encode {}. Choose another audio format, or run setup again to get a full ffmpeg build.",
+                    format.as_str().to_uppercase()
+                )));
+            }
+        }
+    }
+
+    // Queue the download; the id stays the same across every retry attempt
+    let job = DownloadJob {
+        id: download_id.clone(),
+        url,
+        output_path,
+        proxy: resolve_proxy(settings.proxy.as_deref()),
+    };
+    download_queue
+        .enqueue(QueuedDownload {
+            id: download_id.clone(),
+            url: job.url.clone(),
+            output_path: job.output_path.clone(),
+        })
+        .await;
+
+    window
+        .emit(
+            "download-queued",
+            serde_json::json!({
+                "id": download_id,
+                "path": job.output_path
+            }),
+        )
+        .ok();
+
+    // Hold the slot until every attempt has finished
+    let _slot = download_queue.wait_for_slot(&download_id).await?;
+
+    // Attempt 1: Try WITHOUT cookies (works for 90% of videos)
+    let mut attempt = 1;
+    info!("📥 Attempt 1: Downloading without authentication...");
+    let browser_config = BrowserConfig::new(false);
+
+    // A player/extraction error gets one retry after updating yt-dlp
+    let mut ytdlp_updated = false;
+    let first_attempt = loop {
+        let result =
+            download_content_resuming(ctx, &job, &download_type, &browser_config, attempt).await;
+
+        if ytdlp_updated || !matches!(result, Err(DownloadError::PlayerError(_))) {
+            break result;
+        }
+        ytdlp_updated = true;
+
+        if !update_ytdlp_for_retry(&ctx.ytdlp_updater, window, &download_id).await {
+            break result;
+        }
+        attempt += 1;
+        info!(
+            "🔁 yt-dlp updated, retrying download (attempt {})...",
+            attempt
+        );
+        emit_retry(
+            window,
+            &download_id,
+            attempt,
+            None,
+            "Downloader updated, retrying",
+        );
+    };
+
+    let auth_error = match first_attempt {
+        Ok(download_id) => {
+            info!("✅ Download succeeded without authentication!");
+            return Ok(download_id);
+        }
+        Err(DownloadError::Cancelled) => return Err(DownloadError::Cancelled),
+        // Region-locked: one more try with geo bypass, unless the user already chose a country
+        Err(e @ DownloadError::GeoRestricted(_)) => {
+            if download_type.geo_bypass().is_some() {
+                return Err(e);
+            }
+            warn!("🌍 Content is region-locked, retrying with geo bypass...");
+            attempt += 1;
+            emit_retry(
+                window,
+                &download_id,
+                attempt,
+                None,
+                "Region restricted, retrying with geo bypass",
+            );
+
+            return download_content_resuming(
+                ctx,
+                &job,
+                &download_type.with_geo_bypass(),
+                &browser_config,
+                attempt,
+            )
+            .await;
+        }
+        // classify_ytdlp_error maps sign-in, private and members-only errors here
+        Err(e @ DownloadError::Authentication(_)) => {
+            if !settings.cookie_retry && !settings.use_netrc {
+                // User opted out of the app reading browser cookie stores
+                info!("🔐 Authentication required, but cookie retry is disabled");
+                return Err(e);
+            }
+            warn!("🔐 Authentication required, retrying with credentials...");
+            e
+        }
+        Err(e) => {
+            // Not an auth error, fail immediately
+            error!("❌ Download failed (not auth-related): {}", e);
+            return Err(e);
+        }
+    };
+
+    // Next: Try with .netrc credentials, if configured
+    if settings.use_netrc {
+        match netrc_location(&settings) {
+            Ok(location) => {
+                attempt += 1;
+                info!("📥 Attempt {}: Trying with .netrc credentials...", attempt);
+                emit_retry(
+                    window,
+                    &download_id,
+                    attempt,
+                    None,
+                    "Authentication required, trying .netrc credentials",
+                );
+
+                match download_content_resuming(
+                    ctx,
+                    &job,
+                    &download_type,
+                    &BrowserConfig::netrc(location),
+                    attempt,
+                )
+                .await
+                {
+                    Ok(download_id) => {
+                        info!("✅ Download succeeded with .netrc credentials!");
+                        return Ok(download_id);
+                    }
+                    Err(DownloadError::Cancelled) => return Err(DownloadError::Cancelled),
+                    Err(e) => error!("❌ Download failed with .netrc credentials: {}", e),
                 }
             }
+            Err(e) => warn!("⏭️  Skipping .netrc: {}", e),
+        }
+    }
+
+    // Then: Try with cookies from each configured browser, in order
+    let browsers_to_try = if settings.cookie_retry {
+        settings.retry_browsers
+    } else {
+        Vec::new()
+    };
+
+    let mut tried_browser = false;
+    for browser_name in browsers_to_try.iter() {
+        // Check if browser is installed
+        if !is_browser_installed(browser_name) {
+            info!("⏭️  {} not installed, skipping...", browser_name);
+            continue;
+        }
+
+        attempt += 1;
+        tried_browser = true;
+        info!(
+            "📥 Attempt {}: Trying with {} cookies...",
+            attempt, browser_name
+        );
+
+        // Tell the UI this is a retry, not a fresh failure
+        emit_retry(
+            window,
+            &download_id,
+            attempt,
+            Some(browser_name),
+            &format!(
+                "Authentication required, trying {} cookies",
+                browser_display_name(browser_name)
+            ),
+        );
+
+        let browser_config = BrowserConfig::for_browser(browser_name);
+
+        match download_content_resuming(ctx, &job, &download_type, &browser_config, attempt).await {
+            Ok(download_id) => {
+                info!("✅ Download succeeded with {} cookies!", browser_name);
+                return Ok(download_id);
+            }
+            Err(DownloadError::Cancelled) => return Err(DownloadError::Cancelled),
+            // Cookie decryption failures included; the next browser may still work
+            Err(e) => error!("❌ Download failed with {}: {}", browser_name, e),
+        }
+    }
+
+    // Without a browser attempt the original error still says what went wrong
+    if !tried_browser {
+        error!(
+            "❌ Download failed, no browser cookies were tried: {}",
+            auth_error
+        );
+        return Err(auth_error);
+    }
+
+    // All attempts failed
+    error!("❌ All download attempts failed");
+    Err(DownloadError::Authentication(
+        "Unable to download this video. It may require login. Please verify the video is accessible in your browser, or install Firefox and log into the website there for automatic authentication.".to_string()
+    ))
+}
+
+/// Resolve the configured .netrc path, checked like any other user-supplied path
+/// None means yt-dlp falls back to ~/.netrc
+fn netrc_location(settings: &AppSettings) -> Result<Option<String>, String> {
+    let location = match settings
+        .netrc_location
+        .as_deref()
+        .map(str::trim)
+        .filter(|location| !location.is_empty())
+    {
+        Some(location) => location,
+        None => return Ok(None),
+    };
+
+    let allowed_directories: Vec<PathBuf> = settings
+        .allowed_directories
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+    let path = validate_path(location, false, &allowed_directories)?;
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+/// Length of the slice fetched by test_download
+const TEST_DOWNLOAD_SECTION: &str = "*0-5";
+
+/// Time allowed for test_download before yt-dlp is killed
+pub const TEST_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Download only the first seconds of a URL into a throwaway temp folder
+/// Checks that cookies, authentication and format selection work before a long
+/// download is queued. Failures are classified like real downloads, and the temp
+/// folder is removed whatever the outcome. yt-dlp runs under `request_id` in
+/// `info_fetches` like an info fetch, so it can be cancelled and times out
+#[allow(clippy::too_many_arguments)]
+pub async fn test_download(
+    url: String,
+    download_type: DownloadType,
+    browser_config: BrowserConfig,
+    proxy: Option<String>,
+    request_id: &str,
+    info_fetches: Arc<Mutex<std::collections::HashMap<String, CommandChild>>>,
+    app: AppHandle,
+    ytdlp_updater: Arc<Mutex<YtdlpUpdater>>,
+    binary_manager: Arc<BinaryManager>,
+) -> Result<(), DownloadError> {
+    validate_extra_args(download_type.extra_args()).map_err(DownloadError::InvalidInput)?;
+
+    // The test slice replaces any trim the real download would apply
+    let mut download_type = download_type;
+    if let DownloadType::Audio {
+        start_time,
+        end_time,
+        ..
+    } = &mut download_type
+    {
+        *start_time = None;
+        *end_time = None;
+    }
+
+    let test_dir = download_temp_dir(&format!("test-{}", Uuid::new_v4()));
+    let output_path = test_dir.join("output").join("test.%(ext)s");
+    let output_path = output_path.to_string_lossy().to_string();
+    ensure_output_dir_writable(&output_path)?;
+
+    let mut args = build_ytdlp_args(
+        &url,
+        &output_path,
+        &test_dir.join("temp"),
+        &download_type,
+        &browser_config,
+        proxy.as_deref(),
+        &binary_manager,
+    )
+    .await;
+    args.push("--download-sections".to_string());
+    args.push(TEST_DOWNLOAD_SECTION.to_string());
+
+    info!("Running test download for: {}", url);
+    let result = match ytdlp_command(&app, &ytdlp_updater).await {
+        Ok(command) => {
+            run_registered(
+                command.args(&args),
+                &url,
+                request_id,
+                info_fetches,
+                TEST_DOWNLOAD_TIMEOUT,
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
+
+    // Never leave test output behind, even when yt-dlp failed or was killed
+    if let Err(e) = std::fs::remove_dir_all(&test_dir) {
+        debug!("Could not remove test directory {:?}: {}", test_dir, e);
+    }
+
+    match result {
+        Ok(_) => {
+            info!("✅ Test download succeeded: {}", url);
+            Ok(())
         }
+        Err(error) => {
+            warn!("Test download failed: {}", error);
+            Err(error)
+        }
+    }
+}
+
+/// Save a video's .info.json, thumbnail and description without downloading the media
+/// For archiving a catalog offline; returns the paths of the files written
+pub async fn download_metadata_only(
+    url: String,
+    output_dir: PathBuf,
+    proxy: Option<String>,
+    app: AppHandle,
+    ytdlp_updater: Arc<Mutex<YtdlpUpdater>>,
+) -> Result<Vec<String>, DownloadError> {
+    let output_template = output_dir.join("%(title)s [%(id)s].%(ext)s");
+    let output_template = output_template.to_string_lossy().to_string();
+    ensure_output_dir_writable(&output_template)?;
+
+    let mut args = vec![
+        url.clone(),
+        "--no-playlist".to_string(),
+        "--skip-download".to_string(),
+        "--write-info-json".to_string(),
+        "--write-thumbnail".to_string(),
+        "--write-description".to_string(),
+        "-o".to_string(),
+        output_template,
+    ];
+    if let Some(proxy) = proxy {
+        args.push("--proxy".to_string());
+        args.push(proxy);
+    }
+
+    // yt-dlp names thumbnails after whatever format the site serves, so compare
+    // the folder before and after instead of predicting the file names
+    let existing = list_files(&output_dir);
+
+    info!("Saving metadata only for: {}", url);
+    let output = ytdlp_command(&app, &ytdlp_updater)
+        .await?
+        .args(&args)
+        .output()
+        .await
+        .map_err(|e| DownloadError::ProcessFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let error = classify_ytdlp_error(&stderr, detect_platform(&url))
+            .unwrap_or_else(|| DownloadError::ProcessFailed(stderr_tail(&stderr, 3, 300)));
+        warn!("Metadata download failed: {}", error);
+        return Err(error);
+    }
+
+    let mut written: Vec<String> = list_files(&output_dir)
+        .difference(&existing)
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    written.sort();
+
+    info!("✅ Saved {} metadata files for {}", written.len(), url);
+    Ok(written)
+}
+
+/// Files directly inside a folder; empty if it can't be read
+fn list_files(dir: &Path) -> std::collections::HashSet<PathBuf> {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Send the terminal failure events of a download, once every retry has been used up
+/// Attempts don't report their own failures, so the UI doesn't see a failure that a
+/// retry then replaces. A playlist also gets its summary, counted from where it stopped
+pub fn report_download_failure(ctx: &DownloadContext, download_id: &str, error: &DownloadError) {
+    let error_msg = error.to_string();
+    error!("Download failed: {} - {}", download_id, error_msg);
+    ctx.window
+        .emit(
+            "download-complete",
+            DownloadComplete::failed(download_id, &error_msg),
+        )
+        .ok();
+    DownloadUpdate::new(download_id, DownloadPhase::Failed)
+        .with_message(error_msg)
+        .emit(&ctx.window);
+
+    let stop = ctx
+        .playlist_stop
+        .lock()
+        .ok()
+        .and_then(|mut stop| stop.take());
+    if let Some(stop) = stop {
+        let summary =
+            BatchSummary::for_playlist(download_id, &stop.item_url, stop.position, Err(error));
+        ctx.window.emit("batch-complete", summary).ok();
     }
+}
 
-    // All attempts failed
-    error!("❌ All download attempts failed");
-    Err(DownloadError::Authentication(
-        "Unable to download this video. It may require login. Please verify the video is accessible in your browser, or install Firefox and log into the website there for automatic authentication.".to_string()
-    ))
+/// Tell the UI a download is being retried; `attempt` counts every attempt so far, from 1
+fn emit_retry(
+    window: &tauri::WebviewWindow,
+    download_id: &str,
+    attempt: u32,
+    browser: Option<&str>,
+    reason: &str,
+) {
+    window
+        .emit(
+            "download-retry",
+            serde_json::json!({
+                "id": download_id,
+                "attempt": attempt,
+                "browser": browser,
+                "reason": reason
+            }),
+        )
+        .ok();
+}
+
+/// Update yt-dlp after a player error so the download can be retried
+/// Returns false if the update failed or yt-dlp was already on the latest version
+async fn update_ytdlp_for_retry(
+    ytdlp_updater: &Arc<Mutex<YtdlpUpdater>>,
+    window: &tauri::WebviewWindow,
+    download_id: &str,
+) -> bool {
+    warn!("🛠️  Player error detected, updating yt-dlp before retrying...");
+    window
+        .emit(
+            "download-status",
+            serde_json::json!({
+                "id": download_id,
+                "message": "Updating the downloader before retrying..."
+            }),
+        )
+        .ok();
+
+    let updater = ytdlp_updater.lock().await;
+    match updater.force_update().await {
+        Ok(true) => true,
+        Ok(false) => {
+            info!("yt-dlp is already up to date, not retrying");
+            false
+        }
+        Err(e) => {
+            error!("Failed to update yt-dlp: {}", e);
+            false
+        }
+    }
 }
 
-/// Cancel an active download
+/// Cancel an active or queued download
+/// A download between two attempts has no process to kill; the queue records the
+/// cancel and the download stops before its next attempt
 pub async fn cancel_download(
     download_id: String,
     active_downloads: Arc<Mutex<std::collections::HashMap<String, DownloadHandle>>>,
+    download_queue: Arc<DownloadQueue>,
     window: tauri::WebviewWindow,
 ) -> Result<(), DownloadError> {
     info!("Cancelling download: {}", download_id);
 
+    // Checked first: a download leaves the pending set and joins the running one atomically
+    if let Some(queued) = download_queue.cancel_pending(&download_id).await {
+        // Never started, so there is no process to kill or temp files to remove
+        info!("Removed queued download: {}", download_id);
+        window
+            .emit(
+                "download-cancelled",
+                serde_json::json!({
+                    "id": download_id,
+                    "path": queued.output_path,
+                    "completedItems": 0
+                }),
+            )
+            .ok();
+        DownloadUpdate::new(&download_id, DownloadPhase::Cancelled).emit(&window);
+        return Ok(());
+    }
+
+    let between_attempts = download_queue.request_cancel(&download_id);
     let download_handle = {
         let mut downloads = active_downloads.lock().await;
         downloads.remove(&download_id)
     };
 
     if let Some(handle) = download_handle {
-        // Kill the process
-        handle
-            .child
-            .kill()
-            .map_err(|e| DownloadError::ProcessFailed(format!("Failed to kill process: {}", e)))?;
-
-        info!("Killed download process: {}", download_id);
-
-        // Clean up temporary files (yt-dlp creates .part files)
-        let part_file = format!("{}.part", handle.output_path);
-        if std::path::Path::new(&part_file).exists() {
-            std::fs::remove_file(&part_file).ok();
-            info!("Cleaned up temp file: {}", part_file);
-        }
+        let output_path = handle.output_path.clone();
+        let completed_items = terminate_download(handle).await?;
 
         // Emit cancellation event
         window
@@ -758,10 +3346,27 @@ pub async fn cancel_download(
                 "download-cancelled",
                 serde_json::json!({
                     "id": download_id,
-                    "path": handle.output_path
+                    "path": output_path,
+                    "completedItems": completed_items
+                }),
+            )
+            .ok();
+        DownloadUpdate::new(&download_id, DownloadPhase::Cancelled).emit(&window);
+
+        Ok(())
+    } else if let Some(running) = between_attempts {
+        info!("Download {} will stop before its next attempt", download_id);
+        window
+            .emit(
+                "download-cancelled",
+                serde_json::json!({
+                    "id": download_id,
+                    "path": running.output_path,
+                    "completedItems": 0
                 }),
             )
             .ok();
+        DownloadUpdate::new(&download_id, DownloadPhase::Cancelled).emit(&window);
 
         Ok(())
     } else {
@@ -772,3 +3377,772 @@ pub async fn cancel_download(
         )))
     }
 }
+
+/// Kill a download's process and remove the in-progress item's temp files
+/// Returns how many playlist items had already completed
+async fn terminate_download(handle: DownloadHandle) -> Result<u32, DownloadError> {
+    // Kill the process
+    handle
+        .child
+        .kill()
+        .map_err(|e| DownloadError::ProcessFailed(format!("Failed to kill process: {}", e)))?;
+
+    info!("Killed download process: {}", handle.id);
+
+    Ok(cleanup_partial_files(&handle.items, &handle.output_path, handle.no_part).await)
+}
+
+/// Remove the in-progress item's partial files after a cancelled or failed attempt
+/// Only the in-progress item is touched; completed playlist items are kept
+/// Returns how many playlist items had already completed
+async fn cleanup_partial_files(
+    items: &Mutex<ItemTracker>,
+    output_path: &str,
+    no_part: bool,
+) -> u32 {
+    // Clean up temporary files (yt-dlp creates .part files)
+    let (completed_items, current_destination, processing_target) = {
+        let tracker = items.lock().await;
+        (
+            tracker.completed_items,
+            tracker.current_destination.clone(),
+            tracker.processing_target.clone(),
+        )
+    };
+
+    // Stopped mid-merge: the half-written container is unusable. A target that
+    // appeared through the merger's final rename is complete, so only the .temp
+    // intermediate is removed unless the step writes its output in place
+    if let Some(target) = processing_target {
+        let temp_file = ffmpeg_temp_path(&target.path);
+        if Path::new(&temp_file).exists() {
+            std::fs::remove_file(&temp_file).ok();
+            info!("Cleaned up unfinished merge: {}", temp_file);
+        }
+        if target.in_place && !target.existed && Path::new(&target.path).exists() {
+            std::fs::remove_file(&target.path).ok();
+            info!("Cleaned up unfinished output: {}", target.path);
+        }
+    }
+
+    // With --no-part the destination itself is the partial file; only remove it when
+    // yt-dlp actually reported it, never a guessed path that might be an older download
+    if no_part {
+        if let Some(destination) = &current_destination {
+            if std::path::Path::new(destination).exists() {
+                std::fs::remove_file(destination).ok();
+                info!("Cleaned up partial file: {}", destination);
+            }
+        }
+    }
+
+    let in_progress = current_destination.unwrap_or_else(|| output_path.to_string());
+    for temp_file in [
+        format!("{}.part", in_progress),
+        format!("{}.ytdl", in_progress),
+    ] {
+        if std::path::Path::new(&temp_file).exists() {
+            std::fs::remove_file(&temp_file).ok();
+            info!("Cleaned up temp file: {}", temp_file);
+        }
+    }
+
+    completed_items
+}
+
+/// Cancel every queued and running download
+/// Used on shutdown so no orphaned yt-dlp processes keep writing partial files
+pub async fn cancel_all_downloads(
+    active_downloads: Arc<Mutex<std::collections::HashMap<String, DownloadHandle>>>,
+    download_queue: Arc<DownloadQueue>,
+) -> usize {
+    let queued = download_queue.cancel_all_pending().await;
+    download_queue.cancel_all_running();
+
+    let handles: Vec<DownloadHandle> = {
+        let mut downloads = active_downloads.lock().await;
+        downloads.drain().map(|(_, handle)| handle).collect()
+    };
+    let running = handles.len();
+
+    for handle in handles {
+        let id = handle.id.clone();
+        if let Err(e) = terminate_download(handle).await {
+            warn!("Failed to stop download {}: {}", id, e);
+        }
+    }
+
+    info!(
+        "Cancelled all downloads: {} running, {} queued",
+        running,
+        queued.len()
+    );
+    running + queued.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_playlist_item() {
+        assert_eq!(
+            parse_playlist_item("[download] Downloading item 3 of 12"),
+            Some((3, 12))
+        );
+        assert_eq!(parse_playlist_item("[download]  45.0% of 10.00MiB"), None);
+    }
+
+    #[test]
+    fn test_parse_progress_fragments() {
+        let progress = parse_progress(
+            "[download]  12.5% of ~ 200.00MiB at  1.50MiB/s ETA 02:10 (frag 12/340)",
+        )
+        .unwrap();
+        assert_eq!(progress.percent, 12.5);
+        assert_eq!(progress.frag_current, Some(12));
+        assert_eq!(progress.frag_total, Some(340));
+
+        let frag_only = parse_progress("[download] Downloading (frag 50/200)").unwrap();
+        assert_eq!(frag_only.percent, 25.0);
+        assert_eq!(frag_only.eta, "--:--");
+    }
+
+    #[test]
+    fn test_parse_progress_bytes() {
+        let progress =
+            parse_progress("[download]  50.0% of 100.00MiB at  2.00MiB/s ETA 00:25").unwrap();
+        assert_eq!(progress.total_bytes, Some(100 * 1024 * 1024));
+        assert_eq!(progress.speed_bytes, Some(2.0 * 1024.0 * 1024.0));
+        assert_eq!(progress.downloaded_bytes(), Some(50 * 1024 * 1024));
+
+        let unknown = parse_progress("[download]  10.0% of ~ 1.50GiB at Unknown B/s").unwrap();
+        assert!(unknown.total_bytes.is_some());
+        assert_eq!(unknown.speed_bytes, None);
+        assert_eq!(parse_size("800KiB"), Some(800.0 * 1024.0));
+        assert_eq!(parse_size("NA"), None);
+    }
+
+    #[test]
+    fn test_get_container_format() {
+        assert_eq!(
+            get_container_format("1080p", Container::Mkv),
+            "bestvideo[height<=1080]+bestaudio/best[height<=1080]"
+        );
+        assert_eq!(
+            get_container_format("best", Container::Mkv),
+            "bestvideo+bestaudio/best"
+        );
+        assert_eq!(
+            get_container_format("720p", Container::Mp4),
+            get_quality_format("720p")
+        );
+    }
+
+    #[test]
+    fn test_best_quality_uses_mkv_without_codec_limits() {
+        let download_type = DownloadOptions::default().into_video(BEST_QUALITY.to_string());
+        match download_type {
+            DownloadType::Video {
+                quality, container, ..
+            } => {
+                assert_eq!(container, Container::Mkv);
+                assert_eq!(
+                    get_container_format(&quality, container),
+                    "bestvideo+bestaudio/best"
+                );
+            }
+            DownloadType::Audio { .. } => panic!("expected a video download"),
+        }
+    }
+
+    #[test]
+    fn test_prefer_free_formats_uses_webm() {
+        let options = DownloadOptions {
+            prefer_free_formats: true,
+            ..Default::default()
+        };
+        match options.into_video(BEST_QUALITY.to_string()) {
+            DownloadType::Video { container, .. } => assert_eq!(container, Container::Webm),
+            DownloadType::Audio { .. } => panic!("expected a video download"),
+        }
+
+        // An explicitly chosen mkv is kept
+        let options = DownloadOptions {
+            prefer_free_formats: true,
+            container: Container::Mkv,
+            ..Default::default()
+        };
+        match options.into_video("1080p".to_string()) {
+            DownloadType::Video { container, .. } => assert_eq!(container, Container::Mkv),
+            DownloadType::Audio { .. } => panic!("expected a video download"),
+        }
+    }
+
+    #[test]
+    fn test_date_organized_path() {
+        assert_eq!(
+            date_organized_path("/lib/MP4/clip.mp4", Some("20240615")),
+            PathBuf::from("/lib/MP4/2024/2024-06/clip.mp4")
+        );
+        // Malformed dates fall back to today, which still gets both folders
+        let fallback = date_organized_path("/lib/MP4/clip.mp4", Some("2024-06"));
+        assert_eq!(fallback.file_name().unwrap(), "clip.mp4");
+        assert_eq!(fallback.ancestors().nth(3).unwrap(), Path::new("/lib/MP4"));
+    }
+
+    #[test]
+    fn test_item_tracker_snapshot() {
+        let mut tracker = ItemTracker::default();
+        let snapshot = tracker.snapshot("id".into(), "url".into(), "out.mp4".into());
+        assert_eq!(snapshot.phase, DownloadPhase::Started);
+        assert_eq!(snapshot.percent, None);
+
+        tracker.latest_progress =
+            parse_progress("[download]  42.0% of ~ 10.00MiB at    1.00MiB/s ETA 00:06");
+        tracker.phase = DownloadPhase::Downloading;
+        let snapshot = tracker.snapshot("id".into(), "url".into(), "out.mp4".into());
+        assert_eq!(snapshot.phase, DownloadPhase::Downloading);
+        assert_eq!(snapshot.percent, Some(42.0));
+        assert_eq!(snapshot.eta.as_deref(), Some("00:06"));
+    }
+
+    #[test]
+    fn test_audio_only_source_skips_cover_art() {
+        let download_type = DownloadOptions::default()
+            .into_audio(AudioFormat::Mp3)
+            .for_audio_only_source();
+        match download_type {
+            DownloadType::Audio {
+                embed_thumbnail, ..
+            } => assert!(!embed_thumbnail),
+            DownloadType::Video { .. } => panic!("expected an audio download"),
+        }
+    }
+
+    #[test]
+    fn test_next_lower_quality() {
+        let available =
+            |qualities: &[&str]| qualities.iter().map(|q| q.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            next_lower_quality("1080p", &available(&["best", "1080p", "720p", "360p"])),
+            Some("720p".to_string())
+        );
+        assert_eq!(
+            next_lower_quality(BEST_QUALITY, &[]),
+            Some("1080p".to_string())
+        );
+        // No 480p stream, but the 480p step still picks up the 360p one
+        assert_eq!(
+            next_lower_quality("720p", &available(&["720p", "360p"])),
+            Some("480p".to_string())
+        );
+        // 360p is the floor
+        assert_eq!(next_lower_quality("360p", &[]), None);
+        // 480p already got the only stream, so 360p would just repeat it
+        assert_eq!(next_lower_quality("480p", &available(&["240p"])), None);
+        assert_eq!(
+            next_lower_quality("480p", &available(&["720p", "360p"])),
+            None
+        );
+        assert_eq!(
+            next_lower_quality("1080p", &available(&["1080p", "480p"])),
+            Some("720p".to_string())
+        );
+    }
+
+    #[test]
+    fn test_client_options_survive_retries() {
+        let options = DownloadOptions {
+            user_agent: Some("Mozilla/5.0 (X11; Linux x86_64)".to_string()),
+            impersonate: Some(" ".to_string()),
+            ..Default::default()
+        };
+        let download_type = options.into_video("720p".to_string());
+        assert_eq!(download_type.impersonate(), None);
+
+        // Retry variants are copies, so they keep the client settings
+        let retried = download_type
+            .with_geo_bypass()
+            .with_quality("480p".to_string());
+        assert_eq!(
+            retried.user_agent(),
+            Some("Mozilla/5.0 (X11; Linux x86_64)")
+        );
+    }
+
+    #[test]
+    fn test_geo_bypass_args() {
+        assert!(get_geo_bypass_args(None).is_empty());
+        assert_eq!(
+            get_geo_bypass_args(Some("US")),
+            vec!["--geo-bypass-country", "US"]
+        );
+        assert_eq!(
+            get_geo_bypass_args(Some(GEO_BYPASS_DEFAULT)),
+            vec!["--geo-bypass"]
+        );
+
+        let download_type = DownloadOptions::default().into_video("720p".to_string());
+        assert_eq!(download_type.geo_bypass(), None);
+        assert_eq!(
+            download_type.with_geo_bypass().geo_bypass(),
+            Some(GEO_BYPASS_DEFAULT)
+        );
+    }
+
+    #[test]
+    fn test_parse_processing_target() {
+        assert_eq!(
+            parse_processing_target(r#"[Merger] Merging formats into "/lib/MP4/clip.mp4""#),
+            Some(("/lib/MP4/clip.mp4".to_string(), false))
+        );
+        assert_eq!(
+            parse_processing_target("[ExtractAudio] Destination: /lib/MP3/song.mp3"),
+            Some(("/lib/MP3/song.mp3".to_string(), true))
+        );
+        assert_eq!(parse_processing_target("[download] 100% of 10.00MiB"), None);
+        assert_eq!(
+            ffmpeg_temp_path("/lib/MP4/clip.mp4"),
+            "/lib/MP4/clip.temp.mp4"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancel_mid_merge_keeps_finished_files() {
+        let dir = std::env::temp_dir().join(format!("ripvid-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let merged = dir.join("clip.mp4").to_string_lossy().to_string();
+        let extracted = dir.join("song.mp3").to_string_lossy().to_string();
+        std::fs::write(ffmpeg_temp_path(&merged), b"partial").unwrap();
+        // The merger's rename already happened, so this one is complete
+        std::fs::write(&merged, b"complete").unwrap();
+        std::fs::write(&extracted, b"partial").unwrap();
+
+        for (path, in_place) in [(&merged, false), (&extracted, true)] {
+            let items = Mutex::new(ItemTracker {
+                processing_target: Some(ProcessingTarget {
+                    path: path.clone(),
+                    in_place,
+                    existed: false,
+                }),
+                ..ItemTracker::default()
+            });
+            cleanup_partial_files(&items, path, false).await;
+        }
+
+        assert!(!Path::new(&ffmpeg_temp_path(&merged)).exists());
+        assert!(Path::new(&merged).exists());
+        assert!(!Path::new(&extracted).exists());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    /// Records emitted events instead of sending them to a window
+    #[derive(Clone, Default)]
+    struct RecordingSink(Arc<std::sync::Mutex<Vec<(String, serde_json::Value)>>>);
+
+    impl EventSink for RecordingSink {
+        fn send<S: Serialize + Clone>(&self, event: &str, payload: S) -> tauri::Result<()> {
+            let payload = serde_json::to_value(payload).unwrap();
+            self.0.lock().unwrap().push((event.to_string(), payload));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_terminal_events_keep_their_ids() {
+        // Two downloads finishing at the same time, each followed by its own event loop
+        let dir = std::env::temp_dir().join(format!("ripvid-events-{}", Uuid::new_v4()));
+        let sink = RecordingSink::default();
+        let active = Arc::new(Mutex::new(std::collections::HashMap::new()));
+        let probe = |path: &str| CompletedFile {
+            path: path.to_string(),
+            size: None,
+            media: MediaProbe::default(),
+        };
+
+        let mut senders = Vec::new();
+        let mut loops = Vec::new();
+        for id in ["first", "second"] {
+            active.lock().await.insert(id.to_string(), ());
+            let (tx, rx) = tauri::async_runtime::channel(16);
+            let process = ProcessEvents {
+                download_id: id.to_string(),
+                url: format!("https://www.youtube.com/watch?v={}", id),
+                output_path: dir
+                    .join(format!("{}.mp4", id))
+                    .to_string_lossy()
+                    .to_string(),
+                temp_dir: dir.join(id),
+                platform: Some("youtube"),
+                no_part: false,
+                separate_streams: false,
+                items: Arc::new(Mutex::new(ItemTracker::default())),
+                playlist_stop: Arc::default(),
+            };
+            senders.push(tx);
+            loops.push(tokio::spawn(handle_process_events(
+                rx,
+                sink.clone(),
+                process,
+                active.clone(),
+                probe,
+            )));
+        }
+
+        let line = |text: &str| text.as_bytes().to_vec();
+        let exit = |code| {
+            CommandEvent::Terminated(tauri_plugin_shell::process::TerminatedPayload {
+                code: Some(code),
+                signal: None,
+            })
+        };
+        let interleaved = [
+            (
+                0,
+                CommandEvent::Stdout(line("[download]  10.0% of 10.00MiB at 1.00MiB/s ETA 00:09")),
+            ),
+            (
+                1,
+                CommandEvent::Stdout(line("[download]  20.0% of 10.00MiB at 1.00MiB/s ETA 00:08")),
+            ),
+            (
+                1,
+                CommandEvent::Stderr(line("ERROR: [youtube] abc: Private video. Sign in")),
+            ),
+            (
+                0,
+                CommandEvent::Stdout(line("[download] 100.0% of 10.00MiB at 1.00MiB/s ETA 00:00")),
+            ),
+            (1, exit(1)),
+            (0, exit(0)),
+        ];
+        for (stream, event) in interleaved {
+            senders[stream].send(event).await.unwrap();
+            tokio::task::yield_now().await;
+        }
+
+        let mut results = Vec::new();
+        for handle in loops {
+            results.push(handle.await.unwrap());
+        }
+        assert_eq!(results[0].as_deref().ok(), Some("first"));
+        assert!(matches!(results[1], Err(DownloadError::Authentication(_))));
+        assert!(active.lock().await.is_empty());
+
+        let events = sink.0.lock().unwrap().clone();
+        let complete: Vec<_> = events
+            .iter()
+            .filter(|(event, _)| event == "download-complete")
+            .map(|(_, payload)| payload)
+            .collect();
+        // The failure is left to report_download_failure, since a retry may follow
+        assert_eq!(complete.len(), 1);
+        assert_eq!(complete[0]["id"], "first");
+        assert_eq!(complete[0]["success"], true);
+        assert!(complete[0]["path"].as_str().unwrap().ends_with("first.mp4"));
+
+        // Progress ticks stay with the download that printed them
+        for (_, payload) in events
+            .iter()
+            .filter(|(event, _)| event == "download-progress")
+        {
+            let expected = if payload["percent"] == 20.0 {
+                "second"
+            } else {
+                "first"
+            };
+            assert_eq!(payload["id"], expected);
+        }
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_exit_outcome() {
+        assert!(exit_outcome(Some(0), "", None).is_ok());
+        assert!(matches!(
+            exit_outcome(None, "", None),
+            Err(DownloadError::ProcessFailed(_))
+        ));
+        match exit_outcome(Some(1), "ERROR: something broke", None) {
+            Err(DownloadError::ProcessFailed(message)) => {
+                assert!(message.starts_with("Exit code: 1."))
+            }
+            other => panic!("unexpected outcome: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_ensure_output_dir_writable_creates_dir() {
+        let dir = std::env::temp_dir().join(format!("ripvid-test-{}", Uuid::new_v4()));
+        let output = dir.join("video.mp4");
+
+        assert!(ensure_output_dir_writable(&output.to_string_lossy()).is_ok());
+        assert!(dir.is_dir());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_is_kept_format_file() {
+        assert!(is_kept_format_file(Path::new("/videos/clip.f137.mp4")));
+        assert!(is_kept_format_file(Path::new("/videos/clip.f251.webm")));
+        assert!(!is_kept_format_file(Path::new("/videos/clip.mp4")));
+        assert!(!is_kept_format_file(Path::new("/videos/my.final.mp4")));
+    }
+
+    #[test]
+    fn test_is_sidecar_file() {
+        assert!(is_sidecar_file(Path::new("/videos/clip.f137.mp4")));
+        assert!(is_sidecar_file(Path::new("/music/song.jpg")));
+        assert!(is_sidecar_file(Path::new("/music/song.WEBP")));
+        assert!(!is_sidecar_file(Path::new("/music/song.mp3")));
+    }
+
+    #[test]
+    fn test_find_thumbnail_prefers_jpg() {
+        let dir = std::env::temp_dir().join(format!("ripvid-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let video = dir.join("clip.mp4");
+        std::fs::write(&video, b"").unwrap();
+        assert_eq!(find_thumbnail(&video), None);
+
+        std::fs::write(dir.join("clip.webp"), b"").unwrap();
+        assert_eq!(find_thumbnail(&video), Some(dir.join("clip.webp")));
+
+        std::fs::write(dir.join("clip.jpg"), b"").unwrap();
+        assert_eq!(find_thumbnail(&video), Some(dir.join("clip.jpg")));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_webp_thumbnail_falls_back_to_jpg_for_embedding() {
+        assert_eq!(ThumbnailFormat::Webp.convert_to(), Some("webp"));
+        assert_eq!(ThumbnailFormat::Webp.convert_to_for_embed(), Some("jpg"));
+        assert_eq!(ThumbnailFormat::Png.convert_to_for_embed(), Some("png"));
+    }
+
+    #[test]
+    fn test_resolve_output_collision_renames() {
+        let dir = std::env::temp_dir().join(format!("ripvid-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("song.mp3");
+        let output_str = output.to_string_lossy().to_string();
+
+        // Nothing to collide with yet
+        assert_eq!(
+            resolve_output_collision(&output_str, Collision::Rename),
+            output_str
+        );
+
+        std::fs::write(&output, b"").unwrap();
+        std::fs::write(dir.join("song (2).mp3"), b"").unwrap();
+        assert_eq!(
+            resolve_output_collision(&output_str, Collision::Rename),
+            dir.join("song (3).mp3").to_string_lossy()
+        );
+        assert_eq!(
+            resolve_output_collision(&output_str, Collision::Overwrite),
+            output_str
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_separate_streams_template() {
+        assert_eq!(
+            separate_streams_template("/videos/clip.mp4"),
+            "/videos/clip.%(width&video|audio)s.%(ext)s"
+        );
+        assert_eq!(
+            get_separate_streams_format("720p"),
+            "bestvideo[height<=720],bestaudio"
+        );
+    }
+
+    #[test]
+    fn test_separate_stream_paths() {
+        let dir = std::env::temp_dir().join(format!("ripvid-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in [
+            "clip.audio.webm",
+            "clip.video.mp4",
+            "clip.audio.webm.part",
+            "other.mp4",
+        ] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+
+        let paths = separate_stream_paths(&dir.join("clip.mp4").to_string_lossy());
+        let names: Vec<String> = paths
+            .iter()
+            .map(|path| {
+                Path::new(path)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(names, vec!["clip.video.mp4", "clip.audio.webm"]);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_requires_ffmpeg() {
+        let merged = DownloadOptions::default().into_video("720p".to_string());
+        assert!(merged.requires_ffmpeg());
+
+        let separate = DownloadOptions {
+            separate_streams: true,
+            ..Default::default()
+        };
+        assert!(!separate
+            .clone()
+            .into_video("720p".to_string())
+            .requires_ffmpeg());
+        assert!(DownloadOptions {
+            embed_metadata: Some(true),
+            ..separate
+        }
+        .into_video("720p".to_string())
+        .requires_ffmpeg());
+
+        assert!(DownloadOptions::default()
+            .into_audio(AudioFormat::Mp3)
+            .requires_ffmpeg());
+    }
+
+    #[test]
+    fn test_with_sub_langs() {
+        let download_type = DownloadOptions {
+            subtitle_mode: Some(SubtitleMode::Sidecar),
+            manual_sub_langs: Some(vec!["en".to_string()]),
+            auto_sub_langs: Some(vec!["fr".to_string()]),
+            ..Default::default()
+        }
+        .into_video("best".to_string());
+
+        let resolved = download_type.with_sub_langs(vec!["en".to_string()], Vec::new());
+        assert_eq!(resolved.subtitle_mode(), SubtitleMode::Sidecar);
+        assert!(resolved.auto_sub_langs().is_empty());
+
+        let none = download_type.with_sub_langs(Vec::new(), Vec::new());
+        assert_eq!(none.subtitle_mode(), SubtitleMode::None);
+    }
+
+    #[test]
+    fn test_get_subtitle_args() {
+        let langs = |langs: &[&str]| langs.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        let manual = langs(&["en", "pt-BR"]);
+        let auto = langs(&["en", "de"]);
+        assert!(get_subtitle_args(SubtitleMode::None, &manual, &auto).is_empty());
+        assert_eq!(
+            get_subtitle_args(SubtitleMode::Both, &manual, &auto),
+            vec![
+                "--write-subs",
+                "--embed-subs",
+                "--write-auto-subs",
+                "--sub-langs",
+                "en,pt-BR,de"
+            ]
+        );
+        assert_eq!(
+            get_subtitle_args(SubtitleMode::Sidecar, &manual, &[]),
+            vec!["--write-subs", "--sub-langs", "en,pt-BR"]
+        );
+        // Only auto-generated captions were asked for
+        assert_eq!(
+            get_subtitle_args(SubtitleMode::Sidecar, &[], &auto),
+            vec!["--write-auto-subs", "--sub-langs", "en,de"]
+        );
+        // History entries saved before the split used "auto" in one list
+        assert_eq!(
+            get_subtitle_args(SubtitleMode::Sidecar, &langs(&["en", "auto"]), &[]),
+            vec!["--write-subs", "--write-auto-subs", "--sub-langs", "en"]
+        );
+        assert_eq!(
+            get_subtitle_args(SubtitleMode::Embed, &[], &[]),
+            vec!["--embed-subs"]
+        );
+    }
+
+    #[test]
+    fn test_legacy_subtitle_langs_deserialize_as_manual() {
+        let download_type: DownloadType = serde_json::from_str(
+            r#"{"type": "Video", "quality": "720p", "subtitle_mode": "sidecar", "subtitle_langs": ["en"]}"#,
+        )
+        .unwrap();
+        assert_eq!(download_type.manual_sub_langs(), ["en".to_string()]);
+        assert!(download_type.auto_sub_langs().is_empty());
+    }
+
+    #[test]
+    fn test_get_download_section() {
+        assert_eq!(get_download_section(None, None), None);
+        assert_eq!(
+            get_download_section(Some("0:45"), Some("4:10")),
+            Some("*0:45-4:10".to_string())
+        );
+        assert_eq!(
+            get_download_section(Some("30"), None),
+            Some("*30-inf".to_string())
+        );
+        assert_eq!(
+            get_download_section(Some(" 0:45 "), Some("4:10\n")),
+            Some("*0:45-4:10".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_warning() {
+        assert_eq!(
+            parse_warning("WARNING: [youtube] abc: Some formats may be missing\n"),
+            Some("[youtube] abc: Some formats may be missing")
+        );
+        assert_eq!(parse_warning("WARNING: "), None);
+        assert_eq!(parse_warning("ERROR: Video unavailable"), None);
+    }
+
+    #[test]
+    fn test_parse_destination() {
+        assert_eq!(
+            parse_destination("[download] Destination: /home/user/Videos/clip.f137.mp4\n"),
+            Some("/home/user/Videos/clip.f137.mp4".to_string())
+        );
+        assert_eq!(
+            parse_destination("[download] Downloading item 1 of 2"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_progress_throttle() {
+        let start = std::time::Instant::now();
+        let mut throttle = ProgressThrottle::default();
+
+        assert!(throttle.should_emit(1.2, start));
+        // Too soon, and the same whole percent
+        assert!(!throttle.should_emit(1.8, start + Duration::from_millis(150)));
+        assert!(!throttle.should_emit(5.0, start + Duration::from_millis(50)));
+        assert!(throttle.should_emit(5.0, start + Duration::from_millis(150)));
+        // The final tick always goes out
+        assert!(throttle.should_emit(100.0, start + Duration::from_millis(160)));
+    }
+
+    #[test]
+    fn test_playlist_progress_overall_percent() {
+        let progress = PlaylistProgress::new(3, 4, 50.0);
+        assert!((progress.percent - 62.5).abs() < f32::EPSILON);
+
+        let first = PlaylistProgress::new(1, 4, 0.0);
+        assert_eq!(first.percent, 0.0);
+    }
+}
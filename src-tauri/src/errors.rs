@@ -1,3 +1,4 @@
+use serde::Serialize;
 use thiserror::Error;
 
 /// Custom error types for the download application
@@ -36,6 +37,18 @@ pub enum DownloadError {
     #[error("Browser not found: {0}")]
     BrowserNotFound(String),
 
+    #[error("Extraction failed: {0}")]
+    PlayerError(String),
+
+    #[error("Content unavailable: {0}")]
+    ContentUnavailable(String),
+
+    #[error("Region restricted: {0}")]
+    GeoRestricted(String),
+
+    #[error("Missing dependency: {0}")]
+    MissingDependency(String),
+
     #[error("Failed to parse output: {0}")]
     ParseError(String),
 
@@ -43,6 +56,47 @@ pub enum DownloadError {
     Unknown(String),
 }
 
+impl DownloadError {
+    /// Stable identifier the UI can branch on, e.g. to offer cookies for "authentication"
+    pub fn code(&self) -> &'static str {
+        match self {
+            DownloadError::InvalidUrl(_) => "invalid_url",
+            DownloadError::InvalidInput(_) => "invalid_input",
+            DownloadError::Network(_) => "network",
+            DownloadError::ProcessFailed(_) => "process_failed",
+            DownloadError::Io(_) => "io",
+            DownloadError::Sidecar(_) => "sidecar",
+            DownloadError::Authentication(_) => "authentication",
+            DownloadError::RateLimit(_) => "rate_limit",
+            DownloadError::Cancelled => "cancelled",
+            DownloadError::QualityNotAvailable(_) => "quality_not_available",
+            DownloadError::BrowserNotFound(_) => "browser_not_found",
+            DownloadError::PlayerError(_) => "player_error",
+            DownloadError::ContentUnavailable(_) => "content_unavailable",
+            DownloadError::GeoRestricted(_) => "geo_restricted",
+            DownloadError::MissingDependency(_) => "missing_dependency",
+            DownloadError::ParseError(_) => "parse_error",
+            DownloadError::Unknown(_) => "unknown",
+        }
+    }
+}
+
+/// Error returned to the frontend with a machine-readable code next to the message
+#[derive(Debug, Serialize)]
+pub struct CommandError {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl From<DownloadError> for CommandError {
+    fn from(error: DownloadError) -> Self {
+        Self {
+            code: error.code(),
+            message: error.to_string(),
+        }
+    }
+}
+
 impl From<DownloadError> for String {
     fn from(error: DownloadError) -> Self {
         error.to_string()
@@ -57,42 +111,447 @@ pub fn is_retryable_error(error: &DownloadError) -> bool {
     )
 }
 
+/// Determine if a failed download can pick up from its partial file
+/// Only transient connection problems; anything else would fail the same way again
+pub fn is_resumable_error(error: &DownloadError) -> bool {
+    matches!(
+        error,
+        DownloadError::Network(_) | DownloadError::RateLimit(_)
+    )
+}
+
+/// Case-insensitive check for any of the lower-case `needles` in yt-dlp's stderr
+/// yt-dlp rewords and recapitalises messages between releases, so matching is
+/// done on lower-cased text against short, stable phrases
+fn contains_any(stderr: &str, needles: &[&str]) -> bool {
+    let haystack = stderr.to_lowercase();
+    needles.iter().any(|needle| haystack.contains(needle))
+}
+
 /// Determine if an error is a network error
 pub fn is_network_error(stderr: &str) -> bool {
-    stderr.contains("Unable to download")
-        || stderr.contains("HTTP Error")
-        || stderr.contains("Connection")
-        || stderr.contains("timeout")
-        || stderr.contains("network")
+    contains_any(
+        stderr,
+        &[
+            "unable to download",
+            "http error",
+            "connection",
+            "timeout",
+            "timed out",
+            "network",
+            "name resolution",
+            "getaddrinfo failed",
+        ],
+    )
 }
 
 /// Determine if an error is a rate limit error
 pub fn is_rate_limit_error(stderr: &str) -> bool {
-    stderr.contains("rate limit") || stderr.contains("429") || stderr.contains("Too Many Requests")
+    contains_any(
+        stderr,
+        &[
+            "rate limit",
+            "rate-limit",
+            "ratelimit",
+            "429",
+            "too many requests",
+        ],
+    )
 }
 
 /// Determine if an error is an authentication error
-pub fn is_auth_error(stderr: &str) -> bool {
-    stderr.contains("Sign in")
-        || stderr.contains("Private video")
-        || stderr.contains("members-only")
-        || stderr.contains("This video is only available")
-        || stderr.contains("login required")
+/// Instagram and Facebook report login walls as generic "isn't available" errors,
+/// so for those platforms an unavailable error that isn't permanent counts as auth;
+/// on X, unavailable and protected tweets usually need a logged-in account
+pub fn is_auth_error(stderr: &str, platform: Option<&str>) -> bool {
+    let generic = contains_any(
+        stderr,
+        &[
+            "sign in",
+            "private video",
+            "members-only",
+            "members only",
+            "this video is only available",
+            "login required",
+            "requires login",
+            "requires authentication",
+            "authentication required",
+            "confirm your age",
+            "use --cookies",
+        ],
+    );
+
+    let meta = matches!(platform, Some("instagram") | Some("facebook"))
+        && (contains_any(
+            stderr,
+            &[
+                "login_required",
+                "requested content is not available",
+                "you must be logged in",
+                "log in to",
+            ],
+        ) || (is_content_unavailable_error(stderr) && !is_permanently_unavailable(stderr)));
+
+    // X reports protected and login-only posts as unavailable tweets
+    let x = platform == Some("x")
+        && contains_any(
+            stderr,
+            &[
+                "tweet is unavailable",
+                "tweet unavailable",
+                "protected tweet",
+                "protected account",
+                "nsfw tweet",
+            ],
+        );
+
+    generic || meta || x
+}
+
+/// Determine if the site reported the content as unavailable
+pub fn is_content_unavailable_error(stderr: &str) -> bool {
+    // Kept specific: "Requested format is not available" is a quality problem
+    contains_any(
+        stderr,
+        &[
+            "content isn't available",
+            "content is not available",
+            "video unavailable",
+            "video is unavailable",
+            "video is not available",
+            "no longer available",
+            "has been removed",
+        ],
+    )
+}
+
+/// Determine if audio extraction found no audio stream in the downloaded media
+pub fn is_no_audio_error(stderr: &str) -> bool {
+    contains_any(
+        stderr,
+        &[
+            "unable to obtain file audio codec",
+            "does not contain any stream",
+        ],
+    )
+}
+
+/// Determine if the site blocked the content in the user's country
+pub fn is_geo_error(stderr: &str) -> bool {
+    contains_any(
+        stderr,
+        &[
+            "available in your country",
+            "not available from your location",
+            "blocked it in your country",
+            "geo restricted",
+            "geo-restricted",
+            "georestricted",
+            "geo restriction",
+        ],
+    )
+}
+
+/// Determine if unavailable content is gone for good, so logging in won't help
+pub fn is_permanently_unavailable(stderr: &str) -> bool {
+    contains_any(
+        stderr,
+        &[
+            "has been removed",
+            "no longer available",
+            "deleted",
+            "account has been terminated",
+            "copyright",
+        ],
+    )
 }
 
 /// Determine if an error is a DPAPI cookie decryption error (Windows Chrome/Edge)
 pub fn is_dpapi_error(stderr: &str) -> bool {
-    stderr.contains("Failed to decrypt with DPAPI")
-        || stderr.contains("DPAPI")
-        || (stderr.contains("decrypt") && stderr.contains("cookie"))
+    contains_any(stderr, &["dpapi"])
+        || (contains_any(stderr, &["decrypt"]) && contains_any(stderr, &["cookie"]))
+}
+
+/// Determine if the requested format/quality doesn't exist for this video
+pub fn is_format_unavailable_error(stderr: &str) -> bool {
+    contains_any(
+        stderr,
+        &[
+            "requested format is not available",
+            "requested format not available",
+            "no video formats found",
+        ],
+    )
+}
+
+/// Determine if an error comes from a site player change that a yt-dlp update usually fixes
+/// Only ERROR lines count: yt-dlp also prints nsig problems as warnings ("Some formats
+/// may be missing") and carries on, so those say nothing about why a download failed
+pub fn is_player_error(stderr: &str) -> bool {
+    stderr
+        .lines()
+        .filter(|line| line.trim_start().starts_with("ERROR:"))
+        .any(|line| {
+            contains_any(
+                line,
+                &["nsig", "n challenge", "signature extraction failed"],
+            )
+        })
 }
 
 /// Determine if an error is related to ffmpeg/merge issues
 pub fn is_ffmpeg_error(stderr: &str) -> bool {
-    (stderr.contains("ffmpeg") || stderr.contains("Merger") || stderr.contains("merge"))
-        && (stderr.contains("not found")
-            || stderr.contains("does not exist")
-            || stderr.contains("NoneType")
-            || stderr.contains("'lower'")
-            || stderr.contains("FFmpeg"))
+    contains_any(stderr, &["ffmpeg", "merger", "merge"])
+        && contains_any(
+            stderr,
+            &[
+                "not found",
+                "does not exist",
+                "nonetype",
+                "'lower'",
+                "ffmpeg exited",
+                "install or provide the path",
+            ],
+        )
+}
+
+/// Find the encoder ffmpeg reported as missing, e.g. "Unknown encoder 'libmp3lame'"
+/// Checked before is_ffmpeg_error so a build without the encoder gets a specific message
+pub fn missing_ffmpeg_encoder(stderr: &str) -> Option<&str> {
+    let start = stderr.find("Unknown encoder '")? + "Unknown encoder '".len();
+    let len = stderr[start..].find('\'')?;
+    Some(&stderr[start..start + len])
+}
+
+/// Determine if an error came from embedding a thumbnail (unsupported container or ffmpeg failure)
+pub fn is_thumbnail_embed_error(stderr: &str) -> bool {
+    contains_any(stderr, &["embedthumbnail", "thumbnail embedding"])
+        || (contains_any(stderr, &["postprocessing"]) && contains_any(stderr, &["thumbnail"]))
+}
+
+/// Turn yt-dlp's stderr into an error with a helpful message
+/// Shared by downloads and info fetches so both explain failures the same way;
+/// None when no matcher recognises the failure
+pub fn classify_ytdlp_error(stderr: &str, platform: Option<&str>) -> Option<DownloadError> {
+    let error = if let Some(encoder) = missing_ffmpeg_encoder(stderr) {
+        DownloadError::MissingDependency(format!("The installed ffmpeg doesn't include the {} encoder needed for this format. Choose a different audio format or reinstall the tools.", encoder))
+    } else if is_no_audio_error(stderr) {
+        // Checked before is_ffmpeg_error: ffmpeg is working, the source just has no sound
+        DownloadError::ContentUnavailable(
+            "This video has no audio track, so there is no audio to download.".to_string(),
+        )
+    } else if is_ffmpeg_error(stderr) {
+        DownloadError::ProcessFailed("Video processing failed. FFmpeg is required to merge video and audio streams. Please restart the application and try again.".to_string())
+    } else if is_thumbnail_embed_error(stderr) {
+        DownloadError::ProcessFailed("Thumbnail embedding failed. The media was downloaded but the cover art could not be added. Try again with thumbnail embedding disabled.".to_string())
+    } else if is_dpapi_error(stderr) {
+        DownloadError::Authentication("Cookie decryption failed. Chrome/Edge on Windows have encryption issues. Solutions: 1) Close your browser completely and try again, 2) Install Firefox (recommended), or 3) Disable browser cookies in settings.".to_string())
+    } else if is_geo_error(stderr) {
+        // Checked before auth and unavailable: geo blocks are worded like both
+        DownloadError::GeoRestricted(
+            "This content isn't available in your country. Try enabling geo bypass.".to_string(),
+        )
+    } else if is_auth_error(stderr, platform) {
+        DownloadError::Authentication(
+            "Authentication required. Try enabling browser cookies.".to_string(),
+        )
+    } else if is_content_unavailable_error(stderr) {
+        // Removed or deleted; retrying won't help
+        DownloadError::ContentUnavailable(
+            "This content was removed or is no longer available.".to_string(),
+        )
+    } else if is_rate_limit_error(stderr) {
+        DownloadError::RateLimit("Rate limit exceeded. Please wait and try again.".to_string())
+    } else if is_format_unavailable_error(stderr) {
+        DownloadError::QualityNotAvailable(
+            "The selected quality isn't available for this video. Try a lower quality.".to_string(),
+        )
+    } else if is_player_error(stderr) {
+        // Checked before network errors: player failures often end in HTTP 403
+        DownloadError::PlayerError(
+            "The site changed its video player and yt-dlp could not read it. Updating the downloader usually fixes this."
+                .to_string(),
+        )
+    } else if is_network_error(stderr) {
+        DownloadError::Network("Network error. Check your connection and try again.".to_string())
+    } else {
+        return None;
+    };
+
+    Some(error)
+}
+
+/// Extract the last few non-empty stderr lines for display in the UI
+/// Used when no specific matcher recognises the failure
+pub fn stderr_tail(stderr: &str, max_lines: usize, max_chars: usize) -> String {
+    let lines: Vec<&str> = stderr
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    let start = lines.len().saturating_sub(max_lines);
+    let tail = lines[start..].join(" | ");
+
+    if tail.chars().count() > max_chars {
+        let truncated: String = tail.chars().take(max_chars).collect();
+        format!("{}...", truncated)
+    } else {
+        tail
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_private_video_for_info_fetch() {
+        let stderr = "ERROR: [youtube] abc: Private video. Sign in if you've been granted access";
+        let error = CommandError::from(classify_ytdlp_error(stderr, Some("youtube")).unwrap());
+        assert_eq!(error.code, "authentication");
+        assert!(classify_ytdlp_error("ERROR: something odd", None).is_none());
+    }
+
+    #[test]
+    fn test_resumable_errors() {
+        assert!(is_resumable_error(&DownloadError::Network("reset".into())));
+        assert!(is_resumable_error(&DownloadError::RateLimit("429".into())));
+        assert!(!is_resumable_error(&DownloadError::Authentication(
+            "login".into()
+        )));
+        assert!(!is_resumable_error(&DownloadError::ProcessFailed(
+            "exit 1".into()
+        )));
+    }
+
+    #[test]
+    fn test_missing_ffmpeg_encoder() {
+        let stderr = "ERROR: Postprocessing: audio conversion failed: Unknown encoder 'libmp3lame'";
+        assert_eq!(missing_ffmpeg_encoder(stderr), Some("libmp3lame"));
+        assert_eq!(missing_ffmpeg_encoder("ERROR: ffmpeg not found"), None);
+    }
+
+    #[test]
+    fn test_no_audio_track() {
+        let stderr =
+            "ERROR: Postprocessing: WARNING: unable to obtain file audio codec with ffprobe";
+        let error = classify_ytdlp_error(stderr, None).unwrap();
+        assert_eq!(error.code(), "content_unavailable");
+        assert!(error.to_string().contains("no audio track"));
+    }
+
+    #[test]
+    fn test_meta_unavailable_is_auth_error() {
+        let stderr = "ERROR: [facebook] 123: This content isn't available right now";
+        assert!(is_auth_error(stderr, Some("facebook")));
+        assert!(is_auth_error(stderr, Some("instagram")));
+        // Other platforms keep the narrow auth matching
+        assert!(!is_auth_error(stderr, Some("youtube")));
+        assert!(!is_auth_error(stderr, None));
+    }
+
+    #[test]
+    fn test_missing_format_is_not_unavailable_content() {
+        assert!(!is_content_unavailable_error(
+            "ERROR: [youtube] abc: Requested format is not available"
+        ));
+    }
+
+    #[test]
+    fn test_classify_recent_ytdlp_stderr() {
+        let code = |stderr: &str, platform: Option<&str>| {
+            classify_ytdlp_error(stderr, platform).map(|error| error.code())
+        };
+
+        let samples = [
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Sign in to confirm you’re not a bot. Use --cookies-from-browser or --cookies for the authentication.",
+                Some("youtube"),
+                "authentication",
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Sign In to confirm your age. This video may be inappropriate for some users.",
+                Some("youtube"),
+                "authentication",
+            ),
+            (
+                "ERROR: [instagram] C1abc: Requested content is not available, rate-limit reached or login required. Use --cookies, --cookies-from-browser, --username and --password, --netrc-cmd, or --netrc (instagram) to provide account credentials",
+                Some("instagram"),
+                "authentication",
+            ),
+            (
+                "ERROR: [youtube] abc: Video unavailable. This video has been removed by the uploader",
+                Some("youtube"),
+                "content_unavailable",
+            ),
+            (
+                "ERROR: [youtube] abc: Video unavailable. The uploader has not made this video available in your country",
+                Some("youtube"),
+                "geo_restricted",
+            ),
+            (
+                "ERROR: [BBC] p0abc: This video is not available from your location due to geo restriction",
+                None,
+                "geo_restricted",
+            ),
+            (
+                "ERROR: [youtube] abc: Requested format is not available. Use --list-formats for a list of available formats",
+                Some("youtube"),
+                "quality_not_available",
+            ),
+            (
+                "ERROR: [twitter] 1790000000000000000: You are not authorized to view this protected tweet. Use --cookies, --cookies-from-browser, --username and --password, --netrc-cmd, or --netrc (twitter) to provide account credentials",
+                Some("x"),
+                "authentication",
+            ),
+            (
+                "ERROR: [twitter] 1790000000000000000: Requested tweet is unavailable",
+                Some("x"),
+                "authentication",
+            ),
+            (
+                "ERROR: [youtube] abc: HTTP Error 429: Too Many Requests",
+                Some("youtube"),
+                "rate_limit",
+            ),
+            (
+                "ERROR: [youtube] abc: Signature extraction failed: Some formats may be missing",
+                Some("youtube"),
+                "player_error",
+            ),
+            (
+                "WARNING: [youtube] abc: nsig extraction failed: Some formats may be missing\nERROR: unable to download video data: HTTP Error 403: Forbidden",
+                Some("youtube"),
+                "network",
+            ),
+            (
+                "ERROR: Unable to download webpage: <urlopen error [Errno -3] Temporary failure in name resolution>",
+                None,
+                "network",
+            ),
+            (
+                "ERROR: Postprocessing: ffprobe and ffmpeg not found. Please install or provide the path using --ffmpeg-location",
+                None,
+                "process_failed",
+            ),
+            (
+                "ERROR: Failed to decrypt with DPAPI. See  https://github.com/yt-dlp/yt-dlp/issues/10927  for more info",
+                None,
+                "authentication",
+            ),
+        ];
+
+        for (stderr, platform, expected) in samples {
+            assert_eq!(code(stderr, platform), Some(expected), "{}", stderr);
+        }
+    }
+
+    #[test]
+    fn test_permanently_unavailable_is_not_auth_error() {
+        let stderr = "ERROR: [instagram] abc: This content is no longer available";
+        assert!(is_content_unavailable_error(stderr));
+        assert!(is_permanently_unavailable(stderr));
+        assert!(!is_auth_error(stderr, Some("instagram")));
+    }
 }
@@ -0,0 +1,73 @@
+use md5::Md5;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Size of each read while hashing, so large videos never sit in memory
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Digest algorithms offered by hash_file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    #[default]
+    Sha256,
+    /// For comparing against checksums published by older sites and tools
+    Md5,
+}
+
+/// Hex digest of a file, read in chunks
+/// `on_progress` is called after every chunk with the bytes hashed so far
+pub fn hash_file(
+    path: &Path,
+    algorithm: HashAlgorithm,
+    on_progress: impl FnMut(u64),
+) -> io::Result<String> {
+    let file = File::open(path)?;
+    match algorithm {
+        HashAlgorithm::Sha256 => hash_reader::<Sha256>(file, on_progress),
+        HashAlgorithm::Md5 => hash_reader::<Md5>(file, on_progress),
+    }
+}
+
+fn hash_reader<D: Digest>(
+    mut reader: impl Read,
+    mut on_progress: impl FnMut(u64),
+) -> io::Result<String> {
+    let mut hasher = D::new();
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    let mut hashed = 0u64;
+
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        hasher.update(&buffer[..read]);
+        hashed += read as u64;
+        on_progress(hashed);
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_reader_known_digests() {
+        assert_eq!(
+            hash_reader::<Sha256>(&b"abc"[..], |_| {}).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hash_reader::<Md5>(&b"abc"[..], |_| {}).unwrap(),
+            "900150983cd24fb0d6963f7d28e17f72"
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use crate::download::DownloadType;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Final state of a download request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadOutcome {
+    Completed,
+    /// The file already existed and the collision setting was Skip
+    Skipped,
+    Failed,
+    Cancelled,
+}
+
+/// A finished download request, with everything needed to run it again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadRecord {
+    pub id: String,
+    pub url: String,
+    /// Path as requested, before date folders and collision renames were applied
+    pub output_path: String,
+    /// Exact options the download was requested with, before any quality fallback
+    pub download_type: DownloadType,
+    pub outcome: DownloadOutcome,
+    pub error: Option<String>,
+    /// Unix timestamp (seconds)
+    pub finished_at: u64,
+}
+
+impl DownloadRecord {
+    pub fn new(
+        url: String,
+        output_path: String,
+        download_type: DownloadType,
+        outcome: DownloadOutcome,
+        error: Option<String>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            url,
+            output_path,
+            download_type,
+            outcome,
+            error,
+            finished_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// Download history persisted to history.json in the app data directory
+pub struct HistoryManager {
+    path: PathBuf,
+    records: Mutex<Vec<DownloadRecord>>,
+}
+
+impl HistoryManager {
+    pub fn new(app_handle: &AppHandle) -> Self {
+        let path = app_handle
+            .path()
+            .app_data_dir()
+            .unwrap_or_else(|_| PathBuf::from("."))
+            .join("history.json");
+
+        let records = Self::load(&path);
+
+        Self {
+            path,
+            records: Mutex::new(records),
+        }
+    }
+
+    /// Read history from disk, starting empty if missing or invalid
+    fn load(path: &Path) -> Vec<DownloadRecord> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(_) => return Vec::new(),
+        };
+
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            warn!("History file is invalid, starting empty: {}", e);
+            Vec::new()
+        })
+    }
+
+    /// All records, newest first
+    pub async fn list(&self) -> Vec<DownloadRecord> {
+        self.records.lock().await.clone()
+    }
+
+    pub async fn get(&self, id: &str) -> Option<DownloadRecord> {
+        self.records
+            .lock()
+            .await
+            .iter()
+            .find(|record| record.id == id)
+            .cloned()
+    }
+
+    /// Add a record, drop the oldest beyond `max_entries` and persist the history
+    /// Trimming and saving happen under the lock, so concurrent completions can't
+    /// interleave their writes
+    pub async fn add(&self, record: DownloadRecord, max_entries: usize) {
+        let mut records = self.records.lock().await;
+        info!(
+            "Recording download history: {} ({:?})",
+            record.id, record.outcome
+        );
+        records.insert(0, record);
+        trim_history(&mut records, max_entries);
+
+        if let Err(e) = self.save(&records) {
+            warn!("Failed to save download history: {}", e);
+        }
+    }
+
+    fn save(&self, records: &[DownloadRecord]) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create history directory: {}", e))?;
+        }
+
+        // Write a temp file and rename it over the old one, so a crash mid-write
+        // never leaves a truncated history.json
+        let json = serde_json::to_string_pretty(records).map_err(|e| e.to_string())?;
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, json).map_err(|e| format!("Failed to save history: {}", e))?;
+        fs::rename(&temp_path, &self.path).map_err(|e| format!("Failed to save history: {}", e))
+    }
+}
+
+/// Keep only the newest `max_entries` records (records are stored newest first)
+fn trim_history(records: &mut Vec<DownloadRecord>, max_entries: usize) {
+    if records.len() > max_entries {
+        info!(
+            "Trimming download history from {} to {} entries",
+            records.len(),
+            max_entries
+        );
+        records.truncate(max_entries);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::download::DownloadOptions;
+
+    #[test]
+    fn test_record_round_trips_download_options() {
+        let download_type = DownloadOptions::default().into_video("720p".to_string());
+        let record = DownloadRecord::new(
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ".to_string(),
+            "/tmp/video.mp4".to_string(),
+            download_type,
+            DownloadOutcome::Failed,
+            Some("Network error".to_string()),
+        );
+
+        let json = serde_json::to_string(&record).unwrap();
+        let restored: DownloadRecord = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.id, record.id);
+        assert_eq!(restored.outcome, DownloadOutcome::Failed);
+        match restored.download_type {
+            DownloadType::Video { quality, .. } => assert_eq!(quality, "720p"),
+            DownloadType::Audio { .. } => panic!("expected a video download"),
+        }
+    }
+
+    #[test]
+    fn test_trim_history_keeps_newest() {
+        let mut records: Vec<DownloadRecord> = (0..5)
+            .map(|i| {
+                DownloadRecord::new(
+                    format!("https://example.com/{}", i),
+                    format!("/tmp/{}.mp4", i),
+                    DownloadOptions::default().into_video("720p".to_string()),
+                    DownloadOutcome::Completed,
+                    None,
+                )
+            })
+            .collect();
+        let newest = records[0].id.clone();
+
+        trim_history(&mut records, 3);
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].id, newest);
+
+        trim_history(&mut records, 10);
+        assert_eq!(records.len(), 3);
+    }
+}
@@ -3,157 +3,1682 @@
     windows_subsystem = "windows"
 )]
 
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::process::Command;
 use std::sync::Arc;
-use tauri::Manager;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use tauri_plugin_shell::process::CommandChild;
 use tauri_plugin_shell::ShellExt;
 use tokio::sync::Mutex;
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
 
+mod batch;
 mod binary_manager;
+mod dates;
 mod download;
 mod errors;
+mod hash;
+mod history;
 mod logging;
+mod metadata;
+mod network;
+mod queue;
+mod settings;
+mod url_classifier;
 mod validation;
 mod ytdlp_updater;
 
-use binary_manager::BinaryManager;
+use batch::{BatchFailure, BatchStatus, BatchSummary, BatchTracker};
+use binary_manager::{
+    diagnose_tool, BinaryInfo, BinaryManager, FfmpegEncoders, SetupStatus, ToolDiagnostic,
+    REQUIRED_BINARIES,
+};
 use download::{
-    cancel_download, download_content_with_smart_retry, BrowserConfig, DownloadHandle, DownloadType,
+    cancel_all_downloads, cancel_download, date_organized_path, download_content_with_smart_retry,
+    download_temp_root, find_thumbnail, is_sidecar_file, next_lower_quality,
+    report_download_failure, resolve_output_collision, ActiveDownload, BrowserConfig, Collision,
+    DownloadContext, DownloadHandle, DownloadOptions, DownloadType, SubtitleMode,
+    MAX_QUALITY_DOWNGRADES,
+};
+use errors::{CommandError, DownloadError};
+use hash::HashAlgorithm;
+use history::{DownloadOutcome, DownloadRecord, HistoryManager};
+use metadata::{
+    fetch_playlist_entries, fetch_video_info, parse_video_metadata, AudioSource,
+    AvailableQualities, PlaylistInfo, SubtitleKind, SubtitleTrack, VideoMetadata,
+    INFO_FETCH_TIMEOUT,
 };
-use validation::validate_path;
-use ytdlp_updater::YtdlpUpdater;
+use network::{github_ratelimit, resolve_proxy, GithubRateLimit};
+use queue::{DownloadQueue, MAX_CONCURRENT_DOWNLOADS};
+use settings::{AppSettings, SettingsManager, SUPPORTED_BROWSERS};
+use url_classifier::{platform_defaults, PlatformCapabilities, UrlClassification, UrlValidation};
+use validation::{
+    check_windows_path_length, sanitize_filename, validate_output_path, validate_path,
+    validate_url, NO_LIBRARY_ROOT_ERROR,
+};
+use ytdlp_updater::{version_days_behind, YtdlpUpdater};
+
+/// Application state shared across all commands
+struct AppState {
+    ytdlp_updater: Arc<Mutex<YtdlpUpdater>>,
+    active_downloads: Arc<Mutex<HashMap<String, DownloadHandle>>>,
+    binary_manager: Arc<BinaryManager>,
+    download_queue: Arc<DownloadQueue>,
+    settings: Arc<SettingsManager>,
+    /// Latest yt-dlp info per URL, reused by get_available_qualities and downloads
+    metadata_cache: Arc<Mutex<HashMap<String, VideoMetadata>>>,
+    setup_status: Arc<Mutex<SetupStatus>>,
+    info_fetches: Arc<Mutex<HashMap<String, CommandChild>>>,
+    history: Arc<HistoryManager>,
+    batches: Arc<Mutex<HashMap<String, BatchTracker>>>,
+    /// Ids of downloads from passing the setup check until they finish, including
+    /// the info fetches before they reach the queue
+    downloads_in_flight: Arc<std::sync::Mutex<HashSet<String>>>,
+}
+
+/// Detect the platform from a URL
+#[tauri::command]
+async fn detect_platform(url: String) -> Result<String, String> {
+    info!("Detecting platform for URL: {}", url);
+
+    match url_classifier::detect_platform(&url) {
+        Some(platform) => Ok(platform.to_string()),
+        None => {
+            warn!("Unsupported platform: {}", url);
+            Err("Unsupported platform".to_string())
+        }
+    }
+}
+
+/// Classify a URL as a video, playlist or channel
+#[tauri::command]
+async fn classify_url(url: String) -> Result<UrlClassification, String> {
+    info!("Classifying URL: {}", url);
+
+    url_classifier::classify_url(&url).ok_or_else(|| {
+        warn!("Unsupported platform: {}", url);
+        "Unsupported platform".to_string()
+    })
+}
+
+/// Pre-flight a batch of URLs: validity, platform, and repeats of the same video
+#[tauri::command]
+fn validate_urls(urls: Vec<String>) -> Vec<UrlValidation> {
+    info!("Validating {} URLs", urls.len());
+    url_classifier::validate_url_batch(&urls)
+}
+
+/// List the platforms ripVID supports and which controls apply to each
+#[tauri::command]
+fn get_supported_platforms() -> Vec<PlatformCapabilities> {
+    url_classifier::SUPPORTED_PLATFORMS.to_vec()
+}
+
+/// Get video information using yt-dlp
+/// `request_id` lets the UI cancel a slow fetch via cancel_video_info; `timeout_secs`
+/// defaults to 30 seconds, after which yt-dlp is killed. Failures are classified like
+/// download errors and returned as `{ code, message }`
+#[tauri::command]
+async fn get_video_info(
+    url: String,
+    request_id: Option<String>,
+    timeout_secs: Option<u64>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, CommandError> {
+    info!("Fetching video info for: {}", url);
+
+    let request_id = request_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let timeout = timeout_secs
+        .map(Duration::from_secs)
+        .unwrap_or(INFO_FETCH_TIMEOUT);
+
+    match fetch_video_info(&app, &url, &request_id, state.info_fetches.clone(), timeout).await {
+        Ok(json_output) => {
+            info!("Successfully fetched video info");
+            Ok(json_output)
+        }
+        Err(e) => {
+            error!("Failed to fetch video info: {}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// Cancel an in-flight get_video_info call
+#[tauri::command]
+async fn cancel_video_info(
+    request_id: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    let child = state.info_fetches.lock().await.remove(&request_id);
+
+    match child {
+        Some(child) => {
+            info!("Cancelling video info fetch: {}", request_id);
+            child.kill().map_err(|e| e.to_string())
+        }
+        None => Err(format!("No video info fetch found with id: {}", request_id)),
+    }
+}
+
+/// List a playlist's entries without fetching full metadata for each video
+/// Cancellable through cancel_video_info with the same `request_id`; use
+/// get_video_metadata for the items the user actually picks
+#[tauri::command]
+async fn get_playlist_entries(
+    url: String,
+    request_id: Option<String>,
+    timeout_secs: Option<u64>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<PlaylistInfo, CommandError> {
+    info!("Fetching playlist entries for: {}", url);
+
+    let request_id = request_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let timeout = timeout_secs
+        .map(Duration::from_secs)
+        .unwrap_or(INFO_FETCH_TIMEOUT);
+
+    let playlist =
+        fetch_playlist_entries(&app, &url, &request_id, state.info_fetches.clone(), timeout)
+            .await
+            .map_err(|e| {
+                error!("Failed to fetch playlist entries: {}", e);
+                CommandError::from(e)
+            })?;
+
+    info!("Playlist has {} entries", playlist.entries.len());
+    Ok(playlist)
+}
+
+/// Get structured video metadata, including per-format language for audio track pickers
+#[tauri::command]
+async fn get_video_metadata(
+    url: String,
+    request_id: Option<String>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<VideoMetadata, CommandError> {
+    let json_output = get_video_info(url.clone(), request_id, None, app, state.clone()).await?;
+    let metadata = parse_video_metadata(&json_output).map_err(|e| {
+        error!("Failed to parse video metadata: {}", e);
+        CommandError::from(e)
+    })?;
+    state
+        .metadata_cache
+        .lock()
+        .await
+        .insert(url, metadata.clone());
+    Ok(metadata)
+}
+
+/// Get the qualities a URL actually offers so the dropdown only shows real options
+/// Metadata is cached per URL for the lifetime of the app
+#[tauri::command]
+async fn get_available_qualities(
+    url: String,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<AvailableQualities, CommandError> {
+    if let Some(cached) = state.metadata_cache.lock().await.get(&url) {
+        debug!("Using cached qualities for: {}", url);
+        return Ok(cached.available_qualities());
+    }
+
+    let metadata = get_video_metadata(url.clone(), None, app, state).await?;
+    let qualities = metadata.available_qualities();
+    info!("Available qualities for {}: {:?}", url, qualities.qualities);
+    Ok(qualities)
+}
+
+/// List the subtitle languages a video offers, labelled manual or auto-generated
+/// Manual tracks go in manual_sub_langs and auto ones in auto_sub_langs
+#[tauri::command]
+async fn list_subtitles(
+    url: String,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<SubtitleTrack>, CommandError> {
+    let metadata = get_video_metadata(url, None, app, state).await?;
+    let tracks = metadata.subtitle_tracks();
+    info!(
+        "Found {} subtitle tracks ({} auto-generated)",
+        tracks.len(),
+        tracks
+            .iter()
+            .filter(|track| track.kind == SubtitleKind::Auto)
+            .count()
+    );
+    Ok(tracks)
+}
+
+/// Show the name a download will be saved under before starting it
+/// Expands the yt-dlp style `template` (e.g. "%(title)s [%(id)s].%(ext)s") from the
+/// video's metadata with yt-dlp's character substitutions, then cleans it for this OS.
+/// `%(ext)s` follows the same rules as the download: the merge container (mkv for
+/// best quality, webm for free formats) or the audio format
+#[tauri::command]
+async fn preview_filename(
+    url: String,
+    template: String,
+    audio_only: Option<bool>,
+    quality: Option<String>,
+    options: Option<DownloadOptions>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, CommandError> {
+    let invalid_input = |message: String| CommandError::from(DownloadError::InvalidInput(message));
+    let url = validate_url(&url).map_err(invalid_input)?;
+    if template.trim().is_empty() {
+        return Err(invalid_input(
+            "Filename template cannot be empty".to_string(),
+        ));
+    }
+
+    let settings = state.settings.get().await;
+    let ext = match resolve_download_type(&url, audio_only, quality, options, &settings) {
+        DownloadType::Video { container, .. } => container.as_str(),
+        DownloadType::Audio { audio_format, .. } => audio_format.as_str(),
+    };
+
+    let metadata = get_video_metadata(url, None, app, state).await?;
+    let filename = sanitize_filename(
+        &metadata.render_filename_template(&template, ext),
+        cfg!(windows),
+    );
+    debug!("Filename preview: {}", filename);
+    Ok(filename)
+}
+
+/// Download video with specified quality
+/// Uses smart retry: tries without cookies first, auto-retries with cookies if needed
+#[tauri::command]
+async fn download_video(
+    url: String,
+    output_path: String,
+    quality: Option<String>,
+    options: Option<DownloadOptions>,
+    window: tauri::WebviewWindow,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    let settings = state.settings.get().await;
+    let quality = resolve_quality(&url, quality, &settings);
+    info!("Video download requested: url={}, quality={}", url, quality);
+
+    let download_type = options
+        .unwrap_or_default()
+        .with_subtitle_defaults(&settings)
+        .into_video(quality);
+    let output_path = sync_output_extension(output_path, &download_type);
+    check_windows_path_length(&output_path)?;
+
+    run_download(url, output_path, download_type, window, app, &state)
+        .await
+        .map_err(String::from)
+}
+
+/// Download audio (MP3)
+/// Uses smart retry: tries without cookies first, auto-retries with cookies if needed
+#[tauri::command]
+async fn download_audio(
+    url: String,
+    output_path: String,
+    options: Option<DownloadOptions>,
+    window: tauri::WebviewWindow,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    info!("Audio download requested: url={}", url);
+
+    let default_format = state.settings.get().await.default_audio_format;
+    let download_type = options.unwrap_or_default().into_audio(default_format);
+    let output_path = sync_output_extension(output_path, &download_type);
+    check_windows_path_length(&output_path)?;
+
+    run_download(url, output_path, download_type, window, app, &state)
+        .await
+        .map_err(String::from)
+}
+
+/// Quality for a video download: the requested one, else the platform's default while
+/// the default quality setting is untouched, else that setting
+fn resolve_quality(url: &str, quality: Option<String>, settings: &AppSettings) -> String {
+    let setting_untouched = settings.default_quality == AppSettings::default().default_quality;
+    quality
+        .filter(|q| !q.trim().is_empty())
+        .or_else(|| {
+            platform_defaults(url)
+                .quality
+                .filter(|_| setting_untouched)
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| settings.default_quality.clone())
+}
+
+/// Download type for commands that leave the choice of audio or video open
+/// When `audio_only` isn't given the platform decides, e.g. audio for SoundCloud
+fn resolve_download_type(
+    url: &str,
+    audio_only: Option<bool>,
+    quality: Option<String>,
+    options: Option<DownloadOptions>,
+    settings: &AppSettings,
+) -> DownloadType {
+    let options = options.unwrap_or_default();
+    if audio_only.unwrap_or_else(|| platform_defaults(url).audio_only) {
+        options.into_audio(settings.default_audio_format)
+    } else {
+        options
+            .with_subtitle_defaults(settings)
+            .into_video(resolve_quality(url, quality, settings))
+    }
+}
+
+/// Keep the reported output path in sync with the merge container or audio format
+/// The frontend always builds .mp4/.mp3 paths
+fn sync_output_extension(output_path: String, download_type: &DownloadType) -> String {
+    let (default_ext, ext) = match download_type {
+        DownloadType::Video { container, .. } => ("mp4", container.as_str()),
+        DownloadType::Audio { audio_format, .. } => ("mp3", audio_format.as_str()),
+    };
+
+    let output_file = std::path::Path::new(&output_path);
+    if output_file.extension().and_then(|e| e.to_str()) == Some(default_ext) {
+        output_file
+            .with_extension(ext)
+            .to_string_lossy()
+            .to_string()
+    } else {
+        output_path
+    }
+}
+
+/// One entry of a download_batch request
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BatchItem {
+    url: String,
+    output_path: String,
+    /// None picks audio or video from the platform's defaults
+    #[serde(default)]
+    audio_only: Option<bool>,
+    #[serde(default)]
+    quality: Option<String>,
+    #[serde(default)]
+    options: Option<DownloadOptions>,
+}
+
+/// Download several URLs as one batch
+/// Items run through the download queue like single downloads; once all have
+/// finished a "batch-complete" event reports how many succeeded, failed or were skipped
+#[tauri::command]
+async fn download_batch(
+    items: Vec<BatchItem>,
+    window: tauri::WebviewWindow,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<BatchSummary, String> {
+    let batch_id = Uuid::new_v4().to_string();
+    info!(
+        "Batch download {} requested: {} items",
+        batch_id,
+        items.len()
+    );
+
+    let settings = state.settings.get().await;
+    let mut summary = BatchSummary {
+        batch_id: batch_id.clone(),
+        total: items.len(),
+        ..Default::default()
+    };
+
+    let mut prepared_items = Vec::new();
+    for item in items {
+        let download_type = resolve_download_type(
+            &item.url,
+            item.audio_only,
+            item.quality,
+            item.options,
+            &settings,
+        );
+        let output_path = sync_output_extension(item.output_path, &download_type);
+        let prepared = validate_url(&item.url).and_then(|url| {
+            check_windows_path_length(&output_path)?;
+            Ok(url)
+        });
+        let url = match prepared {
+            Ok(url) => url,
+            Err(reason) => {
+                summary.failed += 1;
+                summary.failures.push(BatchFailure {
+                    url: item.url,
+                    reason,
+                });
+                continue;
+            }
+        };
+
+        prepared_items.push((Uuid::new_v4().to_string(), url, output_path, download_type));
+    }
+
+    let download_ids = prepared_items
+        .iter()
+        .map(|(download_id, _, _, _)| download_id.clone())
+        .collect();
+    state
+        .batches
+        .lock()
+        .await
+        .insert(batch_id.clone(), BatchTracker::new(download_ids));
+    window
+        .emit(
+            "batch-started",
+            serde_json::json!({ "batchId": batch_id, "total": summary.total }),
+        )
+        .ok();
+
+    let mut handles = Vec::new();
+    for (download_id, url, output_path, download_type) in prepared_items {
+        let window = window.clone();
+        let app = app.clone();
+        let task_url = url.clone();
+        let task_batch_id = batch_id.clone();
+        let handle = tauri::async_runtime::spawn(async move {
+            let state = app.state::<AppState>();
+            let result = run_download_to_path(
+                download_id,
+                task_url,
+                output_path,
+                download_type,
+                window,
+                app.clone(),
+                &state,
+            )
+            .await;
+
+            if let Some(tracker) = state.batches.lock().await.get_mut(&task_batch_id) {
+                match &result {
+                    Ok(DownloadResult::Completed(saved_path)) => {
+                        tracker.finish(Some(fs::metadata(saved_path).map(|m| m.len()).unwrap_or(0)))
+                    }
+                    Ok(DownloadResult::Skipped(_)) => tracker.skip(),
+                    Err(_) => tracker.finish(None),
+                }
+            }
+            result
+        });
+        handles.push((url, handle));
+    }
+
+    for (url, handle) in handles {
+        let reason = match handle.await {
+            Ok(Ok(DownloadResult::Completed(_))) => {
+                summary.succeeded += 1;
+                continue;
+            }
+            Ok(Ok(DownloadResult::Skipped(_))) | Ok(Err(DownloadError::Cancelled)) => {
+                summary.skipped += 1;
+                continue;
+            }
+            Ok(Err(e)) => e.to_string(),
+            Err(e) => {
+                error!("Batch download task failed: {}", e);
+                e.to_string()
+            }
+        };
+        summary.failed += 1;
+        summary.failures.push(BatchFailure { url, reason });
+    }
+
+    info!(
+        "Batch download {} finished: {} succeeded, {} failed, {} skipped",
+        batch_id, summary.succeeded, summary.failed, summary.skipped
+    );
+    state.batches.lock().await.remove(&batch_id);
+    window.emit("batch-complete", &summary).ok();
+    Ok(summary)
+}
+
+/// Every running download with its latest phase and progress
+/// Read-only; queued downloads that haven't started yet are not included
+#[tauri::command]
+async fn get_active_downloads(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<ActiveDownload>, String> {
+    // Copy out of the map first so the event loops aren't blocked while trackers are read
+    let handles: Vec<_> = state
+        .active_downloads
+        .lock()
+        .await
+        .values()
+        .map(|handle| {
+            (
+                handle.id.clone(),
+                handle.url.clone(),
+                handle.output_path.clone(),
+                handle.items.clone(),
+            )
+        })
+        .collect();
+
+    let mut downloads = Vec::new();
+    for (id, url, output_path, items) in handles {
+        downloads.push(items.lock().await.snapshot(id, url, output_path));
+    }
+    Ok(downloads)
+}
+
+/// Completed/total counts, bytes downloaded and a rough ETA for a running batch
+/// The ETA uses the current speed of the batch's active downloads
+#[tauri::command]
+async fn get_batch_status(
+    batch_id: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<BatchStatus, String> {
+    let tracker = state
+        .batches
+        .lock()
+        .await
+        .get(&batch_id)
+        .cloned()
+        .ok_or_else(|| format!("No running batch with id {}", batch_id))?;
+
+    let items: Vec<_> = {
+        let downloads = state.active_downloads.lock().await;
+        tracker
+            .download_ids
+            .iter()
+            .filter_map(|id| downloads.get(id))
+            .map(|handle| handle.items.clone())
+            .collect()
+    };
+
+    let mut active = Vec::new();
+    for items in items {
+        if let Some(progress) = items.lock().await.latest_progress.clone() {
+            active.push(progress);
+        }
+    }
+
+    Ok(tracker.status(&batch_id, &active))
+}
+
+/// A download_to_path request
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PathDownloadRequest {
+    url: String,
+    /// Absolute path of the file to write
+    output_path: String,
+    /// None picks audio or video from the platform's defaults
+    #[serde(default)]
+    audio_only: Option<bool>,
+    #[serde(default)]
+    quality: Option<String>,
+    #[serde(default)]
+    options: Option<DownloadOptions>,
+}
+
+/// Download to any absolute path instead of the library folders
+/// For integrating with other tools; paths outside home and temp must be
+/// listed in the allowedDirectories setting. The path is used exactly as given.
+#[tauri::command]
+async fn download_to_path(
+    request: PathDownloadRequest,
+    window: tauri::WebviewWindow,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    let PathDownloadRequest {
+        url,
+        output_path,
+        audio_only,
+        quality,
+        options,
+    } = request;
+    info!("Custom path download requested: url={}", url);
+
+    let settings = state.settings.get().await;
+    let allowed_directories: Vec<std::path::PathBuf> = settings
+        .allowed_directories
+        .iter()
+        .map(std::path::PathBuf::from)
+        .collect();
+
+    let url = validate_url(&url)?;
+    let output_path = validate_output_path(&output_path, &allowed_directories)?
+        .to_string_lossy()
+        .to_string();
+
+    let download_type = resolve_download_type(&url, audio_only, quality, options, &settings);
+
+    run_download(url, output_path, download_type, window, app, &state)
+        .await
+        .map_err(String::from)
+}
+
+/// Save only the .info.json, thumbnail and description of a video into a folder
+/// Unlike get_video_metadata the files are kept, for offline archiving
+#[tauri::command]
+async fn download_metadata_only(
+    url: String,
+    output_dir: String,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<String>, CommandError> {
+    info!("Metadata-only download requested: url={}", url);
+    ensure_setup_finished(&state).await?;
+
+    let invalid_input = |message: String| CommandError::from(DownloadError::InvalidInput(message));
+    let url = validate_url(&url).map_err(invalid_input)?;
+
+    let settings = state.settings.get().await;
+    let allowed_directories: Vec<std::path::PathBuf> = settings
+        .allowed_directories
+        .iter()
+        .map(std::path::PathBuf::from)
+        .collect();
+    let output_dir =
+        validate_path(&output_dir, true, &allowed_directories).map_err(invalid_input)?;
+
+    download::download_metadata_only(
+        url,
+        output_dir,
+        resolve_proxy(settings.proxy.as_deref()),
+        app,
+        state.ytdlp_updater.clone(),
+    )
+    .await
+    .map_err(CommandError::from)
+}
+
+/// Download the first few seconds of a URL to a temp folder and delete it again
+/// Verifies cookies and format selection before committing to a long download.
+/// `browser` tests with that browser's cookies; without it no cookies are used.
+/// `request_id` lets the UI stop the test via cancel_video_info
+#[tauri::command]
+async fn test_download(
+    url: String,
+    audio_only: Option<bool>,
+    quality: Option<String>,
+    options: Option<DownloadOptions>,
+    browser: Option<String>,
+    request_id: Option<String>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), CommandError> {
+    info!("Test download requested: url={}", url);
+    ensure_setup_finished(&state).await?;
+
+    let invalid_input = |message: String| CommandError::from(DownloadError::InvalidInput(message));
+    let url = validate_url(&url).map_err(invalid_input)?;
+
+    let settings = state.settings.get().await;
+    let download_type = resolve_download_type(&url, audio_only, quality, options, &settings);
+
+    let browser_config = match browser.as_deref() {
+        Some(browser) if SUPPORTED_BROWSERS.contains(&browser) => {
+            BrowserConfig::for_browser(browser)
+        }
+        Some(browser) => {
+            return Err(invalid_input(format!("Unsupported browser '{}'", browser)));
+        }
+        None => BrowserConfig::new(false),
+    };
+
+    let request_id = request_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    download::test_download(
+        url,
+        download_type,
+        browser_config,
+        resolve_proxy(settings.proxy.as_deref()),
+        &request_id,
+        state.info_fetches.clone(),
+        app,
+        state.ytdlp_updater.clone(),
+        state.binary_manager.clone(),
+    )
+    .await
+    .map_err(CommandError::from)
+}
+
+/// Reject work that needs yt-dlp/ffmpeg while the startup binary check is still running
+async fn ensure_setup_finished(state: &AppState) -> Result<(), DownloadError> {
+    check_setup_finished(&state.setup_status.lock().await)
+}
+
+fn check_setup_finished(status: &SetupStatus) -> Result<(), DownloadError> {
+    if status.in_progress {
+        return Err(DownloadError::MissingDependency(
+            "ripVID is still setting up its download tools. Try again once setup finishes."
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Keeps a download listed in `downloads_in_flight` until dropped
+struct InFlightDownload {
+    id: String,
+    in_flight: Arc<std::sync::Mutex<HashSet<String>>>,
+}
+
+impl Drop for InFlightDownload {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            in_flight.remove(&self.id);
+        }
+    }
+}
+
+/// Pass the setup check and register the download in one step
+/// Both happen under the setup_status lock, so reset_binaries can't start in between
+/// and delete the binaries before the download is counted
+async fn begin_download(
+    state: &AppState,
+    download_id: &str,
+) -> Result<InFlightDownload, DownloadError> {
+    let status = state.setup_status.lock().await;
+    check_setup_finished(&status)?;
+    if let Ok(mut in_flight) = state.downloads_in_flight.lock() {
+        in_flight.insert(download_id.to_string());
+    }
+    Ok(InFlightDownload {
+        id: download_id.to_string(),
+        in_flight: state.downloads_in_flight.clone(),
+    })
+}
+
+/// yt-dlp info for one download, fetched at most once and shared by the checks before it
+/// Starts from the cached metadata when the URL was already looked up. The fetch runs
+/// under the download's id, which the UI learns from "download-preparing", so
+/// cancel_download_command can stop it
+struct DownloadInfo {
+    url: String,
+    request_id: String,
+    metadata: Option<VideoMetadata>,
+    attempted: bool,
+}
+
+impl DownloadInfo {
+    async fn new(url: &str, download_id: &str, state: &AppState) -> Self {
+        let metadata = state.metadata_cache.lock().await.get(url).cloned();
+        Self {
+            url: url.to_string(),
+            request_id: download_id.to_string(),
+            attempted: metadata.is_some(),
+            metadata,
+        }
+    }
+
+    /// Metadata if it is already known, without running yt-dlp
+    fn cached(&self) -> Option<&VideoMetadata> {
+        self.metadata.as_ref()
+    }
+
+    /// Metadata, fetched on first use
+    /// Only the first failed fetch reports its real error; later calls don't retry it
+    async fn fetch(
+        &mut self,
+        app: &tauri::AppHandle,
+        state: &AppState,
+    ) -> Result<&VideoMetadata, DownloadError> {
+        if !self.attempted {
+            self.attempted = true;
+            let metadata = fetch_video_info(
+                app,
+                &self.url,
+                &self.request_id,
+                state.info_fetches.clone(),
+                INFO_FETCH_TIMEOUT,
+            )
+            .await
+            .and_then(|json| parse_video_metadata(&json))?;
+            state
+                .metadata_cache
+                .lock()
+                .await
+                .insert(self.url.clone(), metadata.clone());
+            self.metadata = Some(metadata);
+        }
+
+        self.metadata.as_ref().ok_or_else(|| {
+            DownloadError::ProcessFailed("Video info could not be fetched".to_string())
+        })
+    }
+}
+
+/// Make sure a URL offers a video-only and an audio-only stream before saving them unmerged
+async fn check_separate_streams(
+    app: &tauri::AppHandle,
+    info: &mut DownloadInfo,
+    state: &AppState,
+) -> Result<(), DownloadError> {
+    if info.fetch(app, state).await?.has_separate_streams() {
+        Ok(())
+    } else {
+        Err(DownloadError::InvalidInput(
+            "This video has no separate video and audio streams. Turn off separate streams to download it merged.".to_string(),
+        ))
+    }
+}
+
+/// Qualities for the download's quality ladder
+/// Empty when they can't be fetched, so the ladder isn't restricted; only a cancel fails
+async fn available_qualities_for(
+    app: &tauri::AppHandle,
+    info: &mut DownloadInfo,
+    state: &AppState,
+) -> Result<Vec<String>, DownloadError> {
+    match info.fetch(app, state).await {
+        Ok(metadata) => Ok(metadata.available_qualities().qualities),
+        Err(DownloadError::Cancelled) => Err(DownloadError::Cancelled),
+        Err(e) => {
+            warn!("Could not fetch qualities for quality fallback: {}", e);
+            Ok(Vec::new())
+        }
+    }
+}
+
+/// Check where an audio download's sound will come from before starting it
+/// Fails when the source has no audio at all, notes a slower extraction from a
+/// muxed stream, and turns off cover art for X Spaces. Info is only fetched for X,
+/// which needs it for the cover art; elsewhere the check uses cached metadata or is
+/// skipped, and a silent source fails from yt-dlp's own error instead
+async fn check_audio_source(
+    app: &tauri::AppHandle,
+    info: &mut DownloadInfo,
+    download_type: DownloadType,
+    window: &tauri::WebviewWindow,
+    state: &AppState,
+) -> Result<DownloadType, DownloadError> {
+    let url = info.url.clone();
+    let url = url.as_str();
+    let is_x = url_classifier::detect_platform(url) == Some("x");
+    let metadata = if is_x {
+        match info.fetch(app, state).await {
+            Ok(metadata) => metadata,
+            Err(DownloadError::Cancelled) => return Err(DownloadError::Cancelled),
+            Err(e) => {
+                warn!("Could not check the audio source of {}: {}", url, e);
+                return Ok(download_type);
+            }
+        }
+    } else {
+        match info.cached() {
+            Some(metadata) => metadata,
+            None => return Ok(download_type),
+        }
+    };
+
+    match metadata.audio_source() {
+        AudioSource::NoAudio => {
+            return Err(DownloadError::ContentUnavailable(
+                "This video has no audio track, so there is no audio to download.".to_string(),
+            ));
+        }
+        AudioSource::Muxed => {
+            let message = "No audio-only stream is available, so the audio will be extracted from the video. This takes longer.";
+            info!("{} ({})", message, url);
+            window
+                .emit(
+                    "download-warning",
+                    serde_json::json!({
+                        "url": url,
+                        "message": message
+                    }),
+                )
+                .ok();
+        }
+        AudioSource::AudioOnly | AudioSource::Unknown => {}
+    }
+
+    if is_x && metadata.is_audio_only() {
+        info!("Audio-only X source, skipping cover art: {}", url);
+        return Ok(download_type.for_audio_only_source());
+    }
+    Ok(download_type)
+}
+
+/// Narrow a download's subtitle languages to the video's tracks (see resolve_sub_langs)
+/// Only needed when auto-generated captions are chosen. Without the info, the auto
+/// languages are dropped if uploaded ones were chosen too, since yt-dlp would fetch
+/// auto captions for the uploaded-only languages
+async fn resolve_subtitle_langs(
+    app: &tauri::AppHandle,
+    info: &mut DownloadInfo,
+    download_type: DownloadType,
+    state: &AppState,
+) -> Result<DownloadType, DownloadError> {
+    let manual = download_type.manual_sub_langs();
+    let auto = download_type.auto_sub_langs();
+    // A legacy "auto" entry asks for every auto-generated track, so there is nothing to narrow
+    if manual.iter().chain(auto).any(|lang| lang == "auto") {
+        return Ok(download_type);
+    }
+
+    match info.fetch(app, state).await {
+        Ok(metadata) => {
+            let (manual, auto) = metadata.resolve_sub_langs(manual, auto);
+            debug!("Subtitle languages: uploaded {:?}, auto {:?}", manual, auto);
+            Ok(download_type.with_sub_langs(manual, auto))
+        }
+        Err(DownloadError::Cancelled) => Err(DownloadError::Cancelled),
+        Err(e) if manual.is_empty() => {
+            warn!("Could not check subtitle tracks: {}", e);
+            Ok(download_type)
+        }
+        Err(e) => {
+            warn!(
+                "Could not check subtitle tracks, downloading uploaded subtitles only: {}",
+                e
+            );
+            Ok(download_type.with_sub_langs(manual.to_vec(), Vec::new()))
+        }
+    }
+}
+
+/// Move a download's output into YYYY/YYYY-MM/ folders by upload date
+/// The folders are computed here rather than through a yt-dlp template, so the
+/// final path is known up front for collision handling, history and reveal
+async fn organize_output_by_date(
+    app: &tauri::AppHandle,
+    info: &mut DownloadInfo,
+    output_path: &str,
+    state: &AppState,
+) -> Result<String, DownloadError> {
+    let upload_date = match info.fetch(app, state).await {
+        Ok(metadata) => metadata.upload_date.clone(),
+        Err(DownloadError::Cancelled) => return Err(DownloadError::Cancelled),
+        Err(e) => {
+            warn!(
+                "Could not read upload date, organizing by today's date: {}",
+                e
+            );
+            None
+        }
+    };
+
+    let organized = date_organized_path(output_path, upload_date.as_deref());
+    let settings = state.settings.get().await;
+    let allowed_directories = settings.accessible_dirs();
+
+    // Each missing folder is validated before it is created, top down, so nothing
+    // is created outside the allowed directories through a symlinked folder
+    let mut missing: Vec<&std::path::Path> = organized
+        .parent()
+        .into_iter()
+        .flat_map(std::path::Path::ancestors)
+        .take_while(|dir| !dir.exists())
+        .collect();
+    missing.reverse();
+    for dir in missing {
+        validate_output_path(&dir.to_string_lossy(), &allowed_directories)
+            .map_err(DownloadError::InvalidInput)?;
+        fs::create_dir_all(dir)?;
+    }
+
+    let organized = validate_output_path(&organized.to_string_lossy(), &allowed_directories)
+        .map_err(DownloadError::InvalidInput)?
+        .to_string_lossy()
+        .to_string();
+
+    debug!("Organized output by date: {}", organized);
+    Ok(organized)
+}
+
+/// Run a download with smart retry and record the outcome in history
+/// A cancelled download resolves with its id like a finished one; the UI already
+/// learned about the cancel from "download-cancelled"
+async fn run_download(
+    url: String,
+    output_path: String,
+    download_type: DownloadType,
+    window: tauri::WebviewWindow,
+    app: tauri::AppHandle,
+    state: &AppState,
+) -> Result<String, DownloadError> {
+    let download_id = Uuid::new_v4().to_string();
+    let result = run_download_to_path(
+        download_id.clone(),
+        url,
+        output_path,
+        download_type,
+        window,
+        app,
+        state,
+    )
+    .await;
+
+    match result {
+        Ok(_) | Err(DownloadError::Cancelled) => Ok(download_id),
+        Err(e) => Err(e),
+    }
+}
+
+/// How run_download_to_path ended when it didn't fail
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DownloadResult {
+    /// Saved to this path
+    Completed(String),
+    /// The collision setting is Skip and this path already existed, so nothing ran
+    Skipped(String),
+}
+
+/// Add a finished request to history, trimmed to the user's limit
+async fn record_download(state: &AppState, record: DownloadRecord) {
+    let max_history_entries = state.settings.get().await.max_history_entries;
+    state.history.add(record, max_history_entries).await;
+}
+
+/// Like run_download, also reporting where the file was saved or that it was skipped
+/// With quality fallback on, failures step down the quality ladder before giving up;
+/// every attempt uses `download_id`
+async fn run_download_to_path(
+    download_id: String,
+    url: String,
+    output_path: String,
+    mut download_type: DownloadType,
+    window: tauri::WebviewWindow,
+    app: tauri::AppHandle,
+    state: &AppState,
+) -> Result<DownloadResult, DownloadError> {
+    let _in_flight = begin_download(state, &download_id).await?;
+    // History keeps the options as requested, so a retry starts again from the
+    // requested quality rather than the last rung of the fallback ladder
+    let requested_type = download_type.clone();
+
+    // Sent before any info fetch, so the UI has the id to cancel a slow fetch with
+    window
+        .emit(
+            "download-preparing",
+            serde_json::json!({
+                "id": download_id,
+                "url": url,
+                "path": output_path
+            }),
+        )
+        .ok();
+    let mut info = DownloadInfo::new(&url, &download_id, state).await;
+    if download_type.separate_streams() {
+        check_separate_streams(&app, &mut info, state).await?;
+    }
+    if matches!(download_type, DownloadType::Audio { .. }) {
+        download_type = check_audio_source(&app, &mut info, download_type, &window, state).await?;
+    }
+    if download_type.subtitle_mode() != SubtitleMode::None
+        && !download_type.auto_sub_langs().is_empty()
+    {
+        download_type = resolve_subtitle_langs(&app, &mut info, download_type, state).await?;
+    }
+
+    // History keeps the path as requested, so a retry adds date folders and resolves
+    // collisions afresh instead of nesting YYYY/YYYY-MM/ a second time
+    let requested_path = output_path.clone();
+    let output_path = if state.settings.get().await.organize_by_date {
+        organize_output_by_date(&app, &mut info, &output_path, state).await?
+    } else {
+        output_path
+    };
+
+    // Resolved once so every retry attempt and the history entry use the same name
+    let output_path = resolve_output_collision(&output_path, download_type.collision());
+
+    // Checked on the final path, after date folders are added; skipped files aren't revealed
+    if download_type.collision() == Collision::Skip && std::path::Path::new(&output_path).exists() {
+        info!("Output already exists, skipping download: {}", output_path);
+        window
+            .emit(
+                "download-skipped",
+                serde_json::json!({
+                    "id": download_id,
+                    "path": output_path
+                }),
+            )
+            .ok();
+        let record = DownloadRecord::new(
+            url,
+            requested_path,
+            requested_type,
+            DownloadOutcome::Skipped,
+            None,
+        );
+        record_download(state, record).await;
+        return Ok(DownloadResult::Skipped(output_path));
+    }
+
+    let ctx = DownloadContext {
+        window: window.clone(),
+        app: app.clone(),
+        ytdlp_updater: state.ytdlp_updater.clone(),
+        active_downloads: state.active_downloads.clone(),
+        binary_manager: state.binary_manager.clone(),
+        download_queue: state.download_queue.clone(),
+        playlist_stop: Arc::default(),
+    };
+    let mut downgrades = 0;
+    let mut network_failures = 0;
+    let result = loop {
+        let result = download_content_with_smart_retry(
+            &ctx,
+            download_id.clone(),
+            url.clone(),
+            output_path.clone(),
+            download_type.clone(),
+            state.settings.clone(),
+        )
+        .await;
+
+        if !download_type.quality_fallback() || downgrades >= MAX_QUALITY_DOWNGRADES {
+            break result;
+        }
+
+        // Step down a quality when this one doesn't exist or the connection keeps failing
+        let network_error = matches!(result, Err(DownloadError::Network(_)));
+        if !network_error && !matches!(result, Err(DownloadError::QualityNotAvailable(_))) {
+            break result;
+        }
+        if network_error {
+            network_failures += 1;
+            if network_failures < 2 {
+                warn!(
+                    "Network error downloading {}, retrying at the same quality",
+                    url
+                );
+                continue;
+            }
+        }
+
+        let current = download_type.quality().unwrap_or_default().to_string();
+        let available = match available_qualities_for(&app, &mut info, state).await {
+            Ok(available) => available,
+            Err(e) => break Err(e),
+        };
+        let next = match next_lower_quality(&current, &available) {
+            Some(next) => next,
+            None => break result,
+        };
+
+        warn!(
+            "Quality {} failed for {}, retrying at {}",
+            current, url, next
+        );
+        window
+            .emit(
+                "quality-downgraded",
+                serde_json::json!({
+                    "url": url,
+                    "path": output_path,
+                    "from": current,
+                    "to": next
+                }),
+            )
+            .ok();
+        download_type = download_type.with_quality(next);
+        downgrades += 1;
+        network_failures = 0;
+    };
+
+    // Reported here rather than per attempt, once no retry or downgrade is left
+    match &result {
+        Ok(_) | Err(DownloadError::Cancelled) => {}
+        Err(e) => report_download_failure(&ctx, &download_id, e),
+    }
+
+    // Only successful downloads open the folder; failures and cancels stay quiet
+    if result.is_ok() {
+        let settings = state.settings.get().await;
+        if settings.open_on_complete {
+            if let Err(e) = reveal_file_location(output_path.clone(), &settings) {
+                warn!("Could not open download location: {}", e);
+            }
+        }
+    }
+
+    let (outcome, error) = match &result {
+        Ok(_) => (DownloadOutcome::Completed, None),
+        Err(DownloadError::Cancelled) => (DownloadOutcome::Cancelled, None),
+        Err(e) => (DownloadOutcome::Failed, Some(e.to_string())),
+    };
+    let record = DownloadRecord::new(url, requested_path, requested_type, outcome, error);
+    record_download(state, record).await;
+
+    result.map(|_| DownloadResult::Completed(output_path))
+}
+
+/// Re-run a download from history with the exact options it used
+#[tauri::command]
+async fn retry_download(
+    history_id: String,
+    window: tauri::WebviewWindow,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    info!("Retry requested for history entry: {}", history_id);
+
+    let record = state
+        .history
+        .get(&history_id)
+        .await
+        .ok_or_else(|| format!("Download not found in history: {}", history_id))?;
+
+    // The stored request may predate a settings change or a moved folder, and
+    // history.json is a plain file, so the path is checked like a new request's
+    let url = validate_url(&record.url)?;
+    let settings = state.settings.get().await;
+    let output_path = validate_output_path(&record.output_path, &settings.accessible_dirs())?
+        .to_string_lossy()
+        .to_string();
+    let output_file = std::path::Path::new(&output_path);
+    match output_file.parent() {
+        Some(parent) if parent.is_dir() => {}
+        _ => {
+            return Err(format!(
+                "Output folder no longer exists: {}",
+                record.output_path
+            ))
+        }
+    }
+
+    run_download(url, output_path, record.download_type, window, app, &state)
+        .await
+        .map_err(String::from)
+}
+
+/// Get past downloads, newest first
+#[tauri::command]
+async fn get_download_history(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<DownloadRecord>, String> {
+    Ok(state.history.list().await)
+}
+
+/// Cancel an active or queued download, or one still fetching video info
+/// Takes the id from "download-preparing" or "download-queued"
+#[tauri::command]
+async fn cancel_download_command(
+    download_id: String,
+    window: tauri::WebviewWindow,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    info!("Cancel requested for download: {}", download_id);
+
+    // Still fetching info before the download starts; the fetch ends as Cancelled
+    if let Some(child) = state.info_fetches.lock().await.remove(&download_id) {
+        info!("Stopping the info fetch of download: {}", download_id);
+        child.kill().map_err(|e| e.to_string())?;
+        window
+            .emit(
+                "download-cancelled",
+                serde_json::json!({ "id": download_id }),
+            )
+            .ok();
+        return Ok(());
+    }
+
+    cancel_download(
+        download_id,
+        state.active_downloads.clone(),
+        state.download_queue.clone(),
+        window,
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Run yt-dlp, ffmpeg and ffprobe with their version flags
+/// Confirms the resolved binaries actually execute, for support diagnostics
+#[tauri::command]
+async fn diagnose(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<ToolDiagnostic>, String> {
+    info!("Running tool diagnostics");
+
+    let mut results = vec![diagnose_ytdlp(&app, &state).await?];
+
+    for name in ["ffmpeg", "ffprobe"] {
+        let path = state.binary_manager.get_binary_path(name)?;
+        results.push(
+            diagnose_tool(
+                name,
+                path.to_string_lossy().to_string(),
+                app.shell().command(&path).args(["-version"]),
+            )
+            .await,
+        );
+    }
+
+    Ok(results)
+}
+
+/// Run yt-dlp --version, resolved the same way downloads do: updated binary, else bundled sidecar
+async fn diagnose_ytdlp(
+    app: &tauri::AppHandle,
+    state: &AppState,
+) -> Result<ToolDiagnostic, String> {
+    let ytdlp_path = state.ytdlp_updater.lock().await.get_ytdlp_path()?;
+    let ytdlp_result = if ytdlp_path == std::path::Path::new("yt-dlp") {
+        match app.shell().sidecar("yt-dlp") {
+            Ok(command) => {
+                diagnose_tool(
+                    "yt-dlp",
+                    "bundled sidecar".to_string(),
+                    command.args(["--version"]),
+                )
+                .await
+            }
+            Err(e) => ToolDiagnostic {
+                name: "yt-dlp".to_string(),
+                path: "bundled sidecar".to_string(),
+                version: None,
+                error: Some(format!("Failed to create sidecar: {}", e)),
+            },
+        }
+    } else {
+        diagnose_tool(
+            "yt-dlp",
+            ytdlp_path.to_string_lossy().to_string(),
+            app.shell().command(&ytdlp_path).args(["--version"]),
+        )
+        .await
+    };
+
+    Ok(ytdlp_result)
+}
+
+/// Result of preflight_check
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PreflightReport {
+    ytdlp_current: Option<String>,
+    /// None when GitHub couldn't be reached
+    ytdlp_latest: Option<String>,
+    days_behind: Option<u32>,
+    /// More than the ytdlpOutdatedDays setting behind the latest release, after
+    /// any auto update
+    ytdlp_outdated: bool,
+    /// Set when auto_update installed the latest release during the check
+    ytdlp_updated: bool,
+    ffmpeg_available: bool,
+}
+
+/// Check the tools before a large batch so it doesn't fail midway on an extraction change
+/// Compares the installed yt-dlp with the latest GitHub release; with `auto_update`
+/// (the user's consent) an outdated yt-dlp is updated right away
+#[tauri::command]
+async fn preflight_check(
+    auto_update: Option<bool>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<PreflightReport, String> {
+    let threshold = state.settings.get().await.ytdlp_outdated_days;
+    let mut ytdlp_current = diagnose_ytdlp(&app, &state).await?.version;
+
+    // Cloned so downloads aren't blocked on the updater while GitHub is queried
+    let updater = state.ytdlp_updater.lock().await.clone();
+    let ytdlp_latest = match updater.latest_version().await {
+        Ok(latest) => Some(latest),
+        Err(e) => {
+            warn!("Preflight could not fetch the latest yt-dlp release: {}", e);
+            None
+        }
+    };
+
+    let days_behind = |current: &Option<String>| match (current, &ytdlp_latest) {
+        (Some(current), Some(latest)) => version_days_behind(current, latest),
+        _ => None,
+    };
+    let is_outdated = |behind: Option<u32>| matches!(behind, Some(days) if days > threshold);
+    let mut behind = days_behind(&ytdlp_current);
+    let mut ytdlp_outdated = is_outdated(behind);
+
+    let mut ytdlp_updated = false;
+    if ytdlp_outdated {
+        warn!(
+            "yt-dlp {:?} is {:?} days behind {:?}",
+            ytdlp_current, behind, ytdlp_latest
+        );
+        if auto_update.unwrap_or(false) {
+            ytdlp_updated = updater.force_update().await?;
+            ytdlp_current = diagnose_ytdlp(&app, &state).await?.version;
+            behind = days_behind(&ytdlp_current);
+            ytdlp_outdated = is_outdated(behind);
+        }
+    }
 
-/// Application state shared across all commands
-struct AppState {
-    ytdlp_updater: Arc<Mutex<YtdlpUpdater>>,
-    active_downloads: Arc<Mutex<HashMap<String, DownloadHandle>>>,
-    binary_manager: Arc<BinaryManager>,
+    Ok(PreflightReport {
+        ytdlp_current,
+        ytdlp_latest,
+        days_behind: behind,
+        ytdlp_outdated,
+        ytdlp_updated,
+        ffmpeg_available: state.binary_manager.is_ffmpeg_available().await,
+    })
 }
 
-/// Detect the platform from a URL
+/// Report which audio encoders ffmpeg provides, so unsupported formats can be disabled
+/// ffmpeg runs on the blocking pool, so the command doesn't stall a runtime worker
 #[tauri::command]
-async fn detect_platform(url: String) -> Result<String, String> {
-    info!("Detecting platform for URL: {}", url);
+async fn check_ffmpeg_encoders(
+    state: tauri::State<'_, AppState>,
+) -> Result<FfmpegEncoders, String> {
+    let manager = state.binary_manager.clone();
+    tokio::task::spawn_blocking(move || manager.ffmpeg_encoders())
+        .await
+        .map_err(|e| format!("ffmpeg encoder check failed: {}", e))?
+}
 
-    if url.contains("youtube.com") || url.contains("youtu.be") {
-        Ok("youtube".to_string())
-    } else if url.contains("x.com") || url.contains("twitter.com") {
-        Ok("x".to_string())
-    } else if url.contains("facebook.com") || url.contains("fb.watch") {
-        Ok("facebook".to_string())
-    } else if url.contains("instagram.com") {
-        Ok("instagram".to_string())
-    } else if url.contains("tiktok.com") {
-        Ok("tiktok".to_string())
-    } else {
-        warn!("Unsupported platform: {}", url);
-        Err("Unsupported platform".to_string())
-    }
+/// Versions and download sources of the installed binaries, for support diagnostics
+#[tauri::command]
+async fn get_binary_versions(state: tauri::State<'_, AppState>) -> Result<Vec<BinaryInfo>, String> {
+    Ok(state.binary_manager.binary_versions())
 }
 
-/// Get video information using yt-dlp
+/// GitHub API quota from the last release check, to tell throttling from network trouble
+/// None until setup or the yt-dlp updater has queried the API this session
 #[tauri::command]
-async fn get_video_info(url: String, app: tauri::AppHandle) -> Result<String, String> {
-    info!("Fetching video info for: {}", url);
+async fn get_github_ratelimit() -> Result<Option<GithubRateLimit>, String> {
+    Ok(github_ratelimit())
+}
 
-    let output = app
-        .shell()
-        .sidecar("yt-dlp")
-        .map_err(|e| {
-            error!("Failed to create sidecar: {}", e);
-            e.to_string()
-        })?
-        .args(&["--no-playlist", "--dump-json", &url])
-        .output()
-        .await
-        .map_err(|e| {
-            error!("Failed to execute yt-dlp: {}", e);
-            e.to_string()
-        })?;
-
-    if output.status.success() {
-        let json_output = String::from_utf8_lossy(&output.stdout).to_string();
-        info!("Successfully fetched video info");
-        Ok(json_output)
-    } else {
-        let error_msg = String::from_utf8_lossy(&output.stderr).to_string();
-        error!("Failed to fetch video info: {}", error_msg);
-        Err(error_msg)
+/// Get the result of the startup binary check
+#[tauri::command]
+async fn get_setup_status(state: tauri::State<'_, AppState>) -> Result<SetupStatus, String> {
+    Ok(state.setup_status.lock().await.clone())
+}
+
+/// Re-run the binary setup after a failure
+#[tauri::command]
+async fn retry_setup(state: tauri::State<'_, AppState>) -> Result<SetupStatus, String> {
+    start_setup(&state).await?;
+
+    info!("Retrying binary setup");
+    let status =
+        SetupStatus::from_result(state.binary_manager.ensure_all_binaries_detailed().await);
+
+    if !status.ready {
+        warn!("Binary setup still failing: {:?}", status.failures);
     }
+
+    *state.setup_status.lock().await = status.clone();
+    Ok(status)
 }
 
-/// Download video with specified quality
-/// Uses smart retry: tries without cookies first, auto-retries with cookies if needed
+/// Mark setup as running, or fail if another setup run hasn't finished yet
+async fn start_setup(state: &AppState) -> Result<(), String> {
+    let mut status = lock_idle_setup(state).await?;
+    *status = SetupStatus::in_progress();
+    state.binary_manager.reset_cancel();
+    Ok(())
+}
+
+/// Lock the setup status, failing if a setup run hasn't finished yet
+/// Held while a new run is marked as started, so two runs can't both pass the check
+async fn lock_idle_setup(
+    state: &AppState,
+) -> Result<tokio::sync::MutexGuard<'_, SetupStatus>, String> {
+    let status = state.setup_status.lock().await;
+    if status.in_progress {
+        return Err("Setup is already running".to_string());
+    }
+    Ok(status)
+}
+
+/// Fail while downloads are running, since yt-dlp/ffmpeg processes hold the binaries open
+/// Counts downloads still fetching info and standalone info fetches too. Call it with
+/// the setup_status lock held, so no download can pass begin_download meanwhile.
+/// `action` completes "Cannot … while N download(s) are in progress"
+async fn ensure_no_downloads(state: &AppState, action: &str) -> Result<(), String> {
+    let mut running: HashSet<String> = state
+        .downloads_in_flight
+        .lock()
+        .map(|in_flight| in_flight.iter().cloned().collect())
+        .unwrap_or_default();
+    running.extend(state.active_downloads.lock().await.keys().cloned());
+    running.extend(state.info_fetches.lock().await.keys().cloned());
+
+    let active = running.len();
+    if active > 0 {
+        warn!("Cannot {} with {} downloads in progress", action, active);
+        return Err(format!(
+            "Cannot {} while {} download(s) are in progress. Cancel them and try again.",
+            action, active
+        ));
+    }
+    Ok(())
+}
+
+/// Stop the first-run binary download; the setup screen can then offer retry_setup
 #[tauri::command]
-async fn download_video(
-    url: String,
-    output_path: String,
-    quality: String,
-    _use_browser_cookies: Option<bool>, // Deprecated but kept for API compatibility
-    window: tauri::WebviewWindow,
-    app: tauri::AppHandle,
+async fn cancel_setup(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.binary_manager.cancel_setup();
+    Ok(())
+}
+
+/// Re-download a single binary that failed during setup
+#[tauri::command]
+async fn retry_binary_download(
+    name: String,
     state: tauri::State<'_, AppState>,
-) -> Result<String, String> {
-    info!("Video download requested: url={}, quality={}", url, quality);
+) -> Result<SetupStatus, String> {
+    if !REQUIRED_BINARIES.contains(&name.as_str()) {
+        warn!("Rejected retry for unknown binary: {}", name);
+        return Err(format!(
+            "Unknown binary '{}'. Expected one of: {}",
+            name,
+            REQUIRED_BINARIES.join(", ")
+        ));
+    }
 
-    // Use smart retry - no manual cookie configuration needed
-    download_content_with_smart_retry(
-        url,
-        output_path,
-        DownloadType::Video { quality },
-        window,
-        app,
-        state.ytdlp_updater.clone(),
-        state.active_downloads.clone(),
-        state.binary_manager.clone(),
-    )
-    .await
-    .map_err(|e| e.to_string())
+    // Marked as a setup run, so it can't overlap the startup check, retry_setup or
+    // reset_binaries writing the same files; earlier failures stay listed meanwhile
+    {
+        let mut status = lock_idle_setup(&state).await?;
+        ensure_no_downloads(&state, &format!("re-download {}", name)).await?;
+        status.in_progress = true;
+        state.binary_manager.reset_cancel();
+    }
+
+    info!("Retrying download of {}", name);
+    let result = state.binary_manager.download_binary(&name).await;
+    if let Err(e) = &result {
+        error!("Retry of {} failed: {}", name, e);
+    }
+
+    let retried = result.is_ok();
+    let setup_failed = {
+        let mut status = state.setup_status.lock().await;
+        status.record_retry(&name, result);
+        status
+            .failures
+            .iter()
+            .any(|failure| failure.name == "setup")
+    };
+
+    // A "setup" failure isn't tied to one binary, so only a full check passing clears it
+    if retried && setup_failed {
+        match state.binary_manager.ensure_all_binaries_detailed().await {
+            Ok(()) => state
+                .setup_status
+                .lock()
+                .await
+                .record_retry("setup", Ok(())),
+            Err(failures) => warn!("Setup check still failing after retry: {:?}", failures),
+        }
+    }
+
+    let mut status = state.setup_status.lock().await;
+    status.in_progress = false;
+    Ok(status.clone())
 }
 
-/// Download audio (MP3)
-/// Uses smart retry: tries without cookies first, auto-retries with cookies if needed
+/// Delete all downloaded binaries and download them again
+/// The support "fix it" button for a corrupt ffmpeg or binaries for the wrong architecture
 #[tauri::command]
-async fn download_audio(
-    url: String,
-    output_path: String,
-    _use_browser_cookies: Option<bool>, // Deprecated but kept for API compatibility
-    window: tauri::WebviewWindow,
-    app: tauri::AppHandle,
-    state: tauri::State<'_, AppState>,
-) -> Result<String, String> {
-    info!("Audio download requested: url={}", url);
+async fn reset_binaries(state: tauri::State<'_, AppState>) -> Result<SetupStatus, String> {
+    {
+        let mut status = lock_idle_setup(&state).await?;
+        ensure_no_downloads(&state, "reset tools").await?;
+        *status = SetupStatus::in_progress();
+        state.binary_manager.reset_cancel();
+    }
 
-    // Use smart retry - no manual cookie configuration needed
-    download_content_with_smart_retry(
-        url,
-        output_path,
-        DownloadType::Audio,
-        window,
-        app,
-        state.ytdlp_updater.clone(),
-        state.active_downloads.clone(),
-        state.binary_manager.clone(),
-    )
-    .await
-    .map_err(|e| e.to_string())
+    info!("Resetting binaries");
+    if let Err(e) = state.binary_manager.remove_all_binaries() {
+        *state.setup_status.lock().await = SetupStatus::default();
+        return Err(e);
+    }
+
+    let status =
+        SetupStatus::from_result(state.binary_manager.ensure_all_binaries_detailed().await);
+    if !status.ready {
+        warn!("Binary setup failing after reset: {:?}", status.failures);
+    }
+
+    *state.setup_status.lock().await = status.clone();
+    Ok(status)
 }
 
-/// Cancel an active download
+/// Update yt-dlp immediately, e.g. after a player/extraction error
 #[tauri::command]
-async fn cancel_download_command(
-    download_id: String,
-    window: tauri::WebviewWindow,
+async fn force_update_binaries(state: tauri::State<'_, AppState>) -> Result<String, String> {
+    info!("Forcing yt-dlp update");
+    let updater = state.ytdlp_updater.lock().await;
+    let updated = updater.force_update().await.map_err(|e| {
+        error!("Failed to update yt-dlp: {}", e);
+        e
+    })?;
+
+    let path = updater.get_ytdlp_path()?;
+    info!("yt-dlp ready at {:?} (updated: {})", path, updated);
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Get the current user settings
+#[tauri::command]
+async fn get_settings(state: tauri::State<'_, AppState>) -> Result<AppSettings, String> {
+    Ok(state.settings.get().await)
+}
+
+/// Validate and save user settings
+#[tauri::command]
+async fn update_settings(
+    settings: AppSettings,
     state: tauri::State<'_, AppState>,
 ) -> Result<(), String> {
-    info!("Cancel requested for download: {}", download_id);
-
-    cancel_download(download_id, state.active_downloads.clone(), window)
-        .await
-        .map_err(|e| e.to_string())
+    info!("Updating settings: {:?}", settings);
+    state.settings.update(settings).await.map_err(|e| {
+        error!("Failed to update settings: {}", e);
+        e
+    })
 }
 
 /// Create a directory
@@ -169,10 +1694,16 @@ fn create_directory(path: String) -> Result<(), String> {
 /// Open file location in the system file manager
 /// Gracefully handles missing files by opening parent directory instead
 #[tauri::command]
-fn open_file_location(path: String) -> Result<(), String> {
+async fn open_file_location(path: String, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let settings = state.settings.get().await;
+    reveal_file_location(path, &settings)
+}
+
+/// Reveal a path in the file manager if it is inside home or the library
+fn reveal_file_location(path: String, settings: &AppSettings) -> Result<(), String> {
     info!("Opening file location: {}", path);
 
-    // Basic security: ensure path is within user's home directory
+    // Basic security: ensure path is within the home directory or the library
     // But be more lenient to handle edge cases
     let path_buf = std::path::PathBuf::from(&path);
 
@@ -183,11 +1714,16 @@ fn open_file_location(path: String) -> Result<(), String> {
     }
 
     // Ensure path is within safe directories
-    if let Some(home) = dirs::home_dir() {
-        if !path.starts_with(home.to_string_lossy().as_ref()) {
-            warn!("Path outside home directory: {}", path);
-            return Err("Access denied: path outside allowed directories".to_string());
-        }
+    let allowed_roots: Vec<std::path::PathBuf> = dirs::home_dir()
+        .into_iter()
+        .chain(settings.library_root().ok())
+        .collect();
+    if allowed_roots.is_empty() {
+        return Err(NO_LIBRARY_ROOT_ERROR.to_string());
+    }
+    if !allowed_roots.iter().any(|root| path_buf.starts_with(root)) {
+        warn!("Path outside allowed directories: {}", path);
+        return Err("Access denied: path outside allowed directories".to_string());
     }
 
     // Try to open the exact file if it exists
@@ -297,6 +1833,34 @@ fn open_file_location(path: String) -> Result<(), String> {
     Err("Could not open file location".to_string())
 }
 
+/// Open the app data folder (logs, settings, binaries) in the system file manager
+#[tauri::command]
+fn open_app_data_folder(app: tauri::AppHandle) -> Result<(), String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?;
+
+    open_app_owned_folder(&app_data_dir)
+}
+
+/// Open the folder holding the downloaded yt-dlp/ffmpeg binaries
+#[tauri::command]
+fn open_binaries_folder(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    open_app_owned_folder(state.binary_manager.binaries_dir())
+}
+
+/// Open a directory owned by the app
+/// Skips the home-directory restriction of open_file_location, but the folder must exist
+fn open_app_owned_folder(path: &std::path::Path) -> Result<(), String> {
+    if !path.is_dir() {
+        warn!("App folder does not exist: {:?}", path);
+        return Err(format!("Folder not found: {}", path.display()));
+    }
+
+    open_folder_fallback(path.to_string_lossy().to_string())
+}
+
 /// Helper function to open just the folder
 /// Assumes path has already been validated by caller
 fn open_folder_fallback(path: String) -> Result<(), String> {
@@ -349,14 +1913,190 @@ fn open_folder_fallback(path: String) -> Result<(), String> {
     Ok(())
 }
 
+/// How recycle_file removed a file
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DeleteResult {
+    /// "trash" or "permanent"
+    method: &'static str,
+    /// Set when the trash was unavailable and the file was deleted permanently instead
+    warning: Option<String>,
+}
+
 /// Move a file to the recycle bin
+/// With `to_trash` false the file is deleted permanently. If the trash is unavailable
+/// (some Linux setups, network drives) this falls back to a permanent delete with a warning
+/// The always_permanent_delete setting overrides `to_trash`: every file is removed
+/// outright and can't be restored from the recycle bin
 #[tauri::command]
-fn recycle_file(path: String) -> Result<(), String> {
-    info!("Moving file to recycle bin: {}", path);
-    trash::delete(&path).map_err(|e| {
-        error!("Failed to recycle file {}: {}", path, e);
+async fn recycle_file(
+    path: String,
+    to_trash: Option<bool>,
+    state: tauri::State<'_, AppState>,
+) -> Result<DeleteResult, String> {
+    let settings = state.settings.get().await;
+    // Libraries outside home are only reachable through the configured folder
+    let library_root: Vec<std::path::PathBuf> = settings.library_root().into_iter().collect();
+
+    if settings.always_permanent_delete {
+        info!(
+            "Recycle bin disabled in settings, deleting permanently: {}",
+            path
+        );
+    } else if to_trash.unwrap_or(true) {
+        info!("Moving file to recycle bin: {}", path);
+        match trash::delete(&path) {
+            Ok(()) => {
+                info!("Deleted via trash: {}", path);
+                return Ok(DeleteResult {
+                    method: "trash",
+                    warning: None,
+                });
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to recycle file {}: {}. Falling back to permanent delete",
+                    path, e
+                );
+                delete_file_permanently(&path, &library_root)?;
+                return Ok(DeleteResult {
+                    method: "permanent",
+                    warning: Some(format!(
+                        "Recycle bin unavailable ({}). The file was deleted permanently.",
+                        e
+                    )),
+                });
+            }
+        }
+    }
+
+    delete_file_permanently(&path, &library_root)?;
+    Ok(DeleteResult {
+        method: "permanent",
+        warning: None,
+    })
+}
+
+/// Permanently delete a single file (never a directory)
+fn delete_file_permanently(path: &str, library_root: &[std::path::PathBuf]) -> Result<(), String> {
+    let path_buf = validate_path(path, false, library_root)?;
+    if !path_buf.is_file() {
+        return Err("Only files can be deleted".to_string());
+    }
+
+    fs::remove_file(&path_buf).map_err(|e| {
+        error!("Failed to delete file {}: {}", path, e);
         e.to_string()
+    })?;
+
+    info!("Deleted permanently: {}", path);
+    Ok(())
+}
+
+/// Files at least this large report "hash-progress" events while being hashed
+const HASH_PROGRESS_MIN_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Hex digest (sha256 by default, or md5) of a file in the library or an allowed folder
+/// For deduplicating a library or checking a download against a published hash
+#[tauri::command]
+async fn hash_file(
+    path: String,
+    algo: Option<HashAlgorithm>,
+    window: tauri::WebviewWindow,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    let settings = state.settings.get().await;
+    let allowed_directories = settings.accessible_dirs();
+
+    let path_buf = validate_path(&path, false, &allowed_directories)?;
+    if !path_buf.is_file() {
+        return Err("Only files can be hashed".to_string());
+    }
+
+    let algorithm = algo.unwrap_or_default();
+    info!("Hashing {} with {:?}", path, algorithm);
+    let total = fs::metadata(&path_buf).map(|m| m.len()).unwrap_or(0);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut last_percent = 0;
+        hash::hash_file(&path_buf, algorithm, |hashed| {
+            if total < HASH_PROGRESS_MIN_BYTES {
+                return;
+            }
+            let percent = (hashed * 100 / total).min(100);
+            if percent > last_percent {
+                last_percent = percent;
+                window
+                    .emit(
+                        "hash-progress",
+                        serde_json::json!({ "path": path, "percent": percent }),
+                    )
+                    .ok();
+            }
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("Failed to hash file: {}", e))
+}
+
+/// Rebuild the container of a file that won't play, e.g. after a crash during merging
+/// Streams are copied, not re-encoded, into a temp file that replaces the original
+/// only once ffmpeg succeeds. Emits "repair-progress" {path, percent}
+#[tauri::command]
+async fn repair_file(
+    path: String,
+    window: tauri::WebviewWindow,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    ensure_setup_finished(&state).await?;
+
+    let allowed_directories = state.settings.get().await.accessible_dirs();
+    let path_buf = validate_path(&path, false, &allowed_directories)?;
+    if !path_buf.is_file() {
+        return Err("Only files can be repaired".to_string());
+    }
+    if !state.binary_manager.is_ffmpeg_available().await {
+        return Err("ffmpeg is required to repair files".to_string());
+    }
+
+    // Same folder, so the swap is a rename; same extension, so ffmpeg picks the same container
+    let file_name = path_buf
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temp_path = path_buf.with_file_name(format!(".repairing-{}", file_name));
+
+    info!("Repairing {}", path);
+    let binary_manager = state.binary_manager.clone();
+    let input = path_buf.clone();
+    let output = temp_path.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let mut last_percent = -1;
+        binary_manager.remux(&input, &output, |percent| {
+            let percent = percent as i32;
+            if percent > last_percent {
+                last_percent = percent;
+                window
+                    .emit(
+                        "repair-progress",
+                        serde_json::json!({ "path": path, "percent": percent }),
+                    )
+                    .ok();
+            }
+        })
     })
+    .await
+    .map_err(|e| e.to_string())?
+    .and_then(|()| {
+        fs::rename(&temp_path, &path_buf).map_err(|e| format!("Failed to replace file: {}", e))
+    });
+
+    if let Err(e) = &result {
+        warn!("Repair of {:?} failed: {}", path_buf, e);
+        fs::remove_file(&temp_path).ok();
+    }
+    result
 }
 
 /// Check if a file exists at the given path
@@ -367,75 +2107,225 @@ fn file_exists(path: String) -> Result<bool, String> {
 }
 
 /// Scan downloads folders and return list of actual files
+/// `max_files` caps the work for very large libraries; with `stream` set, entries are
+/// emitted as "library-entry" events as they are found and the returned list is empty
 #[tauri::command]
-async fn scan_downloads_folder() -> Result<Vec<serde_json::Value>, String> {
-    use serde_json::json;
+async fn scan_downloads_folder(
+    max_files: Option<usize>,
+    stream: Option<bool>,
+    window: tauri::WebviewWindow,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<serde_json::Value>, String> {
+    let settings = state.settings.get().await;
+    let ripvid_base = settings.library_root()?;
 
-    let home = dirs::home_dir().ok_or("Could not determine home directory")?;
-    let ripvid_base = home.join("Videos").join("ripVID");
+    let limit = max_files.unwrap_or(usize::MAX);
+    let stream_to = if stream.unwrap_or(false) {
+        Some(&window)
+    } else {
+        None
+    };
 
     let mut files = Vec::new();
+    let mut scanned = 0;
+    let mut truncated = false;
 
-    // Scan MP4 folder
-    let mp4_dir = ripvid_base.join("MP4");
-    if mp4_dir.exists() {
-        if let Ok(entries) = fs::read_dir(&mp4_dir) {
-            for entry in entries.flatten() {
-                if let Ok(metadata) = entry.metadata() {
-                    if metadata.is_file() {
-                        let path = entry.path();
-                        let filename = path
-                            .file_name()
-                            .and_then(|n| n.to_str())
-                            .unwrap_or("unknown");
-
-                        files.push(json!({
-                            "path": path.to_string_lossy().to_string(),
-                            "filename": filename,
-                            "format": "mp4",
-                            "size": metadata.len(),
-                            "modified": metadata.modified()
-                                .ok()
-                                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                                .map(|d| d.as_secs())
-                        }));
-                    }
-                }
+    // Scan MP4 and MP3 folders
+    for (folder, format) in [("MP4", "mp4"), ("MP3", "mp3")] {
+        truncated |= scan_library_folder(
+            &ripvid_base.join(folder),
+            format,
+            &settings,
+            limit,
+            stream_to,
+            &mut files,
+            &mut scanned,
+        )
+        .await;
+    }
+
+    if truncated {
+        warn!("Library scan stopped at the {} file cap", limit);
+    }
+
+    if let Some(window) = stream_to {
+        window
+            .emit(
+                "library-scan-complete",
+                serde_json::json!({
+                    "count": scanned,
+                    "truncated": truncated
+                }),
+            )
+            .ok();
+    }
+
+    info!("Scanned downloads folder, found {} files", scanned);
+    Ok(files)
+}
+
+/// Scan a single library folder, stopping once `limit` files have been found in total
+/// Returns true if files were left unscanned because of the limit
+async fn scan_library_folder(
+    dir: &std::path::Path,
+    format: &str,
+    settings: &AppSettings,
+    limit: usize,
+    stream_to: Option<&tauri::WebviewWindow>,
+    files: &mut Vec<serde_json::Value>,
+    scanned: &mut usize,
+) -> bool {
+    use serde_json::json;
+
+    walk_library_folder(dir, limit, scanned, |path, metadata| {
+        // Kept streams and written thumbnails belong to the download next to them
+        if is_sidecar_file(path) || !settings.is_library_file(path) {
+            return;
+        }
+
+        let filename = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown");
+
+        let file = json!({
+            "path": path.to_string_lossy().to_string(),
+            "filename": filename,
+            "format": format,
+            "size": metadata.len(),
+            "modified": metadata.modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs()),
+            // Written by write_thumbnail, so the library can show posters without probing
+            "thumbnailPath": find_thumbnail(path).map(|p| p.to_string_lossy().to_string())
+        });
+
+        match stream_to {
+            Some(window) => {
+                window.emit("library-entry", &file).ok();
             }
+            None => files.push(file),
         }
-    }
+    })
+    .await
+}
 
-    // Scan MP3 folder
-    let mp3_dir = ripvid_base.join("MP3");
-    if mp3_dir.exists() {
-        if let Ok(entries) = fs::read_dir(&mp3_dir) {
-            for entry in entries.flatten() {
-                if let Ok(metadata) = entry.metadata() {
-                    if metadata.is_file() {
-                        let path = entry.path();
-                        let filename = path
-                            .file_name()
-                            .and_then(|n| n.to_str())
-                            .unwrap_or("unknown");
-
-                        files.push(json!({
-                            "path": path.to_string_lossy().to_string(),
-                            "filename": filename,
-                            "format": "mp3",
-                            "size": metadata.len(),
-                            "modified": metadata.modified()
-                                .ok()
-                                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                                .map(|d| d.as_secs())
-                        }));
-                    }
+/// Visit every file in a library folder and its subfolders, such as the YYYY/YYYY-MM
+/// folders of organize_by_date, stopping once `limit` files have been seen in total
+/// Symlinked folders and ripVID's per-download temp folders are not entered.
+/// Returns true if the limit left a file unvisited
+async fn walk_library_folder(
+    dir: &std::path::Path,
+    limit: usize,
+    scanned: &mut usize,
+    mut visit: impl FnMut(&std::path::Path, &std::fs::Metadata),
+) -> bool {
+    let temp_root = download_temp_root();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        loop {
+            let entry = match entries.next_entry().await {
+                Ok(Some(entry)) => entry,
+                Ok(None) => break,
+                // The iterator doesn't advance past a failed read, so retrying could spin
+                Err(e) => {
+                    warn!("Failed to read entry in {:?}: {}", dir, e);
+                    break;
+                }
+            };
+
+            // Not followed through symlinks, so a link can't loop the walk
+            let metadata = match entry.metadata().await {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            let path = entry.path();
+            if metadata.is_dir() {
+                if !path.starts_with(&temp_root) {
+                    pending.push(path);
                 }
+                continue;
+            }
+            if !metadata.is_file() {
+                continue;
+            }
+
+            if *scanned >= limit {
+                return true;
             }
+            *scanned += 1;
+            visit(&path, &metadata);
         }
     }
 
-    info!("Scanned downloads folder, found {} files", files.len());
-    Ok(files)
+    false
+}
+
+/// Count and size of library files sharing one extension
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FormatStats {
+    count: u64,
+    bytes: u64,
+}
+
+/// Aggregate library size for the storage summary
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LibraryStats {
+    total_files: u64,
+    total_bytes: u64,
+    /// Keyed by lowercase file extension (mp4, mp3, mkv, ...)
+    formats: HashMap<String, FormatStats>,
+}
+
+/// Get total file count and size of the library, broken down by format
+#[tauri::command]
+async fn get_library_stats(state: tauri::State<'_, AppState>) -> Result<LibraryStats, String> {
+    let settings = state.settings.get().await;
+    let ripvid_base = settings.library_root()?;
+
+    let mut stats = LibraryStats::default();
+    let mut scanned = 0;
+
+    for (folder, format) in [("MP4", "mp4"), ("MP3", "mp3")] {
+        walk_library_folder(
+            &ripvid_base.join(folder),
+            usize::MAX,
+            &mut scanned,
+            |path, metadata| {
+                if !settings.is_library_file(path) {
+                    return;
+                }
+                let extension = path
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .map(|e| e.to_lowercase())
+                    .unwrap_or_else(|| format.to_string());
+
+                let format_stats = stats.formats.entry(extension).or_default();
+                format_stats.count += 1;
+                format_stats.bytes += metadata.len();
+
+                stats.total_files += 1;
+                stats.total_bytes += metadata.len();
+            },
+        )
+        .await;
+    }
+
+    info!(
+        "Library stats: {} files, {} bytes",
+        stats.total_files, stats.total_bytes
+    );
+    Ok(stats)
 }
 
 fn main() {
@@ -461,20 +2351,6 @@ fn main() {
             info!("Initializing binary manager...");
             let binary_manager = Arc::new(BinaryManager::new(app.handle().clone()));
 
-            // Ensure all binaries are downloaded/updated (blocks window until ready)
-            info!("Ensuring all binaries are ready...");
-            let manager_clone = binary_manager.clone();
-            tauri::async_runtime::block_on(async move {
-                match manager_clone.ensure_all_binaries().await {
-                    Ok(()) => info!("All binaries ready"),
-                    Err(e) => {
-                        error!("Failed to ensure binaries: {}", e);
-                        return Err(e);
-                    }
-                }
-                Ok::<(), String>(())
-            })?;
-
             // Initialize yt-dlp updater (legacy - will be replaced by binary manager)
             let updater = YtdlpUpdater::new(app.handle().clone());
 
@@ -487,28 +2363,154 @@ fn main() {
                 }
             });
 
+            // Not ready until the background binary check below finishes
+            let setup_status = Arc::new(Mutex::new(SetupStatus::in_progress()));
+
             // Initialize app state
             app.manage(AppState {
                 ytdlp_updater: Arc::new(Mutex::new(updater)),
                 active_downloads: Arc::new(Mutex::new(HashMap::new())),
                 binary_manager: binary_manager.clone(),
+                download_queue: Arc::new(DownloadQueue::new(MAX_CONCURRENT_DOWNLOADS)),
+                settings: Arc::new(SettingsManager::new(app.handle())),
+                metadata_cache: Arc::new(Mutex::new(HashMap::new())),
+                setup_status: setup_status.clone(),
+                info_fetches: Arc::new(Mutex::new(HashMap::new())),
+                history: Arc::new(HistoryManager::new(app.handle())),
+                batches: Arc::new(Mutex::new(HashMap::new())),
+                downloads_in_flight: Arc::default(),
+            });
+
+            // Ensure all binaries are downloaded/updated in the background so the
+            // setup screen stays responsive and cancel_setup can reach the manager
+            // Failures are kept for the setup screen instead of aborting startup
+            info!("Ensuring all binaries are ready...");
+            let app_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let setup_result = binary_manager.ensure_all_binaries_detailed().await;
+                match &setup_result {
+                    Ok(()) => info!("All binaries ready"),
+                    Err(failures) => error!("Failed to ensure binaries: {:?}", failures),
+                }
+
+                let status = SetupStatus::from_result(setup_result);
+                *setup_status.lock().await = status.clone();
+                app_handle.emit("setup-status", status).ok();
             });
 
             info!("Application setup complete");
             Ok(())
         })
+        .on_window_event(|window, event| {
+            // Ask before closing with downloads in flight, then stop them so no
+            // orphaned yt-dlp processes keep writing partial files
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                api.prevent_close();
+
+                let window = window.clone();
+                tauri::async_runtime::spawn(async move {
+                    let (active_downloads, download_queue) = {
+                        let state = window.state::<AppState>();
+                        (state.active_downloads.clone(), state.download_queue.clone())
+                    };
+
+                    let in_flight =
+                        active_downloads.lock().await.len() + download_queue.pending_count().await;
+
+                    if in_flight == 0 {
+                        window.destroy().ok();
+                        return;
+                    }
+
+                    info!("Close requested with {} downloads in flight", in_flight);
+                    let dialog_window = window.clone();
+                    dialog_window
+                        .dialog()
+                        .message(format!(
+                            "{} download(s) still in progress. Quitting will cancel them.",
+                            in_flight
+                        ))
+                        .title("Downloads in progress")
+                        .kind(MessageDialogKind::Warning)
+                        .buttons(MessageDialogButtons::OkCancelCustom(
+                            "Quit".to_string(),
+                            "Keep downloading".to_string(),
+                        ))
+                        .show(move |confirmed| {
+                            if !confirmed {
+                                info!("Close cancelled, downloads continue");
+                                return;
+                            }
+
+                            tauri::async_runtime::spawn(async move {
+                                cancel_all_downloads(active_downloads, download_queue).await;
+                                window.destroy().ok();
+                            });
+                        });
+                });
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             detect_platform,
+            classify_url,
+            get_supported_platforms,
+            validate_urls,
             get_video_info,
+            cancel_video_info,
+            get_playlist_entries,
+            get_video_metadata,
+            get_available_qualities,
+            list_subtitles,
+            preview_filename,
             download_video,
             download_audio,
+            download_to_path,
+            download_batch,
+            get_batch_status,
+            get_active_downloads,
+            test_download,
+            download_metadata_only,
             cancel_download_command,
+            retry_download,
+            get_download_history,
+            diagnose,
+            check_ffmpeg_encoders,
+            get_binary_versions,
+            get_github_ratelimit,
+            preflight_check,
+            get_setup_status,
+            retry_setup,
+            cancel_setup,
+            retry_binary_download,
+            reset_binaries,
+            force_update_binaries,
+            get_settings,
+            update_settings,
             create_directory,
             open_file_location,
+            open_app_data_folder,
+            open_binaries_folder,
             recycle_file,
             file_exists,
-            scan_downloads_folder
+            hash_file,
+            repair_file,
+            scan_downloads_folder,
+            get_library_stats
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Safety net for exits that bypass the close prompt (e.g. OS logout)
+            if let tauri::RunEvent::Exit = event {
+                if let Some(state) = app.try_state::<AppState>() {
+                    let stopped = tauri::async_runtime::block_on(cancel_all_downloads(
+                        state.active_downloads.clone(),
+                        state.download_queue.clone(),
+                    ));
+                    if stopped > 0 {
+                        warn!("Stopped {} downloads during shutdown", stopped);
+                    }
+                }
+            }
+        });
 }
@@ -0,0 +1,728 @@
+use crate::errors::{classify_ytdlp_error, stderr_tail, DownloadError};
+use crate::url_classifier::detect_platform;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::AppHandle;
+use tauri_plugin_shell::process::{Command, CommandChild, CommandEvent};
+use tauri_plugin_shell::ShellExt;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// Default time allowed for yt-dlp to return video info
+pub const INFO_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Structured subset of yt-dlp's --dump-json output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoMetadata {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub duration: Option<f64>,
+    #[serde(default)]
+    pub uploader: Option<String>,
+    #[serde(default)]
+    pub thumbnail: Option<String>,
+    /// Upload date as YYYYMMDD
+    #[serde(default)]
+    pub upload_date: Option<String>,
+    #[serde(default)]
+    pub formats: Vec<FormatInfo>,
+    /// Subtitles uploaded with the video, by language code
+    /// Only used for list_subtitles, so they're kept out of get_video_metadata's result
+    #[serde(default, skip_serializing)]
+    pub subtitles: HashMap<String, Vec<SubtitleFormat>>,
+    /// Captions generated by the site's speech recognition, by language code
+    #[serde(default, skip_serializing)]
+    pub automatic_captions: HashMap<String, Vec<SubtitleFormat>>,
+}
+
+/// One file format a subtitle track is offered in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubtitleFormat {
+    #[serde(default)]
+    pub ext: Option<String>,
+    /// Display name of the language, e.g. "English (United States)"
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// Whether a subtitle track was uploaded or generated automatically
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubtitleKind {
+    /// Uploaded by the creator; fetched with manual_sub_langs
+    Manual,
+    /// Speech-recognition captions, usually lower quality; fetched with auto_sub_langs
+    Auto,
+}
+
+/// A subtitle language a video offers, for the subtitle picker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubtitleTrack {
+    pub lang: String,
+    pub name: Option<String>,
+    pub kind: SubtitleKind,
+    /// File formats available, e.g. ["vtt", "srt"]
+    pub formats: Vec<String>,
+}
+
+/// A single format yt-dlp can download
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormatInfo {
+    pub format_id: String,
+    #[serde(default)]
+    pub ext: Option<String>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub vcodec: Option<String>,
+    #[serde(default)]
+    pub acodec: Option<String>,
+    /// Audio language code (e.g. "en"), present for multi-language uploads
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub filesize: Option<u64>,
+    #[serde(default)]
+    pub abr: Option<f64>,
+}
+
+impl FormatInfo {
+    /// yt-dlp reports a missing stream as "none"
+    pub fn has_video(&self) -> bool {
+        matches!(&self.vcodec, Some(codec) if codec != "none")
+    }
+
+    pub fn has_audio(&self) -> bool {
+        matches!(&self.acodec, Some(codec) if codec != "none")
+    }
+}
+
+/// Lightweight playlist listing from yt-dlp's --flat-playlist -J output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistInfo {
+    pub id: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub entries: Vec<PlaylistEntry>,
+}
+
+/// One playlist item; full metadata is fetched separately once the user picks it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistEntry {
+    pub id: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub duration: Option<f64>,
+}
+
+/// Where the sound of an audio download comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioSource {
+    /// A dedicated audio stream, picked by bestaudio
+    AudioOnly,
+    /// Only streams with video, so the audio is extracted from one (slower)
+    Muxed,
+    /// Every format reports that it has no audio
+    NoAudio,
+    /// The extractor doesn't report codecs
+    Unknown,
+}
+
+/// Qualities a URL actually offers, for the quality dropdown
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AvailableQualities {
+    /// "best" followed by the real heights, highest first (e.g. "1080p")
+    pub qualities: Vec<String>,
+    pub audio_only: bool,
+}
+
+impl VideoMetadata {
+    /// Whether there is both a video-only and an audio-only stream to save separately
+    pub fn has_separate_streams(&self) -> bool {
+        let video_only = self
+            .formats
+            .iter()
+            .any(|format| format.has_video() && !format.has_audio());
+        let audio_only = self
+            .formats
+            .iter()
+            .any(|format| format.has_audio() && !format.has_video());
+        video_only && audio_only
+    }
+
+    /// Whether the source has no video at all, like an X Space
+    pub fn is_audio_only(&self) -> bool {
+        !self.formats.is_empty() && !self.formats.iter().any(|format| format.has_video())
+    }
+
+    /// Which stream `bestaudio/best` will take the audio from
+    pub fn audio_source(&self) -> AudioSource {
+        if self
+            .formats
+            .iter()
+            .any(|format| format.has_audio() && !format.has_video())
+        {
+            AudioSource::AudioOnly
+        } else if self.formats.iter().any(FormatInfo::has_audio) {
+            AudioSource::Muxed
+        } else if !self.formats.is_empty()
+            && self
+                .formats
+                .iter()
+                .all(|format| format.acodec.as_deref() == Some("none"))
+        {
+            AudioSource::NoAudio
+        } else {
+            AudioSource::Unknown
+        }
+    }
+
+    /// Derive the distinct video heights and whether an audio-only stream exists
+    pub fn available_qualities(&self) -> AvailableQualities {
+        let mut heights: Vec<u32> = self
+            .formats
+            .iter()
+            .filter(|format| format.has_video())
+            .filter_map(|format| format.height)
+            .collect();
+        heights.sort_unstable_by(|a, b| b.cmp(a));
+        heights.dedup();
+
+        let mut qualities = vec!["best".to_string()];
+        qualities.extend(heights.iter().map(|height| format!("{}p", height)));
+
+        AvailableQualities {
+            qualities,
+            audio_only: self
+                .formats
+                .iter()
+                .any(|format| format.has_audio() && !format.has_video()),
+        }
+    }
+
+    /// Uploaded subtitle tracks followed by auto-generated ones, each sorted by language
+    /// YouTube's live chat replay is listed as a subtitle but isn't one, so it's left out
+    pub fn subtitle_tracks(&self) -> Vec<SubtitleTrack> {
+        let tracks = |subtitles: &HashMap<String, Vec<SubtitleFormat>>, kind: SubtitleKind| {
+            let mut tracks: Vec<SubtitleTrack> = subtitles
+                .iter()
+                .filter(|(lang, formats)| lang.as_str() != "live_chat" && !formats.is_empty())
+                .map(|(lang, formats)| SubtitleTrack {
+                    lang: lang.clone(),
+                    name: formats.iter().find_map(|format| format.name.clone()),
+                    kind,
+                    formats: formats
+                        .iter()
+                        .filter_map(|format| format.ext.clone())
+                        .collect(),
+                })
+                .collect();
+            tracks.sort_by(|a, b| a.lang.cmp(&b.lang));
+            tracks
+        };
+
+        let mut all = tracks(&self.subtitles, SubtitleKind::Manual);
+        all.extend(tracks(&self.automatic_captions, SubtitleKind::Auto));
+        all
+    }
+
+    /// Requested subtitle languages narrowed to the tracks this video offers
+    /// yt-dlp takes auto captions for any language in --sub-langs without an uploaded
+    /// track, so uploaded languages are kept only where an uploaded track exists and
+    /// auto-generated ones only where none does. Patterns like "en.*" are expanded
+    pub fn resolve_sub_langs(
+        &self,
+        manual: &[String],
+        auto: &[String],
+    ) -> (Vec<String>, Vec<String>) {
+        let matching = |patterns: &[String], tracks: &HashMap<String, Vec<SubtitleFormat>>| {
+            let mut langs: Vec<String> = tracks
+                .keys()
+                .filter(|lang| lang.as_str() != "live_chat")
+                .filter(|lang| {
+                    patterns
+                        .iter()
+                        .any(|pattern| sub_lang_matches(pattern, lang))
+                })
+                .cloned()
+                .collect();
+            langs.sort();
+            langs
+        };
+
+        let auto = matching(auto, &self.automatic_captions)
+            .into_iter()
+            .filter(|lang| !self.subtitles.contains_key(lang))
+            .collect();
+        (matching(manual, &self.subtitles), auto)
+    }
+
+    /// Expand `%(field)s` placeholders the way yt-dlp's -o template would
+    /// Supports title, uploader, upload_date, id and ext; other fields, and fields
+    /// the extractor didn't report, become "NA" like they do in yt-dlp. Field values
+    /// are cleaned with yt-dlp's substitutions, see sanitize_field
+    pub fn render_filename_template(&self, template: &str, ext: &str) -> String {
+        let mut rendered = String::with_capacity(template.len());
+        let mut rest = template;
+
+        while let Some(start) = rest.find("%(") {
+            rendered.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let end = match after.find(")s") {
+                Some(end) => end,
+                None => {
+                    rest = &rest[start..];
+                    break;
+                }
+            };
+
+            let value = match &after[..end] {
+                "title" => Some(self.title.as_str()),
+                "id" => Some(self.id.as_str()),
+                "ext" => Some(ext),
+                "uploader" => self.uploader.as_deref(),
+                "upload_date" => self.upload_date.as_deref(),
+                _ => None,
+            };
+            match value {
+                Some(value) => rendered.push_str(&sanitize_field(value)),
+                None => rendered.push_str("NA"),
+            }
+            rest = &after[end + 2..];
+        }
+
+        rendered.push_str(rest);
+        rendered
+    }
+}
+
+/// Clean a template field like yt-dlp's default sanitize_filename
+/// `"*:<>?|/\` become full-width look-alikes (＂ ＊ ： ＜ ＞ ？ ｜ ⧸ ⧹), except that a
+/// colon between digits is a timestamp and becomes "_". Line breaks become single
+/// spaces and are dropped at either end; other control characters are removed
+fn sanitize_field(value: &str) -> String {
+    if value.is_empty() {
+        return String::new();
+    }
+
+    let chars: Vec<char> = value.chars().collect();
+    let mut cleaned: Vec<char> = Vec::with_capacity(chars.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let timestamp = c == ':'
+            && i > 0
+            && chars[i - 1].is_ascii_digit()
+            && chars.get(i + 1).map_or(false, |next| next.is_ascii_digit());
+        match c {
+            ':' if timestamp => cleaned.push('_'),
+            // Consecutive line breaks collapse into one
+            '\n' if cleaned.last() == Some(&'\n') => {}
+            '\n' => cleaned.push('\n'),
+            '/' => cleaned.push('\u{29F8}'),
+            '\\' => cleaned.push('\u{29F9}'),
+            '"' | '*' | ':' | '<' | '>' | '?' | '|' => {
+                cleaned.push(char::from_u32(c as u32 + 0xFEE0).unwrap_or('_'))
+            }
+            c if c.is_ascii_control() => {}
+            c => cleaned.push(c),
+        }
+    }
+
+    // A line break at either end is dropped with the spaces, dashes and underscores next to it
+    let strippable = |c: &char| matches!(c, '\n' | ' ' | '_' | '-');
+    if cleaned.first() == Some(&'\n') {
+        let start = cleaned
+            .iter()
+            .position(|c| !strippable(c))
+            .unwrap_or(cleaned.len());
+        cleaned.drain(..start);
+    }
+    if cleaned.last() == Some(&'\n') {
+        let end = cleaned
+            .iter()
+            .rposition(|c| !strippable(c))
+            .map_or(0, |i| i + 1);
+        cleaned.truncate(end);
+    }
+
+    let cleaned: String = cleaned
+        .into_iter()
+        .map(|c| if c == '\n' { ' ' } else { c })
+        .collect();
+    if cleaned.is_empty() {
+        "_".to_string()
+    } else {
+        cleaned
+    }
+}
+
+/// Whether a --sub-langs entry selects a language: "all", a "prefix.*" pattern or an exact code
+fn sub_lang_matches(pattern: &str, lang: &str) -> bool {
+    match pattern.strip_suffix(".*") {
+        Some(prefix) => lang.starts_with(prefix),
+        None => pattern == "all" || pattern == lang,
+    }
+}
+
+/// Run yt-dlp --dump-json for a URL
+/// The child is stored under `request_id` while running so cancel_info_fetch can kill it;
+/// on timeout the process is killed and a Network error is returned
+pub async fn fetch_video_info(
+    app: &AppHandle,
+    url: &str,
+    request_id: &str,
+    info_fetches: Arc<Mutex<HashMap<String, CommandChild>>>,
+    timeout: Duration,
+) -> Result<String, DownloadError> {
+    run_info_fetch(
+        app,
+        url,
+        &["--no-playlist", "--dump-json"],
+        request_id,
+        info_fetches,
+        timeout,
+    )
+    .await
+}
+
+/// Run yt-dlp --flat-playlist -J for a URL
+/// Lists entries without resolving each video, so large playlists return quickly
+pub async fn fetch_playlist_entries(
+    app: &AppHandle,
+    url: &str,
+    request_id: &str,
+    info_fetches: Arc<Mutex<HashMap<String, CommandChild>>>,
+    timeout: Duration,
+) -> Result<PlaylistInfo, DownloadError> {
+    let json = run_info_fetch(
+        app,
+        url,
+        &["--flat-playlist", "-J"],
+        request_id,
+        info_fetches,
+        timeout,
+    )
+    .await?;
+    parse_playlist_info(&json)
+}
+
+/// Run yt-dlp with the given arguments and the URL, and collect its stdout
+/// Shared by the info fetches so they can all be cancelled, time out and report
+/// errors the same way
+async fn run_info_fetch(
+    app: &AppHandle,
+    url: &str,
+    args: &[&str],
+    request_id: &str,
+    info_fetches: Arc<Mutex<HashMap<String, CommandChild>>>,
+    timeout: Duration,
+) -> Result<String, DownloadError> {
+    let command = app
+        .shell()
+        .sidecar("yt-dlp")
+        .map_err(|e| DownloadError::Sidecar(e.to_string()))?
+        .args(args)
+        .arg(url);
+
+    run_registered(command, url, request_id, info_fetches, timeout).await
+}
+
+/// Run a prepared yt-dlp command and collect its stdout
+/// The child is stored under `request_id` in `info_fetches` while it runs, so
+/// cancel_video_info can kill it, and it is killed once `timeout` passes.
+/// `url` is only used to classify a failure
+pub async fn run_registered(
+    command: Command,
+    url: &str,
+    request_id: &str,
+    info_fetches: Arc<Mutex<HashMap<String, CommandChild>>>,
+    timeout: Duration,
+) -> Result<String, DownloadError> {
+    // Held until the child is stored, so two fetches can't claim the same id
+    let mut fetches = info_fetches.lock().await;
+    if fetches.contains_key(request_id) {
+        return Err(DownloadError::InvalidInput(format!(
+            "A video info fetch with id {} is already running",
+            request_id
+        )));
+    }
+
+    let (mut rx, child) = command
+        .spawn()
+        .map_err(|e| DownloadError::ProcessFailed(e.to_string()))?;
+
+    fetches.insert(request_id.to_string(), child);
+    drop(fetches);
+
+    let collect_output = async {
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let mut code = None;
+
+        while let Some(event) = rx.recv().await {
+            match event {
+                CommandEvent::Stdout(line) => {
+                    stdout.extend(line);
+                    stdout.push(b'\n');
+                }
+                CommandEvent::Stderr(line) => {
+                    stderr.extend(line);
+                    stderr.push(b'\n');
+                }
+                CommandEvent::Terminated(payload) => code = payload.code,
+                _ => {}
+            }
+        }
+
+        (stdout, stderr, code)
+    };
+
+    let outcome = tokio::time::timeout(timeout, collect_output).await;
+
+    // A missing child means cancel_info_fetch already took and killed it
+    let child = info_fetches.lock().await.remove(request_id);
+
+    let (stdout, stderr, code) = match outcome {
+        Ok(output) => output,
+        Err(_) => {
+            warn!(
+                "Video info fetch {} timed out after {:?}",
+                request_id, timeout
+            );
+            if let Some(child) = child {
+                child.kill().ok();
+            }
+            return Err(DownloadError::Network(format!(
+                "Timed out after {}s. The site is slow to respond, please retry.",
+                timeout.as_secs()
+            )));
+        }
+    };
+
+    if child.is_none() {
+        info!("Video info fetch {} was cancelled", request_id);
+        return Err(DownloadError::Cancelled);
+    }
+
+    if code == Some(0) {
+        Ok(String::from_utf8_lossy(&stdout).to_string())
+    } else {
+        // Same classification as downloads, so a private video is reported before downloading
+        let stderr = String::from_utf8_lossy(&stderr);
+        Err(classify_ytdlp_error(&stderr, detect_platform(url))
+            .unwrap_or_else(|| DownloadError::ProcessFailed(stderr_tail(&stderr, 3, 300))))
+    }
+}
+
+/// Parse yt-dlp's --dump-json output into structured metadata
+pub fn parse_video_metadata(json: &str) -> Result<VideoMetadata, DownloadError> {
+    serde_json::from_str(json)
+        .map_err(|e| DownloadError::ParseError(format!("Invalid video metadata: {}", e)))
+}
+
+/// Parse yt-dlp's --flat-playlist -J output into playlist entries
+pub fn parse_playlist_info(json: &str) -> Result<PlaylistInfo, DownloadError> {
+    serde_json::from_str(json)
+        .map_err(|e| DownloadError::ParseError(format!("Invalid playlist info: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_video_metadata_formats() {
+        let json = r#"{
+            "id": "abc123",
+            "title": "Podcast episode",
+            "duration": 3600.0,
+            "formats": [
+                {"format_id": "251", "ext": "webm", "vcodec": "none", "acodec": "opus", "language": "en"},
+                {"format_id": "251-1", "ext": "webm", "vcodec": "none", "acodec": "opus", "language": "es"},
+                {"format_id": "137", "ext": "mp4", "height": 1080, "vcodec": "avc1.640028", "acodec": "none"}
+            ]
+        }"#;
+
+        let metadata = parse_video_metadata(json).unwrap();
+        assert_eq!(metadata.formats.len(), 3);
+        assert_eq!(metadata.formats[1].language.as_deref(), Some("es"));
+        assert_eq!(metadata.formats[2].height, Some(1080));
+        assert!(metadata.has_separate_streams());
+        assert!(!metadata.is_audio_only());
+    }
+
+    #[test]
+    fn test_audio_only_space() {
+        let json = r#"{
+            "id": "1YqKDqWqdPLGV",
+            "title": "Weekly Space",
+            "formats": [
+                {"format_id": "hls", "ext": "m4a", "vcodec": "none", "acodec": "mp4a.40.2"}
+            ]
+        }"#;
+
+        let metadata = parse_video_metadata(json).unwrap();
+        assert!(metadata.is_audio_only());
+        assert_eq!(metadata.thumbnail, None);
+        assert_eq!(metadata.audio_source(), AudioSource::AudioOnly);
+    }
+
+    #[test]
+    fn test_audio_source_fallbacks() {
+        let metadata = |formats: &str| {
+            parse_video_metadata(&format!(
+                r#"{{"id": "abc", "title": "Clip", "formats": {}}}"#,
+                formats
+            ))
+            .unwrap()
+        };
+
+        let muxed = metadata(r#"[{"format_id": "18", "vcodec": "avc1", "acodec": "mp4a.40.2"}]"#);
+        assert_eq!(muxed.audio_source(), AudioSource::Muxed);
+
+        let silent = metadata(r#"[{"format_id": "gif", "vcodec": "avc1", "acodec": "none"}]"#);
+        assert_eq!(silent.audio_source(), AudioSource::NoAudio);
+
+        // Codecs not reported, so nothing can be concluded
+        let generic = metadata(r#"[{"format_id": "0"}]"#);
+        assert_eq!(generic.audio_source(), AudioSource::Unknown);
+    }
+
+    #[test]
+    fn test_parse_playlist_info() {
+        let json = r#"{
+            "id": "PL1234",
+            "title": "Mixtape",
+            "_type": "playlist",
+            "entries": [
+                {"id": "abc", "title": "First", "url": "https://www.youtube.com/watch?v=abc", "duration": 215.0},
+                {"id": "def", "title": null, "url": "https://www.youtube.com/watch?v=def"}
+            ]
+        }"#;
+
+        let playlist = parse_playlist_info(json).unwrap();
+        assert_eq!(playlist.title.as_deref(), Some("Mixtape"));
+        assert_eq!(playlist.entries.len(), 2);
+        assert_eq!(playlist.entries[0].duration, Some(215.0));
+        assert_eq!(playlist.entries[1].title, None);
+    }
+
+    #[test]
+    fn test_available_qualities() {
+        let json = r#"{
+            "id": "abc123",
+            "title": "Clip",
+            "formats": [
+                {"format_id": "140", "vcodec": "none", "acodec": "mp4a.40.2"},
+                {"format_id": "134", "height": 360, "vcodec": "avc1", "acodec": "none"},
+                {"format_id": "136", "height": 720, "vcodec": "avc1", "acodec": "none"},
+                {"format_id": "247", "height": 720, "vcodec": "vp9", "acodec": "none"}
+            ]
+        }"#;
+
+        let qualities = parse_video_metadata(json).unwrap().available_qualities();
+        assert_eq!(qualities.qualities, vec!["best", "720p", "360p"]);
+        assert!(qualities.audio_only);
+    }
+
+    #[test]
+    fn test_subtitle_tracks() {
+        let json = r#"{
+            "id": "abc123",
+            "title": "Clip",
+            "subtitles": {
+                "live_chat": [{"ext": "json"}],
+                "fr": [{"ext": "vtt", "name": "French"}],
+                "en": [{"ext": "vtt", "name": "English"}, {"ext": "srt", "name": "English"}]
+            },
+            "automatic_captions": {
+                "en": [{"ext": "vtt", "name": "English (auto-generated)"}]
+            }
+        }"#;
+
+        let tracks = parse_video_metadata(json).unwrap().subtitle_tracks();
+        let labels: Vec<(&str, SubtitleKind)> = tracks
+            .iter()
+            .map(|track| (track.lang.as_str(), track.kind))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                ("en", SubtitleKind::Manual),
+                ("fr", SubtitleKind::Manual),
+                ("en", SubtitleKind::Auto)
+            ]
+        );
+        assert_eq!(tracks[0].formats, vec!["vtt", "srt"]);
+        assert_eq!(tracks[2].name.as_deref(), Some("English (auto-generated)"));
+    }
+
+    #[test]
+    fn test_resolve_sub_langs() {
+        let json = r#"{
+            "id": "abc123",
+            "title": "Clip",
+            "subtitles": {
+                "en": [{"ext": "vtt"}],
+                "en-GB": [{"ext": "vtt"}]
+            },
+            "automatic_captions": {
+                "en": [{"ext": "vtt"}],
+                "de": [{"ext": "vtt"}],
+                "fr": [{"ext": "vtt"}]
+            }
+        }"#;
+        let metadata = parse_video_metadata(json).unwrap();
+        let langs = |list: &[&str]| list.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+
+        // "fr" has no uploaded track, so asking for it as uploaded fetches nothing
+        let (manual, auto) = metadata.resolve_sub_langs(&langs(&["en.*", "fr"]), &langs(&["de"]));
+        assert_eq!(manual, vec!["en", "en-GB"]);
+        assert_eq!(auto, vec!["de"]);
+
+        // Auto "en" is left out: yt-dlp would pick the uploaded track instead
+        let (manual, auto) = metadata.resolve_sub_langs(&[], &langs(&["en", "fr"]));
+        assert!(manual.is_empty());
+        assert_eq!(auto, vec!["fr"]);
+    }
+
+    #[test]
+    fn test_render_filename_template() {
+        let metadata = parse_video_metadata(
+            r#"{"id": "abc123", "title": "Clip", "uploader": "Someone", "upload_date": "20240315"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            metadata
+                .render_filename_template("%(upload_date)s - %(title)s [%(id)s].%(ext)s", "mp4"),
+            "20240315 - Clip [abc123].mp4"
+        );
+        assert_eq!(
+            metadata.render_filename_template("%(uploader)s - %(channel)s", "mp3"),
+            "Someone - NA"
+        );
+        // An unterminated placeholder is kept as written
+        assert_eq!(
+            metadata.render_filename_template("%(title)s %(oops", "mp4"),
+            "Clip %(oops"
+        );
+
+        let metadata = parse_video_metadata(
+            r#"{"id": "abc123", "title": "\nAC/DC: Live? 12:30\n\nEncore \"Cut\""}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            metadata.render_filename_template("%(title)s.%(ext)s", "mkv"),
+            "AC\u{29F8}DC\u{FF1A} Live\u{FF1F} 12_30 Encore \u{FF02}Cut\u{FF02}.mkv"
+        );
+    }
+}
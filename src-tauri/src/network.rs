@@ -0,0 +1,131 @@
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+/// Environment variables checked for a proxy, in priority order
+const PROXY_ENV_VARS: [&str; 4] = ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"];
+
+/// Proxy configured through the environment, if any
+pub fn env_proxy() -> Option<String> {
+    PROXY_ENV_VARS.iter().find_map(|var| {
+        std::env::var(var)
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    })
+}
+
+/// Proxy to use for downloads: an explicit setting overrides the environment
+pub fn resolve_proxy(explicit: Option<&str>) -> Option<String> {
+    explicit
+        .map(str::trim)
+        .filter(|proxy| !proxy.is_empty())
+        .map(str::to_string)
+        .or_else(env_proxy)
+}
+
+/// How long binary and update downloads wait for a connection before giving up
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long a binary download may go without receiving anything before giving up
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// HTTP client for binary and update downloads
+/// Routes through the environment proxy so corporate networks work without extra setup
+/// Only connecting is time-limited here, since large binaries may legitimately take
+/// minutes; wrap each send() and chunk() in idle_timeout so a stalled server fails
+pub fn http_client() -> reqwest::Client {
+    let mut builder = reqwest::Client::builder().connect_timeout(CONNECT_TIMEOUT);
+
+    if let Some(proxy) = env_proxy() {
+        match reqwest::Proxy::all(&proxy) {
+            Ok(proxy) => {
+                debug!("Using proxy from environment for HTTP requests");
+                builder = builder.proxy(proxy);
+            }
+            Err(e) => warn!("Ignoring invalid proxy in environment: {}", e),
+        }
+    }
+
+    builder.build().unwrap_or_else(|e| {
+        warn!(
+            "Failed to build HTTP client with proxy, using defaults: {}",
+            e
+        );
+        reqwest::Client::new()
+    })
+}
+
+/// Await one step of an HTTP transfer, failing when nothing arrives within IDLE_TIMEOUT
+/// reqwest 0.11 has no read timeout, so a server that accepts the request and then
+/// stops sending would otherwise block forever
+pub async fn idle_timeout<T>(step: impl Future<Output = reqwest::Result<T>>) -> Result<T, String> {
+    match tokio::time::timeout(IDLE_TIMEOUT, step).await {
+        Ok(result) => result.map_err(|e| e.to_string()),
+        Err(_) => Err(format!(
+            "No data received for {} seconds",
+            IDLE_TIMEOUT.as_secs()
+        )),
+    }
+}
+
+/// GitHub API quota reported by the X-RateLimit-* headers of the last API response
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GithubRateLimit {
+    pub limit: Option<u32>,
+    pub remaining: u32,
+    /// Unix time (seconds) at which the quota refills
+    pub reset_at: u64,
+}
+
+/// Shared by setup and the yt-dlp updater, which both query the releases API
+static GITHUB_RATE_LIMIT: Mutex<Option<GithubRateLimit>> = Mutex::new(None);
+
+/// Remember the rate-limit headers of a GitHub API response
+/// Unauthenticated requests get 60 per hour, so a failed update check is often
+/// just throttling; a warning with the reset time makes that visible in the logs
+pub fn record_github_ratelimit(headers: &reqwest::header::HeaderMap) {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+    };
+
+    let (remaining, reset_at) = match (header("x-ratelimit-remaining"), header("x-ratelimit-reset"))
+    {
+        (Some(remaining), Some(reset_at)) => (remaining as u32, reset_at),
+        _ => return,
+    };
+    let rate_limit = GithubRateLimit {
+        limit: header("x-ratelimit-limit").map(|limit| limit as u32),
+        remaining,
+        reset_at,
+    };
+
+    if remaining == 0 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        warn!(
+            "GitHub API rate limit exhausted; resets in {}s (at unix time {})",
+            reset_at.saturating_sub(now),
+            reset_at
+        );
+    } else {
+        debug!("GitHub API requests remaining: {}", remaining);
+    }
+
+    if let Ok(mut last) = GITHUB_RATE_LIMIT.lock() {
+        *last = Some(rate_limit);
+    }
+}
+
+/// Last rate-limit values seen, or None before any GitHub API request this session
+pub fn github_ratelimit() -> Option<GithubRateLimit> {
+    GITHUB_RATE_LIMIT.lock().ok().and_then(|last| *last)
+}
@@ -0,0 +1,156 @@
+use crate::errors::DownloadError;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+use tracing::{debug, info};
+
+/// Maximum number of yt-dlp processes allowed to run at the same time
+pub const MAX_CONCURRENT_DOWNLOADS: usize = 3;
+
+/// A download waiting for a free slot
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuedDownload {
+    pub id: String,
+    pub url: String,
+    pub output_path: String,
+}
+
+/// A download holding a slot
+#[derive(Debug)]
+struct RunningDownload {
+    item: QueuedDownload,
+    /// Set by a cancel; checked before each attempt
+    cancelled: bool,
+}
+
+/// Downloads holding a slot, by id
+/// A std Mutex so DownloadSlot can deregister itself on drop
+type RunningDownloads = Arc<std::sync::Mutex<HashMap<String, RunningDownload>>>;
+
+/// Concurrency-limited download queue
+/// Pending items are keyed by download id so they can be cancelled before yt-dlp is spawned
+pub struct DownloadQueue {
+    slots: Arc<Semaphore>,
+    pending: Mutex<HashMap<String, QueuedDownload>>,
+    /// Downloads past the queue, so a cancel between two attempts isn't lost
+    running: RunningDownloads,
+}
+
+/// A download's claim on a slot, held until every attempt has finished
+pub struct DownloadSlot {
+    id: String,
+    running: RunningDownloads,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Drop for DownloadSlot {
+    fn drop(&mut self) {
+        if let Ok(mut running) = self.running.lock() {
+            running.remove(&self.id);
+        }
+    }
+}
+
+impl DownloadQueue {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            slots: Arc::new(Semaphore::new(max_concurrent)),
+            pending: Mutex::new(HashMap::new()),
+            running: Arc::new(std::sync::Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Add a download to the pending set
+    pub async fn enqueue(&self, item: QueuedDownload) {
+        info!("Queued download: {}", item.id);
+        let mut pending = self.pending.lock().await;
+        pending.insert(item.id.clone(), item);
+    }
+
+    /// Wait until a slot is free for the given download
+    /// The returned slot holds it until dropped, and records cancels from then on
+    /// Fails with Cancelled if the item was removed from the queue while waiting
+    pub async fn wait_for_slot(&self, id: &str) -> Result<DownloadSlot, DownloadError> {
+        let permit = self
+            .slots
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| DownloadError::Cancelled)?;
+
+        // Moved to running while the pending lock is held, so a cancel finds it in one or the other
+        let mut pending = self.pending.lock().await;
+        let item = match pending.remove(id) {
+            Some(item) => item,
+            None => {
+                info!("Queued download {} was cancelled before starting", id);
+                return Err(DownloadError::Cancelled);
+            }
+        };
+        if let Ok(mut running) = self.running.lock() {
+            running.insert(
+                id.to_string(),
+                RunningDownload {
+                    item,
+                    cancelled: false,
+                },
+            );
+        }
+        drop(pending);
+
+        debug!("Download {} acquired a slot", id);
+        Ok(DownloadSlot {
+            id: id.to_string(),
+            running: self.running.clone(),
+            _permit: permit,
+        })
+    }
+
+    /// Record a cancel for a download holding a slot
+    /// Returns None when the download isn't past the queue
+    pub fn request_cancel(&self, id: &str) -> Option<QueuedDownload> {
+        let mut running = self.running.lock().ok()?;
+        let download = running.get_mut(id)?;
+        download.cancelled = true;
+        Some(download.item.clone())
+    }
+
+    /// Whether a cancel was requested for a download holding a slot
+    /// Checked before every attempt, since between attempts there is no process to kill
+    pub fn is_cancelled(&self, id: &str) -> bool {
+        self.running
+            .lock()
+            .map(|running| matches!(running.get(id), Some(download) if download.cancelled))
+            .unwrap_or(false)
+    }
+
+    /// Remove a pending download so it never spawns yt-dlp
+    pub async fn cancel_pending(&self, id: &str) -> Option<QueuedDownload> {
+        self.pending.lock().await.remove(id)
+    }
+
+    /// Number of downloads still waiting for a slot
+    pub async fn pending_count(&self) -> usize {
+        self.pending.lock().await.len()
+    }
+
+    /// Mark every download holding a slot as cancelled (used on shutdown)
+    pub fn cancel_all_running(&self) {
+        if let Ok(mut running) = self.running.lock() {
+            running
+                .values_mut()
+                .for_each(|download| download.cancelled = true);
+        }
+    }
+
+    /// Remove every pending download (used on shutdown)
+    pub async fn cancel_all_pending(&self) -> Vec<QueuedDownload> {
+        self.pending
+            .lock()
+            .await
+            .drain()
+            .map(|(_, item)| item)
+            .collect()
+    }
+}
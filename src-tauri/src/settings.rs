@@ -0,0 +1,316 @@
+use crate::download::{is_valid_quality, AudioFormat, SubtitleMode};
+use crate::validation::{
+    validate_library_extensions, validate_subtitle_langs, NO_LIBRARY_ROOT_ERROR,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// Browsers yt-dlp can read cookies from that ripVID knows how to detect
+pub const SUPPORTED_BROWSERS: [&str; 4] = ["firefox", "chrome", "edge", "brave"];
+
+/// Download history entries kept unless the user changes it
+pub const DEFAULT_MAX_HISTORY_ENTRIES: usize = 500;
+
+/// Upper bound for max_history_entries, so the history file stays quick to load
+const MAX_HISTORY_ENTRIES_LIMIT: usize = 10_000;
+
+/// Media files the library scan shows unless the user changes the list
+pub const DEFAULT_LIBRARY_EXTENSIONS: [&str; 11] = [
+    "mp4", "mkv", "webm", "mov", "mp3", "m4a", "aac", "opus", "ogg", "flac", "wav",
+];
+
+/// Age, in days behind the latest release, at which preflight_check flags yt-dlp as outdated
+pub const DEFAULT_YTDLP_OUTDATED_DAYS: u32 = 30;
+
+/// User settings persisted to settings.json in the app data directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AppSettings {
+    /// Browsers tried, in order, when smart retry escalates to cookies
+    pub retry_browsers: Vec<String>,
+    /// Allow smart retry to read browser cookies after an authentication error
+    pub cookie_retry: bool,
+    /// Let smart retry try .netrc credentials before browser cookies
+    pub use_netrc: bool,
+    /// .netrc file used instead of ~/.netrc
+    pub netrc_location: Option<String>,
+    /// Quality used when a video download doesn't specify one
+    pub default_quality: String,
+    /// Format used when an audio download doesn't specify one
+    #[serde(deserialize_with = "or_default")]
+    pub default_audio_format: AudioFormat,
+    /// Proxy passed to yt-dlp; overrides HTTPS_PROXY/ALL_PROXY from the environment
+    pub proxy: Option<String>,
+    /// Folders outside home that custom-path downloads may write to
+    pub allowed_directories: Vec<String>,
+    /// Library folder holding MP4/ and MP3/; defaults to ~/Videos/ripVID
+    pub default_download_dir: Option<String>,
+    /// Reveal the downloaded file in the file manager when a download succeeds
+    pub open_on_complete: bool,
+    /// Save downloads into YYYY/YYYY-MM/ subfolders by upload date
+    pub organize_by_date: bool,
+    /// Delete files outright instead of using the recycle bin; they can't be restored
+    pub always_permanent_delete: bool,
+    /// Subtitle handling for video downloads that don't choose one
+    #[serde(deserialize_with = "or_default")]
+    pub default_subtitle_mode: SubtitleMode,
+    /// Uploaded subtitle languages for video downloads that don't choose any, e.g. ["en"]
+    #[serde(alias = "defaultSubtitleLangs")]
+    pub default_manual_sub_langs: Vec<String>,
+    /// Auto-generated caption languages for video downloads that don't choose any
+    pub default_auto_sub_langs: Vec<String>,
+    /// Download history entries kept; the oldest are dropped beyond this
+    pub max_history_entries: usize,
+    /// preflight_check warns when yt-dlp is more than this many days behind the latest release
+    pub ytdlp_outdated_days: u32,
+    /// File extensions, without the dot, that the library scan and stats include
+    pub library_extensions: Vec<String>,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            // Firefox first - doesn't have Windows DPAPI cookie encryption issues
+            retry_browsers: SUPPORTED_BROWSERS.iter().map(|b| b.to_string()).collect(),
+            cookie_retry: true,
+            use_netrc: false,
+            netrc_location: None,
+            default_quality: "best".to_string(),
+            default_audio_format: AudioFormat::default(),
+            proxy: None,
+            allowed_directories: Vec::new(),
+            default_download_dir: None,
+            open_on_complete: false,
+            organize_by_date: false,
+            always_permanent_delete: false,
+            default_subtitle_mode: SubtitleMode::default(),
+            default_manual_sub_langs: Vec::new(),
+            default_auto_sub_langs: Vec::new(),
+            max_history_entries: DEFAULT_MAX_HISTORY_ENTRIES,
+            ytdlp_outdated_days: DEFAULT_YTDLP_OUTDATED_DAYS,
+            library_extensions: DEFAULT_LIBRARY_EXTENSIONS
+                .iter()
+                .map(|e| e.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl AppSettings {
+    /// Reject values the download code can't act on
+    pub fn validate(&self) -> Result<(), String> {
+        for browser in &self.retry_browsers {
+            if !SUPPORTED_BROWSERS.contains(&browser.as_str()) {
+                return Err(format!(
+                    "Unsupported browser '{}'. Supported: {}",
+                    browser,
+                    SUPPORTED_BROWSERS.join(", ")
+                ));
+            }
+        }
+
+        if !is_valid_quality(&self.default_quality) {
+            return Err(format!(
+                "Invalid default quality '{}'. Use 'best' or a height like '1080p'",
+                self.default_quality
+            ));
+        }
+
+        if let Some(proxy) = self.proxy.as_deref().filter(|p| !p.trim().is_empty()) {
+            let valid = url::Url::parse(proxy.trim())
+                .map(|url| matches!(url.scheme(), "http" | "https" | "socks4" | "socks5"))
+                .unwrap_or(false);
+            if !valid {
+                return Err(format!(
+                    "Invalid proxy '{}'. Use a URL like http://host:port or socks5://host:port",
+                    proxy
+                ));
+            }
+        }
+
+        validate_subtitle_langs(&self.default_manual_sub_langs)?;
+        validate_subtitle_langs(&self.default_auto_sub_langs)?;
+
+        if self.max_history_entries == 0 || self.max_history_entries > MAX_HISTORY_ENTRIES_LIMIT {
+            return Err(format!(
+                "History size must be between 1 and {}",
+                MAX_HISTORY_ENTRIES_LIMIT
+            ));
+        }
+
+        if self.ytdlp_outdated_days == 0 || self.ytdlp_outdated_days > 365 {
+            return Err("yt-dlp outdated threshold must be between 1 and 365 days".to_string());
+        }
+
+        validate_library_extensions(&self.library_extensions)?;
+
+        for dir in &self.allowed_directories {
+            if !Path::new(dir).is_absolute() {
+                return Err(format!(
+                    "Allowed directory '{}' must be an absolute path",
+                    dir
+                ));
+            }
+        }
+
+        if let Some(location) = self.netrc_location.as_deref().map(str::trim) {
+            if !location.is_empty() && !Path::new(location).is_absolute() {
+                return Err(format!(
+                    ".netrc location '{}' must be an absolute path",
+                    location
+                ));
+            }
+        }
+
+        if let Some(dir) = self.configured_download_dir() {
+            if !Path::new(dir).is_absolute() {
+                return Err(format!(
+                    "Default download folder '{}' must be an absolute path",
+                    dir
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn configured_download_dir(&self) -> Option<&str> {
+        self.default_download_dir
+            .as_deref()
+            .map(str::trim)
+            .filter(|dir| !dir.is_empty())
+    }
+
+    /// Resolve the library folder: the configured download folder, else ~/Videos/ripVID
+    pub fn library_root(&self) -> Result<PathBuf, String> {
+        if let Some(dir) = self.configured_download_dir() {
+            return Ok(PathBuf::from(dir));
+        }
+
+        dirs::home_dir()
+            .map(|home| home.join("Videos").join("ripVID"))
+            .ok_or_else(|| NO_LIBRARY_ROOT_ERROR.to_string())
+    }
+
+    /// Whether the library scan should include a file, judged by its extension
+    pub fn is_library_file(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .map(|e| {
+                self.library_extensions
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(e))
+            })
+            .unwrap_or(false)
+    }
+
+    /// The library folder plus allowed_directories, for validate_path on existing files
+    pub fn accessible_dirs(&self) -> Vec<PathBuf> {
+        self.library_root()
+            .into_iter()
+            .chain(self.allowed_directories.iter().map(PathBuf::from))
+            .collect()
+    }
+
+    /// Reset invalid defaults instead of discarding the whole file
+    fn sanitize(&mut self) {
+        if !is_valid_quality(&self.default_quality) {
+            warn!(
+                "Invalid default quality '{}' in settings, resetting to 'best'",
+                self.default_quality
+            );
+            self.default_quality = "best".to_string();
+        }
+    }
+}
+
+/// Deserialize a value, falling back to its default if it isn't recognised
+fn or_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned + Default,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    Ok(serde_json::from_value(value.clone()).unwrap_or_else(|_| {
+        warn!("Unrecognised setting value {}, using default", value);
+        T::default()
+    }))
+}
+
+/// Loads and saves AppSettings
+pub struct SettingsManager {
+    path: PathBuf,
+    current: Mutex<AppSettings>,
+}
+
+impl SettingsManager {
+    pub fn new(app_handle: &AppHandle) -> Self {
+        let path = app_handle
+            .path()
+            .app_data_dir()
+            .unwrap_or_else(|_| PathBuf::from("."))
+            .join("settings.json");
+
+        let current = Self::load(&path);
+
+        Self {
+            path,
+            current: Mutex::new(current),
+        }
+    }
+
+    /// Read settings from disk, falling back to defaults if missing or invalid
+    fn load(path: &Path) -> AppSettings {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(_) => {
+                info!("No settings file found, using defaults");
+                return AppSettings::default();
+            }
+        };
+
+        match serde_json::from_str::<AppSettings>(&content) {
+            Ok(mut settings) => {
+                settings.sanitize();
+                if settings.validate().is_ok() {
+                    settings
+                } else {
+                    warn!("Settings file is invalid, using defaults");
+                    AppSettings::default()
+                }
+            }
+            Err(_) => {
+                warn!("Settings file is invalid, using defaults");
+                AppSettings::default()
+            }
+        }
+    }
+
+    /// Get a snapshot of the current settings
+    pub async fn get(&self) -> AppSettings {
+        self.current.lock().await.clone()
+    }
+
+    /// Validate, persist and apply new settings
+    pub async fn update(&self, settings: AppSettings) -> Result<(), String> {
+        settings.validate()?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+        }
+
+        let json = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+        fs::write(&self.path, json).map_err(|e| format!("Failed to save settings: {}", e))?;
+
+        *self.current.lock().await = settings;
+        info!("Settings saved");
+
+        Ok(())
+    }
+}
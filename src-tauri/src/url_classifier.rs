@@ -0,0 +1,507 @@
+use crate::validation::validate_url;
+use serde::Serialize;
+use std::collections::HashMap;
+use url::Url;
+
+/// What a URL points at, so the UI knows whether to offer playlist options
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UrlKind {
+    Video,
+    Playlist,
+    Channel,
+    Unknown,
+}
+
+/// Result of the classify_url command
+#[derive(Debug, Clone, Serialize)]
+pub struct UrlClassification {
+    pub platform: String,
+    pub kind: UrlKind,
+}
+
+/// Pre-flight result for one URL of a batch
+#[derive(Debug, Clone, Serialize)]
+pub struct UrlValidation {
+    pub url: String,
+    pub valid: bool,
+    pub platform: Option<String>,
+    pub error: Option<String>,
+    /// Earlier URL in the batch pointing at the same video
+    pub duplicate_of: Option<String>,
+}
+
+/// What the app can do on a platform, so the UI only shows controls that apply
+/// Maintained by hand from experience with each site, not queried from yt-dlp
+#[derive(Debug, Clone, Serialize)]
+pub struct PlatformCapabilities {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub supports_playlist: bool,
+    pub supports_subtitles: bool,
+    pub supports_live: bool,
+    pub typical_qualities: &'static [&'static str],
+    pub defaults: PlatformDefaults,
+}
+
+/// Choices pre-selected for a platform when the user hasn't made their own
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PlatformDefaults {
+    /// Download audio rather than video, for audio-first sites
+    pub audio_only: bool,
+    /// Video quality used while the default quality setting is untouched; None keeps it
+    pub quality: Option<&'static str>,
+}
+
+/// Defaults for platforms without special needs
+const NO_DEFAULTS: PlatformDefaults = PlatformDefaults {
+    audio_only: false,
+    quality: None,
+};
+
+/// Every platform detect_platform recognizes
+pub const SUPPORTED_PLATFORMS: &[PlatformCapabilities] = &[
+    PlatformCapabilities {
+        id: "youtube",
+        name: "YouTube",
+        supports_playlist: true,
+        supports_subtitles: true,
+        supports_live: true,
+        typical_qualities: &["2160p", "1440p", "1080p", "720p", "480p", "360p"],
+        // "best" merges VP9/AV1 into mkv; 1080p stays in H.264 mp4
+        defaults: PlatformDefaults {
+            audio_only: false,
+            quality: Some("1080p"),
+        },
+    },
+    PlatformCapabilities {
+        id: "x",
+        name: "X (Twitter)",
+        supports_playlist: false,
+        supports_subtitles: false,
+        supports_live: false,
+        typical_qualities: &["1080p", "720p", "480p"],
+        defaults: NO_DEFAULTS,
+    },
+    PlatformCapabilities {
+        id: "facebook",
+        name: "Facebook",
+        supports_playlist: false,
+        supports_subtitles: true,
+        supports_live: true,
+        typical_qualities: &["1080p", "720p", "360p"],
+        defaults: NO_DEFAULTS,
+    },
+    PlatformCapabilities {
+        id: "instagram",
+        name: "Instagram",
+        supports_playlist: false,
+        supports_subtitles: false,
+        supports_live: false,
+        typical_qualities: &["1080p", "720p"],
+        defaults: NO_DEFAULTS,
+    },
+    PlatformCapabilities {
+        id: "tiktok",
+        name: "TikTok",
+        supports_playlist: true,
+        supports_subtitles: true,
+        supports_live: false,
+        typical_qualities: &["1080p", "720p", "540p"],
+        defaults: NO_DEFAULTS,
+    },
+    PlatformCapabilities {
+        id: "soundcloud",
+        name: "SoundCloud",
+        supports_playlist: true,
+        supports_subtitles: false,
+        supports_live: false,
+        typical_qualities: &[],
+        defaults: PlatformDefaults {
+            audio_only: true,
+            quality: None,
+        },
+    },
+    PlatformCapabilities {
+        id: "twitch",
+        name: "Twitch",
+        supports_playlist: false,
+        supports_subtitles: false,
+        supports_live: true,
+        typical_qualities: &["1080p", "720p", "480p", "360p"],
+        // Clips are short, so the source quality is worth it
+        defaults: PlatformDefaults {
+            audio_only: false,
+            quality: Some("best"),
+        },
+    },
+];
+
+/// Defaults for the platform of a URL; unrecognized platforms get none
+pub fn platform_defaults(url: &str) -> PlatformDefaults {
+    detect_platform(url)
+        .and_then(|id| {
+            SUPPORTED_PLATFORMS
+                .iter()
+                .find(|platform| platform.id == id)
+        })
+        .map_or(NO_DEFAULTS, |platform| platform.defaults)
+}
+
+/// Detect the platform from a URL
+pub fn detect_platform(url: &str) -> Option<&'static str> {
+    if url.contains("youtube.com") || url.contains("youtu.be") {
+        Some("youtube")
+    } else if url.contains("x.com") || url.contains("twitter.com") {
+        Some("x")
+    } else if url.contains("facebook.com") || url.contains("fb.watch") {
+        Some("facebook")
+    } else if url.contains("instagram.com") {
+        Some("instagram")
+    } else if url.contains("tiktok.com") {
+        Some("tiktok")
+    } else if url.contains("soundcloud.com") {
+        Some("soundcloud")
+    } else if url.contains("twitch.tv") {
+        Some("twitch")
+    } else {
+        None
+    }
+}
+
+/// Classify a URL as a single video, playlist or channel from its path/query
+pub fn classify_url(url: &str) -> Option<UrlClassification> {
+    let platform = detect_platform(url)?;
+
+    let kind = match platform {
+        "youtube" => classify_youtube(url),
+        "tiktok" => {
+            if url.contains("/video/") {
+                UrlKind::Video
+            } else if url.contains("/@") {
+                UrlKind::Channel
+            } else {
+                UrlKind::Unknown
+            }
+        }
+        "x" => {
+            // Spaces are audio-only recordings, downloadable like a post's video
+            if url.contains("/status/") || url.contains("/i/spaces/") {
+                UrlKind::Video
+            } else {
+                UrlKind::Unknown
+            }
+        }
+        "instagram" => {
+            if url.contains("/reel/") || url.contains("/p/") || url.contains("/tv/") {
+                UrlKind::Video
+            } else {
+                UrlKind::Unknown
+            }
+        }
+        "facebook" => {
+            if url.contains("fb.watch") || url.contains("/videos/") || url.contains("/watch") {
+                UrlKind::Video
+            } else {
+                UrlKind::Unknown
+            }
+        }
+        "soundcloud" => classify_soundcloud(url),
+        "twitch" => {
+            if url.contains("clips.twitch.tv") || url.contains("/clip/") || url.contains("/videos/")
+            {
+                UrlKind::Video
+            } else {
+                UrlKind::Unknown
+            }
+        }
+        _ => UrlKind::Unknown,
+    };
+
+    Some(UrlClassification {
+        platform: platform.to_string(),
+        kind,
+    })
+}
+
+/// Reduce a video URL to "platform:id" so different forms of the same link compare equal
+/// e.g. youtu.be/abc, youtube.com/watch?v=abc and youtube.com/shorts/abc are all "youtube:abc"
+pub fn canonical_video_id(url: &str) -> Option<String> {
+    let platform = detect_platform(url)?;
+    let parsed = Url::parse(url).ok()?;
+    let segments: Vec<&str> = parsed
+        .path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default();
+    let query_param = |name: &str| {
+        parsed
+            .query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.to_string())
+    };
+    // The segment following a marker like "video" in /@user/video/123
+    let after = |marker: &str| {
+        segments
+            .iter()
+            .position(|segment| *segment == marker)
+            .and_then(|i| segments.get(i + 1))
+            .map(|id| id.to_string())
+    };
+
+    let id = match platform {
+        "youtube" => {
+            if parsed.host_str() == Some("youtu.be") {
+                segments.first().map(|id| id.to_string())
+            } else {
+                query_param("v")
+                    .or_else(|| after("shorts"))
+                    .or_else(|| after("live"))
+                    .or_else(|| after("embed"))
+            }
+        }
+        "x" => after("status").or_else(|| after("spaces")),
+        "tiktok" => after("video"),
+        "instagram" => after("reel").or_else(|| after("p")).or_else(|| after("tv")),
+        "facebook" => {
+            if parsed.host_str() == Some("fb.watch") {
+                segments.first().map(|id| id.to_string())
+            } else {
+                query_param("v").or_else(|| after("videos"))
+            }
+        }
+        // Tracks have no numeric id in the URL; "artist/track" is unique
+        "soundcloud" => match segments.as_slice() {
+            [artist, track] if *track != "sets" => Some(format!("{}/{}", artist, track)),
+            _ => None,
+        },
+        "twitch" => {
+            if parsed.host_str() == Some("clips.twitch.tv") {
+                segments.first().map(|id| id.to_string())
+            } else {
+                after("clip").or_else(|| after("videos"))
+            }
+        }
+        _ => None,
+    }?;
+
+    Some(format!("{}:{}", platform, id))
+}
+
+/// Validate a batch of URLs and flag repeats of the same video
+pub fn validate_url_batch(urls: &[String]) -> Vec<UrlValidation> {
+    let mut seen: HashMap<String, String> = HashMap::new();
+
+    urls.iter()
+        .map(|url| {
+            let url = url.trim();
+            let mut result = UrlValidation {
+                url: url.to_string(),
+                valid: false,
+                platform: None,
+                error: None,
+                duplicate_of: None,
+            };
+
+            if let Err(e) = validate_url(url) {
+                result.error = Some(e);
+                return result;
+            }
+
+            match detect_platform(url) {
+                Some(platform) => {
+                    result.valid = true;
+                    result.platform = Some(platform.to_string());
+                }
+                None => {
+                    result.error = Some("Unsupported platform".to_string());
+                    return result;
+                }
+            }
+
+            if let Some(id) = canonical_video_id(url) {
+                match seen.get(&id) {
+                    Some(first) => result.duplicate_of = Some(first.clone()),
+                    None => {
+                        seen.insert(id, url.to_string());
+                    }
+                }
+            }
+
+            result
+        })
+        .collect()
+}
+
+fn classify_soundcloud(url: &str) -> UrlKind {
+    let path = Url::parse(url)
+        .map(|parsed| parsed.path().trim_matches('/').to_string())
+        .unwrap_or_default();
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    match segments.as_slice() {
+        [_, "sets", _] => UrlKind::Playlist,
+        [_] => UrlKind::Channel,
+        [_, _] => UrlKind::Video,
+        _ => UrlKind::Unknown,
+    }
+}
+
+fn classify_youtube(url: &str) -> UrlKind {
+    // yt-dlp downloads the whole list for watch URLs that carry list=
+    if url.contains("list=") || url.contains("/playlist") {
+        UrlKind::Playlist
+    } else if url.contains("/@")
+        || url.contains("/channel/")
+        || url.contains("/c/")
+        || url.contains("/user/")
+    {
+        UrlKind::Channel
+    } else if url.contains("watch?")
+        || url.contains("youtu.be/")
+        || url.contains("/shorts/")
+        || url.contains("/live/")
+        || url.contains("/embed/")
+    {
+        UrlKind::Video
+    } else {
+        UrlKind::Unknown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(url: &str) -> UrlKind {
+        classify_url(url).unwrap().kind
+    }
+
+    #[test]
+    fn test_classify_youtube() {
+        assert_eq!(
+            kind("https://www.youtube.com/watch?v=dQw4w9WgXcQ"),
+            UrlKind::Video
+        );
+        assert_eq!(kind("https://youtu.be/dQw4w9WgXcQ"), UrlKind::Video);
+        assert_eq!(
+            kind("https://www.youtube.com/shorts/abc123"),
+            UrlKind::Video
+        );
+        assert_eq!(
+            kind("https://www.youtube.com/playlist?list=PL1234"),
+            UrlKind::Playlist
+        );
+        assert_eq!(
+            kind("https://www.youtube.com/watch?v=abc&list=PL1234"),
+            UrlKind::Playlist
+        );
+        assert_eq!(
+            kind("https://www.youtube.com/@somechannel"),
+            UrlKind::Channel
+        );
+        assert_eq!(
+            kind("https://www.youtube.com/channel/UC1234"),
+            UrlKind::Channel
+        );
+        assert_eq!(kind("https://www.youtube.com/"), UrlKind::Unknown);
+    }
+
+    #[test]
+    fn test_classify_other_platforms() {
+        assert_eq!(
+            kind("https://www.tiktok.com/@user/video/123456"),
+            UrlKind::Video
+        );
+        assert_eq!(kind("https://www.tiktok.com/@user"), UrlKind::Channel);
+        assert_eq!(kind("https://x.com/user/status/123456"), UrlKind::Video);
+        assert_eq!(kind("https://x.com/i/spaces/1YqKDqWqdPLGV"), UrlKind::Video);
+        assert_eq!(
+            kind("https://www.instagram.com/reel/abc123/"),
+            UrlKind::Video
+        );
+        assert!(classify_url("https://example.com/video").is_none());
+    }
+
+    #[test]
+    fn test_canonical_video_id() {
+        for url in [
+            "https://youtu.be/dQw4w9WgXcQ",
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42",
+            "https://www.youtube.com/shorts/dQw4w9WgXcQ",
+        ] {
+            assert_eq!(
+                canonical_video_id(url).as_deref(),
+                Some("youtube:dQw4w9WgXcQ")
+            );
+        }
+        assert_eq!(
+            canonical_video_id("https://x.com/user/status/123").as_deref(),
+            Some("x:123")
+        );
+        assert_eq!(
+            canonical_video_id("https://www.youtube.com/@somechannel"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_validate_url_batch_flags_duplicates() {
+        let urls = vec![
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ".to_string(),
+            "https://youtu.be/dQw4w9WgXcQ".to_string(),
+            "https://example.com/video".to_string(),
+            "not a url".to_string(),
+        ];
+        let results = validate_url_batch(&urls);
+
+        assert!(results[0].valid && results[0].duplicate_of.is_none());
+        assert_eq!(results[1].duplicate_of.as_deref(), Some(urls[0].as_str()));
+        assert!(!results[2].valid);
+        assert!(!results[3].valid && results[3].error.is_some());
+    }
+
+    #[test]
+    fn test_detected_platforms_have_capabilities() {
+        for url in [
+            "https://www.youtube.com/watch?v=abc",
+            "https://x.com/user/status/1",
+            "https://www.facebook.com/watch?v=1",
+            "https://www.instagram.com/reel/abc/",
+            "https://www.tiktok.com/@user/video/1",
+            "https://soundcloud.com/artist/track",
+            "https://clips.twitch.tv/SomeClipSlug",
+        ] {
+            let platform = detect_platform(url).unwrap();
+            assert!(SUPPORTED_PLATFORMS.iter().any(|p| p.id == platform));
+        }
+    }
+
+    #[test]
+    fn test_platform_defaults() {
+        assert!(platform_defaults("https://soundcloud.com/artist/track").audio_only);
+        assert_eq!(
+            platform_defaults("https://www.youtube.com/watch?v=abc").quality,
+            Some("1080p")
+        );
+        assert_eq!(
+            platform_defaults("https://www.twitch.tv/streamer/clip/Slug").quality,
+            Some("best")
+        );
+        let unknown = platform_defaults("https://example.com/video");
+        assert!(!unknown.audio_only);
+        assert_eq!(unknown.quality, None);
+
+        assert_eq!(
+            kind("https://soundcloud.com/artist/sets/mixtape"),
+            UrlKind::Playlist
+        );
+        assert_eq!(kind("https://soundcloud.com/artist"), UrlKind::Channel);
+        assert_eq!(
+            canonical_video_id("https://soundcloud.com/artist/track?in=x").as_deref(),
+            Some("soundcloud:artist/track")
+        );
+        assert_eq!(
+            canonical_video_id("https://www.twitch.tv/streamer/clip/Slug").as_deref(),
+            canonical_video_id("https://clips.twitch.tv/Slug").as_deref()
+        );
+    }
+}
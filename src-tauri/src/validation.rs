@@ -4,6 +4,10 @@
 use std::path::{Path, PathBuf};
 use url::Url;
 
+/// Returned whenever neither a home directory nor a configured download folder is available
+pub const NO_LIBRARY_ROOT_ERROR: &str =
+    "Could not determine the home directory. Set a default download folder in settings.";
+
 /// Validates a URL to prevent command injection and ensure safe URL schemes
 ///
 /// # Security Checks:
@@ -46,11 +50,10 @@ pub fn validate_url(url_str: &str) -> Result<String, String> {
         return Err("URL must have a valid host".to_string());
     }
 
-    // Check for dangerous characters that could be used in shell injection
-    // Even though we use proper argument passing, defense in depth
-    let dangerous_chars = &[
-        '<', '>', '|', '&', ';', '`', '$', '(', ')', '{', '}', '[', ']', '!', '#',
-    ];
+    // Check for characters that mean something to a shell
+    // yt-dlp gets the URL as a single argument, so brackets, parentheses, '!' and
+    // fragments are left alone; real links (CDN paths, wiki-style titles) use them
+    let dangerous_chars = &['`', '$', ';', '|', '&', '<', '>', '\n'];
 
     // Only check dangerous characters in certain parts of the URL
     // Allow them in query parameters as they may be URL-encoded
@@ -63,7 +66,7 @@ pub fn validate_url(url_str: &str) -> Result<String, String> {
     for &ch in dangerous_chars {
         if url_without_query.contains(ch) {
             return Err(format!(
-                "URL contains dangerous character '{}' which is not allowed",
+                "URL contains dangerous character {:?} which is not allowed",
                 ch
             ));
         }
@@ -97,11 +100,16 @@ pub fn validate_url(url_str: &str) -> Result<String, String> {
 /// # Arguments
 /// * `path_str` - The file path to validate
 /// * `allow_nonexistent` - Whether to allow paths that don't exist yet
+/// * `extra_allowed` - Configured folders outside home (e.g. another drive) to also accept
 ///
 /// # Returns
 /// * `Ok(PathBuf)` - Validated and normalized path if safe
 /// * `Err(String)` - Error message if validation fails
-pub fn validate_path(path_str: &str, allow_nonexistent: bool) -> Result<PathBuf, String> {
+pub fn validate_path(
+    path_str: &str,
+    allow_nonexistent: bool,
+    extra_allowed: &[PathBuf],
+) -> Result<PathBuf, String> {
     // Check for empty paths
     if path_str.trim().is_empty() {
         return Err("Path cannot be empty".to_string());
@@ -166,8 +174,17 @@ pub fn validate_path(path_str: &str, allow_nonexistent: bool) -> Result<PathBuf,
         return Err(format!("Path does not exist: {}", absolute_path.display()));
     };
 
+    // User-configured folders are allowed as-is, after resolving symlinks
+    let in_extra_allowed = extra_allowed.iter().any(|dir| {
+        dir.canonicalize()
+            .map(|dir| normalized_path.starts_with(dir))
+            .unwrap_or(false)
+    });
+
     // Check that the normalized path is within allowed directories
-    if let Some(home_dir) = dirs::home_dir() {
+    if in_extra_allowed {
+        // Still subject to the system directory check below
+    } else if let Some(home_dir) = dirs::home_dir() {
         // Check if path is under home directory
         if !normalized_path.starts_with(&home_dir) {
             // Also allow system temp directory
@@ -186,23 +203,14 @@ pub fn validate_path(path_str: &str, allow_nonexistent: bool) -> Result<PathBuf,
             }
         }
     } else {
-        // If we can't determine home directory, be more restrictive
-        // Only allow if explicitly in a safe location
-        let path_str = normalized_path.to_string_lossy().to_lowercase();
-        let safe_prefixes = if cfg!(windows) {
-            vec!["c:\\users\\", "c:\\temp\\", "c:\\tmp\\"]
-        } else {
-            vec!["/home/", "/tmp/", "/var/tmp/"]
-        };
+        // Without a home directory only temp and configured folders are usable
+        let in_temp = std::env::temp_dir()
+            .canonicalize()
+            .map(|temp_dir| normalized_path.starts_with(temp_dir))
+            .unwrap_or(false);
 
-        if !safe_prefixes
-            .iter()
-            .any(|prefix| path_str.starts_with(prefix))
-        {
-            return Err(
-                "Cannot validate path: home directory unknown and path not in safe location"
-                    .to_string(),
-            );
+        if !in_temp {
+            return Err(NO_LIBRARY_ROOT_ERROR.to_string());
         }
     }
 
@@ -238,12 +246,462 @@ pub fn validate_path(path_str: &str, allow_nonexistent: bool) -> Result<PathBuf,
 ///
 /// # Arguments
 /// * `path_str` - The output file path
+/// * `extra_allowed` - Folders allowed in addition to home and temp
 ///
 /// # Returns
 /// * `Ok(PathBuf)` - Validated path if safe
 /// * `Err(String)` - Error message if validation fails
-pub fn validate_output_path(path_str: &str) -> Result<PathBuf, String> {
-    validate_path(path_str, true)
+pub fn validate_output_path(path_str: &str, extra_allowed: &[PathBuf]) -> Result<PathBuf, String> {
+    let path = validate_path(path_str, true, extra_allowed)?;
+    check_windows_path_length(&path.to_string_lossy())?;
+    Ok(path)
+}
+
+/// yt-dlp options extra_args may set
+/// An allowlist, since yt-dlp keeps adding options: anything that redirects output,
+/// runs programs or postprocessors (--exec, --use-postprocessor), defines options
+/// (--alias), passes arguments to ffmpeg (--postprocessor-args) or touches other
+/// files stays out until it is known to be safe
+const ALLOWED_YTDLP_OPTIONS: &[&str] = &[
+    // Network and rate limiting
+    "-4",
+    "-6",
+    "--force-ipv4",
+    "--force-ipv6",
+    "-r",
+    "--limit-rate",
+    "--throttled-rate",
+    "-R",
+    "--retries",
+    "--fragment-retries",
+    "--extractor-retries",
+    "--retry-sleep",
+    "--socket-timeout",
+    "--sleep-interval",
+    "--min-sleep-interval",
+    "--max-sleep-interval",
+    "--sleep-requests",
+    "--sleep-subtitles",
+    "-N",
+    "--concurrent-fragments",
+    "--http-chunk-size",
+    "--buffer-size",
+    "--no-resize-buffer",
+    "--no-check-certificates",
+    "--legacy-server-connect",
+    "--referer",
+    "--add-header",
+    // Format selection
+    "-f",
+    "--format",
+    "-S",
+    "--format-sort",
+    "--format-sort-force",
+    "--no-format-sort-force",
+    "--check-formats",
+    "--no-check-formats",
+    "--extractor-args",
+    // Playlists and filtering
+    "--no-playlist",
+    "--yes-playlist",
+    "-I",
+    "--playlist-items",
+    "--match-filters",
+    "--age-limit",
+    "--live-from-start",
+    "--no-live-from-start",
+    "--wait-for-video",
+    "--hls-use-mpegts",
+    "--no-hls-use-mpegts",
+    // Chapters and SponsorBlock
+    "--embed-chapters",
+    "--no-embed-chapters",
+    "--sponsorblock-mark",
+    "--sponsorblock-remove",
+    "--no-sponsorblock",
+    // Misc
+    "--no-mtime",
+    "--xattrs",
+    "--abort-on-error",
+    "--no-abort-on-error",
+    "-v",
+    "--verbose",
+    "--no-warnings",
+];
+
+/// Validates extra yt-dlp arguments passed through by power users
+///
+/// # Security Checks:
+/// - Each argument may only contain letters, digits and `-_=.:,/+%@[]`
+/// - Short options must stand alone (`-k`, not `-ofile`)
+/// - Only options in the allowlist are accepted; arguments not starting with `-`
+///   are taken as their values
+///
+/// # Arguments
+/// * `args` - The arguments to append to the yt-dlp command line
+///
+/// # Returns
+/// * `Ok(())` - All arguments are safe to pass
+/// * `Err(String)` - Error message naming the first rejected argument
+pub fn validate_extra_args(args: &[String]) -> Result<(), String> {
+    if args.len() > 32 {
+        return Err("Too many extra arguments (max 32)".to_string());
+    }
+
+    for arg in args {
+        if arg.is_empty() {
+            return Err("Extra arguments cannot be empty".to_string());
+        }
+
+        if arg.len() > 256 {
+            return Err(format!(
+                "Extra argument is too long (max 256 characters): {}",
+                arg
+            ));
+        }
+
+        // Whitelist rather than blacklist: rules out shell metacharacters,
+        // quotes, whitespace and control characters in one check
+        if let Some(ch) = arg
+            .chars()
+            .find(|ch| !ch.is_ascii_alphanumeric() && !"-_=.:,/+%@[]".contains(*ch))
+        {
+            return Err(format!(
+                "Extra argument '{}' contains '{}' which is not allowed",
+                arg, ch
+            ));
+        }
+
+        if arg.starts_with('-') && !arg.starts_with("--") && arg.len() > 2 {
+            return Err(format!(
+                "Short option '{}' must be passed on its own, without a value attached",
+                arg
+            ));
+        }
+
+        let option = arg.split('=').next().unwrap_or(arg);
+        if option.starts_with('-') && !ALLOWED_YTDLP_OPTIONS.contains(&option) {
+            return Err(format!("Extra argument '{}' is not allowed", option));
+        }
+    }
+
+    Ok(())
+}
+
+/// Longest filename, in bytes, that sanitize_filename returns
+const MAX_FILENAME_BYTES: usize = 240;
+
+/// Names Windows reserves for devices, with or without an extension
+const WINDOWS_RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Makes a single filename safe to create on this OS
+///
+/// # Arguments
+/// * `name` - Filename without directories; path separators are replaced too
+/// * `windows` - Also apply Windows rules: `<>:"\|?*`, trailing dots and spaces, and
+///   reserved device names like CON
+///
+/// # Returns
+/// * `String` - The cleaned name, at most 240 bytes, or "download" if nothing is left
+pub fn sanitize_filename(name: &str, windows: bool) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| {
+            let invalid = c == '/'
+                || c.is_control()
+                || (windows && matches!(c, '<' | '>' | ':' | '"' | '\\' | '|' | '?' | '*'));
+            if invalid {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+
+    if sanitized.len() > MAX_FILENAME_BYTES {
+        let mut end = MAX_FILENAME_BYTES;
+        while !sanitized.is_char_boundary(end) {
+            end -= 1;
+        }
+        sanitized.truncate(end);
+    }
+
+    let trimmed = if windows {
+        sanitized.trim_end_matches(['.', ' ']).trim_start()
+    } else {
+        sanitized.trim()
+    };
+    if trimmed.is_empty() || trimmed == "." || trimmed == ".." {
+        return "download".to_string();
+    }
+
+    let stem = trimmed.split('.').next().unwrap_or(trimmed).trim_end();
+    if windows
+        && WINDOWS_RESERVED_NAMES
+            .iter()
+            .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+    {
+        return format!("_{}", trimmed);
+    }
+
+    trimmed.to_string()
+}
+
+/// Validates the file extensions the library scan recognizes
+///
+/// # Arguments
+/// * `extensions` - Extensions without the dot, such as "mp4" or "flac"
+///
+/// # Returns
+/// * `Ok(())` - The list is non-empty and every entry is 1-5 letters or digits
+/// * `Err(String)` - Error message naming the first bad entry
+pub fn validate_library_extensions(extensions: &[String]) -> Result<(), String> {
+    if extensions.is_empty() {
+        return Err("At least one library file extension is required".to_string());
+    }
+
+    for extension in extensions {
+        if extension.is_empty()
+            || extension.len() > 5
+            || !extension.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return Err(format!(
+                "Invalid library extension '{}'. Use 1-5 letters or digits without the dot, like 'mkv'",
+                extension
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Longest --sub-langs entry accepted; real codes and patterns are far shorter
+const MAX_SUBTITLE_LANG_LEN: usize = 32;
+
+/// Validates subtitle languages before they reach --sub-langs
+/// Only the shape is checked, since sites use codes no fixed list keeps up with
+/// ("iw", "zh-Hans", "en-orig"): letters, digits, '-', '.' and '*', so patterns like
+/// "en.*" work. Commas would split into extra entries and a leading '-' would read as
+/// an option, so both are rejected
+///
+/// # Arguments
+/// * `langs` - Language codes such as "en", "pt-BR" or "en.*"; "all" and "auto" are also allowed
+///
+/// # Returns
+/// * `Ok(())` - Every code is well-formed
+/// * `Err(String)` - Error message naming the first malformed code
+pub fn validate_subtitle_langs(langs: &[String]) -> Result<(), String> {
+    for lang in langs {
+        let well_formed = !lang.is_empty()
+            && lang.len() <= MAX_SUBTITLE_LANG_LEN
+            && !lang.starts_with('-')
+            && lang
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '*'));
+
+        if !well_formed {
+            return Err(format!(
+                "Invalid subtitle language '{}'. Use codes like 'en', 'pt-BR' or 'en.*', 'all' or 'auto'",
+                lang
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Validates a custom User-Agent header before it reaches --user-agent
+///
+/// # Arguments
+/// * `user_agent` - Header value such as "Mozilla/5.0 (Windows NT 10.0; Win64; x64) ..."
+///
+/// # Returns
+/// * `Ok(())` - The value is safe to pass
+/// * `Err(String)` - Error message naming the rejected character
+pub fn validate_user_agent(user_agent: &str) -> Result<(), String> {
+    if user_agent.trim().is_empty() || user_agent.len() > 512 {
+        return Err("User agent must be between 1 and 512 characters".to_string());
+    }
+
+    // Real user agents contain ';' and parentheses, so only the rest of the shell set is refused
+    if let Some(ch) = user_agent
+        .chars()
+        .find(|ch| ch.is_control() || "`$|&<>\\".contains(*ch))
+    {
+        return Err(format!("User agent contains {:?} which is not allowed", ch));
+    }
+
+    Ok(())
+}
+
+/// Validates a target for --impersonate
+///
+/// # Arguments
+/// * `target` - Client to imitate, e.g. "chrome", "chrome-116" or "safari:ios"
+///
+/// # Returns
+/// * `Ok(())` - The target is well-formed
+/// * `Err(String)` - Error message if it contains anything but letters, digits and "-._:"
+pub fn validate_impersonate(target: &str) -> Result<(), String> {
+    let valid = !target.is_empty()
+        && target.len() <= 64
+        && target
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || "-._:".contains(ch));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid impersonation target '{}'. Use a client like 'chrome' or 'safari:ios'",
+            target
+        ))
+    }
+}
+
+/// --geo-bypass value that lets yt-dlp choose the country itself
+pub const GEO_BYPASS_DEFAULT: &str = "default";
+
+/// Validates the country a download pretends to come from with --geo-bypass-country
+///
+/// # Arguments
+/// * `country` - Two-letter ISO 3166 code such as "US", or "default"
+///
+/// # Returns
+/// * `Ok(())` - The value is usable
+/// * `Err(String)` - Error message if it isn't two uppercase letters
+pub fn validate_geo_bypass(country: &str) -> Result<(), String> {
+    if country == GEO_BYPASS_DEFAULT
+        || (country.len() == 2 && country.chars().all(|c| c.is_ascii_uppercase()))
+    {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid geo bypass country '{}'. Use a two-letter code like 'US'",
+            country
+        ))
+    }
+}
+
+/// Parses a timestamp like "90", "1:30" or "01:02:03.5" into seconds
+///
+/// # Arguments
+/// * `timestamp` - Seconds, MM:SS or HH:MM:SS, with optional fractional seconds
+///
+/// # Returns
+/// * `Ok(f64)` - The timestamp in seconds
+/// * `Err(String)` - Error message if the timestamp is malformed
+pub fn parse_timestamp(timestamp: &str) -> Result<f64, String> {
+    let invalid = || {
+        format!(
+            "Invalid timestamp '{}'. Use seconds, MM:SS or HH:MM:SS",
+            timestamp
+        )
+    };
+
+    let parts: Vec<&str> = timestamp.trim().split(':').collect();
+    if parts.len() > 3 {
+        return Err(invalid());
+    }
+
+    let mut seconds = 0.0;
+    for (i, part) in parts.iter().enumerate() {
+        let is_last = i == parts.len() - 1;
+        // Only digits (and a single '.' in the seconds part) so "1e3" or "-5" can't slip in
+        let valid_chars = part
+            .chars()
+            .all(|ch| ch.is_ascii_digit() || (is_last && ch == '.'));
+        if part.is_empty() || !valid_chars {
+            return Err(invalid());
+        }
+
+        let value: f64 = part.parse().map_err(|_| invalid())?;
+        // Minutes and seconds after the leading field must stay below 60
+        if i > 0 && value >= 60.0 {
+            return Err(invalid());
+        }
+        seconds = seconds * 60.0 + value;
+    }
+
+    Ok(seconds)
+}
+
+/// Validates an optional start/end range for trimmed downloads
+///
+/// # Arguments
+/// * `start` - Where the section starts; None starts at the beginning
+/// * `end` - Where the section ends; None runs to the end
+///
+/// # Returns
+/// * `Ok(())` - Both timestamps are valid and start is before end
+/// * `Err(String)` - Error message if validation fails
+pub fn validate_time_range(start: Option<&str>, end: Option<&str>) -> Result<(), String> {
+    let start = start.map(parse_timestamp).transpose()?;
+    let end = end.map(parse_timestamp).transpose()?;
+
+    if let (Some(start), Some(end)) = (start, end) {
+        if start >= end {
+            return Err("Start time must be before end time".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// Classic Windows MAX_PATH limit, including the drive letter and terminator
+const WINDOWS_MAX_PATH: usize = 260;
+
+/// Whether a path is too long for Windows APIs without long path support
+/// The `\\?\` extended-length prefix added by canonicalize is not counted
+fn exceeds_windows_max_path(path_str: &str) -> bool {
+    let path_str = path_str.strip_prefix(r"\\?\").unwrap_or(path_str);
+    path_str.chars().count() >= WINDOWS_MAX_PATH
+}
+
+/// Fail early with guidance when a path exceeds MAX_PATH and long paths are disabled
+/// yt-dlp and ffmpeg otherwise fail later with a cryptic "cannot create file" error
+///
+/// # Arguments
+/// * `path_str` - The output file path
+///
+/// # Returns
+/// * `Ok(())` - Path length is fine (always on non-Windows platforms)
+/// * `Err(String)` - Explanation and suggested fixes
+pub fn check_windows_path_length(path_str: &str) -> Result<(), String> {
+    if !cfg!(windows) || !exceeds_windows_max_path(path_str) || windows_long_paths_enabled() {
+        return Ok(());
+    }
+
+    Err(format!(
+        "Output path is too long for Windows ({} characters, limit is {}). Choose a shorter download folder or enable long paths (LongPathsEnabled in the registry).",
+        path_str.chars().count(),
+        WINDOWS_MAX_PATH
+    ))
+}
+
+/// Read HKLM\SYSTEM\CurrentControlSet\Control\FileSystem\LongPathsEnabled
+#[cfg(windows)]
+fn windows_long_paths_enabled() -> bool {
+    std::process::Command::new("reg")
+        .args([
+            "query",
+            r"HKLM\SYSTEM\CurrentControlSet\Control\FileSystem",
+            "/v",
+            "LongPathsEnabled",
+        ])
+        .output()
+        .map(|output| {
+            output.status.success() && String::from_utf8_lossy(&output.stdout).contains("0x1")
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(not(windows))]
+fn windows_long_paths_enabled() -> bool {
+    true
 }
 
 #[cfg(test)]
@@ -270,6 +728,81 @@ mod tests {
         assert!(validate_url("https://example.com/$(command)").is_err());
     }
 
+    #[test]
+    fn test_validate_url_allows_path_punctuation() {
+        assert!(validate_url("https://example.com/Video_(2024)").is_ok());
+        assert!(validate_url("https://cdn.example.com/clips/[1080p]/clip!.mp4").is_ok());
+        assert!(validate_url("https://example.com/watch#t=30").is_ok());
+        assert!(validate_url("https://example.com/a|b").is_err());
+    }
+
+    #[test]
+    fn test_validate_subtitle_langs() {
+        let langs = |codes: &[&str]| codes.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        assert!(validate_subtitle_langs(&langs(&["en", "pt-BR", "zh-Hans", "auto"])).is_ok());
+        assert!(validate_subtitle_langs(&langs(&["all"])).is_ok());
+        assert!(validate_subtitle_langs(&langs(&["ga", "is", "az", "iw", "en.*"])).is_ok());
+        assert!(validate_subtitle_langs(&langs(&["-en"])).is_err());
+        assert!(validate_subtitle_langs(&langs(&[""])).is_err());
+        assert!(validate_subtitle_langs(&langs(&["en us"])).is_err());
+        assert!(validate_subtitle_langs(&langs(&["en,--exec"])).is_err());
+    }
+
+    #[test]
+    fn test_sanitize_filename() {
+        assert_eq!(sanitize_filename("AC/DC: Live?", false), "AC_DC: Live?");
+        assert_eq!(sanitize_filename("AC/DC: Live?", true), "AC_DC_ Live_");
+        assert_eq!(
+            sanitize_filename("Ends with dots... ", true),
+            "Ends with dots"
+        );
+        assert_eq!(sanitize_filename("con.mp4", true), "_con.mp4");
+        assert_eq!(sanitize_filename("con.mp4", false), "con.mp4");
+        assert_eq!(sanitize_filename("..", false), "download");
+        assert_eq!(sanitize_filename("line\nbreak", false), "line_break");
+
+        let long = "é".repeat(200);
+        let sanitized = sanitize_filename(&long, false);
+        assert!(sanitized.len() <= 240);
+        assert!(sanitized.chars().all(|c| c == 'é'));
+    }
+
+    #[test]
+    fn test_validate_library_extensions() {
+        let extensions = |exts: &[&str]| exts.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert!(validate_library_extensions(&extensions(&["mp4", "MKV", "m4a"])).is_ok());
+        assert!(validate_library_extensions(&extensions(&[])).is_err());
+        assert!(validate_library_extensions(&extensions(&[".mp4"])).is_err());
+        assert!(validate_library_extensions(&extensions(&["mpeg-4"])).is_err());
+        assert!(validate_library_extensions(&extensions(&["../x"])).is_err());
+    }
+
+    #[test]
+    fn test_validate_user_agent_and_impersonate() {
+        assert!(validate_user_agent(
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko)"
+        )
+        .is_ok());
+        assert!(validate_user_agent("curl/8.0 $(whoami)").is_err());
+        assert!(validate_user_agent("agent\nX-Header: injected").is_err());
+        assert!(validate_user_agent("   ").is_err());
+
+        assert!(validate_impersonate("chrome").is_ok());
+        assert!(validate_impersonate("safari:ios").is_ok());
+        assert!(validate_impersonate("chrome-116").is_ok());
+        assert!(validate_impersonate("chrome;ls").is_err());
+        assert!(validate_impersonate("").is_err());
+    }
+
+    #[test]
+    fn test_validate_geo_bypass() {
+        assert!(validate_geo_bypass("US").is_ok());
+        assert!(validate_geo_bypass(GEO_BYPASS_DEFAULT).is_ok());
+        assert!(validate_geo_bypass("us").is_err());
+        assert!(validate_geo_bypass("USA").is_err());
+        assert!(validate_geo_bypass("É").is_err());
+    }
+
     #[test]
     fn test_validate_url_empty() {
         assert!(validate_url("").is_err());
@@ -278,12 +811,92 @@ mod tests {
 
     #[test]
     fn test_validate_path_traversal() {
-        assert!(validate_path("../../../etc/passwd", false).is_err());
-        assert!(validate_path("/home/user/../../etc/passwd", false).is_err());
+        assert!(validate_path("../../../etc/passwd", false, &[]).is_err());
+        assert!(validate_path("/home/user/../../etc/passwd", false, &[]).is_err());
     }
 
     #[test]
     fn test_validate_path_null_bytes() {
-        assert!(validate_path("/home/user/file\0.txt", false).is_err());
+        assert!(validate_path("/home/user/file\0.txt", false, &[]).is_err());
+    }
+
+    #[test]
+    fn test_extra_allowed_keeps_system_directories_blocked() {
+        let allowed = [PathBuf::from("/etc")];
+        assert!(validate_path("/etc/hosts", false, &allowed).is_err());
+    }
+
+    #[test]
+    fn test_exceeds_windows_max_path() {
+        let short = r"C:\Users\user\Videos\ripVID\MP4\video.mp4";
+        assert!(!exceeds_windows_max_path(short));
+
+        let long = format!(r"C:\Users\user\Videos\{}.mp4", "a".repeat(250));
+        assert!(exceeds_windows_max_path(&long));
+
+        // The extended-length prefix doesn't count towards the limit
+        let prefixed = format!(r"\\?\C:\{}.mp4", "a".repeat(245));
+        assert!(!exceeds_windows_max_path(&prefixed));
+    }
+
+    #[test]
+    fn test_validate_extra_args_allows_plain_options() {
+        let args = vec![
+            "--sponsorblock-remove".to_string(),
+            "sponsor,intro".to_string(),
+            "--limit-rate=2M".to_string(),
+            "-N".to_string(),
+            "4".to_string(),
+        ];
+        assert!(validate_extra_args(&args).is_ok());
+    }
+
+    #[test]
+    fn test_validate_extra_args_rejects_output_and_exec() {
+        assert!(validate_extra_args(&["-o".to_string(), "x.mp4".to_string()]).is_err());
+        assert!(validate_extra_args(&["--output=/tmp/x".to_string()]).is_err());
+        assert!(validate_extra_args(&["-o/tmp/x".to_string()]).is_err());
+        assert!(validate_extra_args(&["--exec".to_string(), "rm".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_validate_extra_args_rejects_postprocessors_and_aliases() {
+        let rejected = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+            validate_extra_args(&args).is_err()
+        };
+        assert!(rejected(&["--use-postprocessor", "Exec:exec_cmd=/tmp/x"]));
+        assert!(rejected(&["--use-postprocessor=Exec:exec_cmd=/tmp/x"]));
+        assert!(rejected(&["--alias", "get-it", "--exec=/tmp/x"]));
+        assert!(rejected(&["--postprocessor-args", "ffmpeg:-y"]));
+        assert!(rejected(&["--ppa", "ffmpeg:-y"]));
+        // Options yt-dlp adds later are rejected until they're reviewed
+        assert!(rejected(&["--some-future-option"]));
+    }
+
+    #[test]
+    fn test_validate_extra_args_rejects_shell_metacharacters() {
+        assert!(validate_extra_args(&["--format=best;rm".to_string()]).is_err());
+        assert!(validate_extra_args(&["$(whoami)".to_string()]).is_err());
+        assert!(validate_extra_args(&["a b".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("90"), Ok(90.0));
+        assert_eq!(parse_timestamp("1:30"), Ok(90.0));
+        assert_eq!(parse_timestamp("01:02:03.5"), Ok(3723.5));
+        assert!(parse_timestamp("1:75").is_err());
+        assert!(parse_timestamp("1::3").is_err());
+        assert!(parse_timestamp("-5").is_err());
+        assert!(parse_timestamp("1:2:3:4").is_err());
+    }
+
+    #[test]
+    fn test_validate_time_range() {
+        assert!(validate_time_range(Some("0:30"), Some("3:45")).is_ok());
+        assert!(validate_time_range(Some("0:30"), None).is_ok());
+        assert!(validate_time_range(Some("3:45"), Some("0:30")).is_err());
+        assert!(validate_time_range(Some("1:00"), Some("60")).is_err());
     }
 }
@@ -1,3 +1,6 @@
+use crate::binary_manager::write_binary_atomically;
+use crate::dates::days_from_civil;
+use crate::network::{http_client, record_github_ratelimit};
 use hex;
 use reqwest;
 use serde::{Deserialize, Serialize};
@@ -26,6 +29,10 @@ struct YtdlpVersion {
     path: String,
 }
 
+/// Held while a yt-dlp update runs, by YtdlpUpdater and BinaryManager alike,
+/// so two background checks never download and replace yt-dlp at the same time
+pub static YTDLP_UPDATE_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
 #[derive(Clone)]
 pub struct YtdlpUpdater {
     app_handle: AppHandle,
@@ -52,6 +59,12 @@ impl YtdlpUpdater {
             return self.get_ytdlp_path();
         }
 
+        // Every download calls this, so skip the check while one is already running
+        if YTDLP_UPDATE_LOCK.try_lock().is_err() {
+            tracing::debug!("yt-dlp update already in progress, skipping check");
+            return self.get_ytdlp_path();
+        }
+
         // Check for updates in the background
         let updater = self.clone();
         tauri::async_runtime::spawn(async move {
@@ -64,6 +77,12 @@ impl YtdlpUpdater {
         self.get_ytdlp_path()
     }
 
+    /// Check for a new yt-dlp release now, ignoring the once-per-day limit
+    /// Returns true if a newer version was installed
+    pub async fn force_update(&self) -> Result<bool, String> {
+        self.check_and_update().await
+    }
+
     pub fn clone_for_background(&self) -> Self {
         self.clone()
     }
@@ -89,7 +108,9 @@ impl YtdlpUpdater {
         Ok(now - version_info.last_check > 86400)
     }
 
-    async fn check_and_update(&self) -> Result<(), String> {
+    async fn check_and_update(&self) -> Result<bool, String> {
+        // Waits for an update already in flight; it then usually finds the new version
+        let _update = YTDLP_UPDATE_LOCK.lock().await;
         tracing::info!("Checking for yt-dlp updates...");
 
         // Ensure data directory exists
@@ -97,15 +118,8 @@ impl YtdlpUpdater {
             .map_err(|e| format!("Failed to create data directory: {}", e))?;
 
         // Get latest release info
-        let client = reqwest::Client::new();
-        let response = client
-            .get("https://api.github.com/repos/yt-dlp/yt-dlp/releases/latest")
-            .header("User-Agent", "ripVID")
-            .send()
-            .await
-            .map_err(|e| e.to_string())?;
-
-        let release: GitHubRelease = response.json().await.map_err(|e| e.to_string())?;
+        let client = http_client();
+        let release = Self::fetch_latest_release(&client).await?;
 
         // Check if we need to update
         let version_file = self.data_dir.join("ytdlp-version.json");
@@ -122,7 +136,7 @@ impl YtdlpUpdater {
 
             // Update last check time
             self.save_version_info(&release.tag_name)?;
-            return Ok(());
+            return Ok(false);
         }
 
         // Find the right asset for the platform
@@ -187,7 +201,7 @@ impl YtdlpUpdater {
         }
 
         // Save the verified binary
-        if let Err(e) = fs::write(&ytdlp_path, bytes) {
+        if let Err(e) = write_binary_atomically(&ytdlp_path, &bytes) {
             // Rollback on failure
             tracing::error!("Failed to write new yt-dlp binary: {}", e);
             if backup_path.exists() {
@@ -224,7 +238,25 @@ impl YtdlpUpdater {
         self.save_version_info(&release.tag_name)?;
 
         tracing::info!("Successfully updated yt-dlp to {}", release.tag_name);
-        Ok(())
+        Ok(true)
+    }
+
+    /// Tag of the newest yt-dlp release on GitHub, e.g. "2024.08.06"
+    pub async fn latest_version(&self) -> Result<String, String> {
+        let release = Self::fetch_latest_release(&http_client()).await?;
+        Ok(release.tag_name)
+    }
+
+    async fn fetch_latest_release(client: &reqwest::Client) -> Result<GitHubRelease, String> {
+        let response = client
+            .get("https://api.github.com/repos/yt-dlp/yt-dlp/releases/latest")
+            .header("User-Agent", "ripVID")
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        record_github_ratelimit(response.headers());
+
+        response.json().await.map_err(|e| e.to_string())
     }
 
     fn save_version_info(&self, version: &str) -> Result<(), String> {
@@ -349,3 +381,40 @@ impl YtdlpUpdater {
         ))
     }
 }
+
+/// Days between two yt-dlp versions, which are release dates like "2024.08.06"
+/// Nightly builds add a time ("2024.08.06.232000"), which is ignored.
+/// None when either version isn't date-shaped; 0 when `current` is newer
+pub fn version_days_behind(current: &str, latest: &str) -> Option<u32> {
+    let behind = release_day(latest)? - release_day(current)?;
+    Some(behind.max(0) as u32)
+}
+
+/// Days since 1970-01-01 of a "YYYY.MM.DD" version
+fn release_day(version: &str) -> Option<i64> {
+    let mut parts = version.trim().split('.');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: u32 = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some(days_from_civil(year, month, day))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_days_behind() {
+        assert_eq!(version_days_behind("2024.01.01", "2024.03.01"), Some(60));
+        assert_eq!(
+            version_days_behind("2024.08.06.232000", "2024.08.06"),
+            Some(0)
+        );
+        assert_eq!(version_days_behind("2025.01.01", "2024.12.01"), Some(0));
+        assert_eq!(version_days_behind("unknown", "2024.12.01"), None);
+        assert_eq!(release_day("1970.01.01"), Some(0));
+    }
+}